@@ -1,3 +1,381 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use ciborium::value::Value;
+use serde::Deserialize;
+
+const SCHEMA_LOCK: &str = "schema/schema.lock";
+const GRAPHQL_DIR: &str = "graphql";
+// Node schema without the application schemas, which are generated from the lock
+const BASE_SCHEMA: &str = "graphql/schema.graphql";
+const GENERATED_DIR: &str = "graphql/generated";
+
+const SCHEMA_DEFINITION: &str = "schema_definition_v1";
+const SCHEMA_FIELD_DEFINITION: &str = "schema_field_definition_v1";
+
+const QUERY_ANCHOR: &str = "    # Query a schema_definition document by id or view id.\n";
+const TYPE_ANCHOR: &str =
+    "# Publish data schemas for your application.\ntype schema_definition_v1 {\n";
+
+#[derive(Deserialize)]
+struct LockFile {
+    commits: Vec<Commit>,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    entry_hash: String,
+    operation: String,
+}
+
+/// One version of an application schema in the lock file, every update of the schema
+/// definition is a new version with its own schema id
+#[derive(Clone)]
+struct SchemaDef {
+    name: String,
+    // starts from 1
+    version: u32,
+    description: String,
+    view_id: String,
+    // view ids of the field definitions
+    fields: Vec<String>,
+}
+
+impl SchemaDef {
+    fn schema_id(&self) -> String {
+        format!("{}_{}", self.name, self.view_id)
+    }
+
+    fn is_latest(&self, schemas: &[SchemaDef]) -> bool {
+        !schemas
+            .iter()
+            .any(|s| s.name.eq(&self.name) && s.version > self.version)
+    }
+}
+
 fn main() {
+    if let Err(err) = generate() {
+        panic!("generate schema registry error: {}", err);
+    }
     tauri_build::build()
 }
+
+/// Derive the schema ids from the lock file, write the registry for `p2panda::schema_registry`
+/// and render the query documents used by `crate::graphql`
+fn generate() -> Result<(), String> {
+    println!("cargo:rerun-if-changed={}", SCHEMA_LOCK);
+    println!("cargo:rerun-if-changed={}", GRAPHQL_DIR);
+
+    let lock = fs::read_to_string(SCHEMA_LOCK).map_err(|e| e.to_string())?;
+    let lock: LockFile = toml::from_str(&lock).map_err(|e| e.to_string())?;
+    let (schemas, field_defs) = read_schemas(&lock)?;
+
+    let mut registry = String::from("// Generated by build.rs from schema/schema.lock\n");
+    registry.push_str("pub const SCHEMA_IDS: &[(&str, u32, &str)] = &[\n");
+    for schema in schemas.iter() {
+        registry.push_str(&format!(
+            "    ({:?}, {}, {:?}),\n",
+            schema.name,
+            schema.version,
+            schema.schema_id()
+        ));
+    }
+    registry.push_str("];\n");
+    // 空间的 schema 复用锁文件中的字段定义，按 schema id 列出字段定义的 view id
+    registry.push_str("pub const SCHEMA_FIELDS: &[(&str, &[&str])] = &[\n");
+    for schema in schemas.iter() {
+        registry.push_str(&format!(
+            "    ({:?}, &{:?}),\n",
+            schema.schema_id(),
+            schema.fields
+        ));
+    }
+    registry.push_str("];\n");
+    let out_dir = env::var("OUT_DIR").map_err(|e| e.to_string())?;
+    write_if_changed(&Path::new(&out_dir).join("schema_registry.rs"), &registry)?;
+
+    fs::create_dir_all(GENERATED_DIR).map_err(|e| e.to_string())?;
+    let mut node_schema = fs::read_to_string(BASE_SCHEMA).map_err(|e| e.to_string())?;
+    for schema in schemas.iter() {
+        let mut fields = vec![];
+        for view_id in schema.fields.iter() {
+            let field = field_defs
+                .get(view_id)
+                .ok_or(format!("unknown field {} of {}", view_id, schema.name))?;
+            fields.push(field.clone());
+        }
+        let (query, types) = graphql_blocks(schema, fields)?;
+        node_schema = insert_before(&node_schema, QUERY_ANCHOR, &query)?;
+        node_schema = insert_before(&node_schema, TYPE_ANCHOR, &types)?;
+    }
+    write_if_changed(
+        &Path::new(GENERATED_DIR).join("schema.graphql"),
+        &node_schema,
+    )?;
+
+    // 查询文档中的 {{record}} 占位符替换为最新版本的 schema id，{{record@1}} 为指定版本
+    let entries = fs::read_dir(GRAPHQL_DIR).map_err(|e| e.to_string())?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !file_name.ends_with(".graphql") || path.eq(Path::new(BASE_SCHEMA)) {
+            continue;
+        }
+        let mut query = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        for schema in schemas.iter() {
+            let id = schema.schema_id();
+            let placeholder = format!("{{{{{}@{}}}}}", schema.name, schema.version);
+            query = query.replace(&placeholder, &id);
+            if schema.is_latest(&schemas) {
+                query = query.replace(&format!("{{{{{}}}}}", schema.name), &id);
+            }
+        }
+        if let Some(pos) = query.find("{{") {
+            let placeholder = query[pos..].lines().next().unwrap_or_default();
+            return Err(format!("unknown schema in {}: {}", file_name, placeholder));
+        }
+        write_if_changed(&Path::new(GENERATED_DIR).join(&*file_name), &query)?;
+    }
+    Ok(())
+}
+
+/// Replay the schema operations of the lock file, return all the schema versions in creation
+/// order and the `(name, type)` of the field definitions by view id
+#[allow(clippy::type_complexity)]
+fn read_schemas(
+    lock: &LockFile,
+) -> Result<(Vec<SchemaDef>, HashMap<String, (String, String)>), String> {
+    let mut schemas: Vec<SchemaDef> = vec![];
+    let mut field_defs = HashMap::new();
+
+    for commit in lock.commits.iter() {
+        let bytes = hex::decode(&commit.operation).map_err(|e| e.to_string())?;
+        let operation: Value = ciborium::de::from_reader(bytes.as_slice())
+            .map_err(|e| format!("invalid operation {}: {}", commit.entry_hash, e))?;
+        // [version, action, schema_id, previous?, fields?]
+        let items = operation.as_array().ok_or("operation is not an array")?;
+        let action = items
+            .get(1)
+            .and_then(|v| v.as_integer())
+            .map(i128::from)
+            .ok_or("missing operation action")?;
+        let schema_id = items.get(2).and_then(|v| v.as_text()).unwrap_or_default();
+        let (previous, fields) = match action {
+            0 => (None, items.get(3)),
+            1 => (items.get(3).and_then(|v| v.as_array()), items.get(4)),
+            _ => return Err(format!("unsupported operation {}", commit.entry_hash)),
+        };
+        let fields = fields.and_then(|v| v.as_map()).ok_or("missing fields")?;
+        let text = |name: &str| {
+            field(fields, name)
+                .and_then(|v| v.as_text())
+                .map(|s| s.to_string())
+        };
+
+        match schema_id {
+            SCHEMA_FIELD_DEFINITION => {
+                let (name, field_type) = text("name")
+                    .zip(text("type"))
+                    .ok_or(format!("invalid field definition {}", commit.entry_hash))?;
+                field_defs.insert(commit.entry_hash.clone(), (name, field_type));
+            }
+            SCHEMA_DEFINITION => {
+                let view_ids = field(fields, "fields").map(pinned_relations).transpose()?;
+                match previous {
+                    None => schemas.push(SchemaDef {
+                        name: text("name").ok_or("schema without name")?,
+                        version: 1,
+                        description: text("description").unwrap_or_default(),
+                        view_id: commit.entry_hash.clone(),
+                        fields: view_ids.unwrap_or_default(),
+                    }),
+                    Some(previous) => {
+                        let previous: Vec<&str> =
+                            previous.iter().filter_map(|v| v.as_text()).collect();
+                        let mut schema = schemas
+                            .iter()
+                            .find(|s| previous.contains(&s.view_id.as_str()))
+                            .cloned()
+                            .ok_or(format!("unknown schema update {}", commit.entry_hash))?;
+                        schema.version += 1;
+                        schema.view_id = commit.entry_hash.clone();
+                        if let Some(description) = text("description") {
+                            schema.description = description;
+                        }
+                        if let Some(view_ids) = view_ids {
+                            schema.fields = view_ids;
+                        }
+                        schemas.push(schema);
+                    }
+                }
+            }
+            _ => return Err(format!("unexpected schema {} in lock file", schema_id)),
+        }
+    }
+    Ok((schemas, field_defs))
+}
+
+fn field<'a>(fields: &'a [(Value, Value)], name: &str) -> Option<&'a Value> {
+    fields
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(name))
+        .map(|(_, v)| v)
+}
+
+/// Pinned relation list, every field is pinned to a single operation
+fn pinned_relations(value: &Value) -> Result<Vec<String>, String> {
+    let relations = value.as_array().ok_or("fields is not a relation list")?;
+    relations
+        .iter()
+        .map(|relation| {
+            relation
+                .as_array()
+                .and_then(|ids| ids.first())
+                .and_then(|id| id.as_text())
+                .map(|id| id.to_string())
+                .ok_or("invalid pinned relation".to_string())
+        })
+        .collect()
+}
+
+fn insert_before(text: &str, anchor: &str, block: &str) -> Result<String, String> {
+    if text.matches(anchor).count() != 1 {
+        return Err(format!(
+            "anchor not found in {}: {}",
+            BASE_SCHEMA,
+            anchor.trim()
+        ));
+    }
+    Ok(text.replacen(anchor, &format!("{}{}", block, anchor), 1))
+}
+
+fn write_if_changed(path: &Path, content: &str) -> Result<(), String> {
+    if fs::read_to_string(path).ok().as_deref() == Some(content) {
+        return Ok(());
+    }
+    fs::write(path, content).map_err(|e| format!("write {:?} error: {}", path, e))
+}
+
+/// Query fields and types which aquadoggo generates for the schema
+fn graphql_blocks(
+    schema: &SchemaDef,
+    mut fields: Vec<(String, String)>,
+) -> Result<(String, String), String> {
+    fields.sort();
+    let (name, id) = (&schema.name, schema.schema_id());
+
+    let mut field_docs = vec![];
+    let mut filters = vec![];
+    let mut orders = vec![];
+    for (field, field_type) in fields.iter() {
+        let (graphql_type, filter) = match field_type.as_str() {
+            "str" => ("String", "StringFilter"),
+            "int" => ("Int", "IntegerFilter"),
+            "float" => ("Float", "FloatFilter"),
+            "bool" => ("Boolean", "BooleanFilter"),
+            _ => return Err(format!("unsupported field type {} of {}", field_type, name)),
+        };
+        field_docs.push(format!(
+            "    # The `{field}` field of a {name} document.\n    {field}: {graphql_type}"
+        ));
+        filters.push(format!("    {field}: {filter}"));
+        orders.push(format!("    {field}"));
+    }
+    let (field_docs, filters, orders) = (
+        field_docs.join("\n\n"),
+        filters.join("\n"),
+        orders.join("\n"),
+    );
+    let description = &schema.description;
+
+    let query = format!(
+        r#"    # Query a {name} document by id or view id.
+    {id}(
+        # Specify the id of the document to be retrieved
+        id: DocumentId
+
+        # Specify the view id of the document to be retrieved
+        viewId: DocumentViewId
+    ): {id}
+
+    # Get all {id} documents with pagination, ordering and filtering.
+    all_{id}(
+        # Filter the query based on field values
+        filter: {id}Filter
+
+        # Filter the query based on meta field values
+        meta: MetaFilterInputObject
+
+        # Field by which items in the collection will be ordered
+        orderBy: {id}OrderBy
+
+        # Direction which items in the collection will be ordered
+        orderDirection: OrderDirection
+
+        # Number of paginated items we want from this request
+        first: Int = 25
+
+        # The item we wish to start paginating from identified by a cursor
+        after: Cursor
+    ): {id}Collection!
+
+"#
+    );
+    let types = format!(
+        r#"# {description}
+type {id} {{
+    # Application fields of a `{name}` document.
+    fields: {id}Fields
+
+    # Meta fields of a `{name}` document.
+    meta: DocumentMeta
+}}
+
+# A single page response returned when querying a collection of `{name}` documents.
+type {id}Collection {{
+    # The total number of documents available in this paginated collection.
+    totalCount: Int!
+
+    # Cursor for the next page
+    endCursor: String!
+
+    # Boolean value denoting whether there is a next page available on this query.
+    hasNextPage: Boolean!
+
+    # Field containing the actual document fields.
+    documents: [{id}Item!]!
+}}
+
+# The application fields of a `{name}` document.
+type {id}Fields {{
+{field_docs}
+}}
+
+input {id}Filter {{
+{filters}
+}}
+
+# {description}
+type {id}Item {{
+    # Application fields of a `{name}` document.
+    fields: {id}Fields
+
+    # Meta fields of a `{name}` document.
+    meta: DocumentMeta
+
+    # The pagination cursor for this `{name}` document.
+    cursor: String
+}}
+
+enum {id}OrderBy {{
+    DOCUMENT_ID
+    DOCUMENT_VIEW_ID
+{orders}
+}}
+
+"#
+    );
+    Ok((query, types))
+}
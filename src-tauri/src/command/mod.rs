@@ -1,2 +1,8 @@
 pub mod config;
+pub mod pairing;
+pub mod paste_queue;
 pub mod record;
+pub mod register;
+pub mod snippet;
+pub mod space;
+pub mod storage;
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use log::error;
+
+use crate::command::space::rotate_space;
+use crate::dao::paired_device_dao::PairedDeviceDao;
+use crate::handler::pairing::PairingHandler;
+use crate::listener::maintenance::MaintenanceListener;
+use crate::models::pairing::{PairedDevice, PairingRequest};
+use crate::p2panda::node::NodeServer;
+
+/// Return the invite to show as a QR code, the code to compare comes with the
+/// `PairingRequestBackend` message once a device joined
+#[tauri::command]
+pub async fn start_pairing(device_name: String) -> Result<String, String> {
+    match PairingHandler::start(device_name).await {
+        Ok(invite) => Ok(invite.encode()),
+        Err(err) => {
+            let err_msg = format!("start pairing error: {:?}", err);
+            error!("call start_pairing error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn join_pairing(invite: String, device_name: String) -> Result<PairingRequest, String> {
+    match PairingHandler::join(&invite, device_name).await {
+        Ok(request) => Ok(request),
+        Err(err) => {
+            let err_msg = format!("join pairing error: {:?}", err);
+            error!("call join_pairing error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Accept or reject after comparing the codes, the result comes with the
+/// `PairingResultBackend` message
+#[tauri::command]
+pub fn confirm_pairing(accepted: bool) -> Result<(), String> {
+    PairingHandler::confirm(accepted).map_err(|err| {
+        error!("call confirm_pairing error: {:?}", err);
+        format!("confirm pairing error: {:?}", err)
+    })
+}
+
+#[tauri::command]
+pub fn cancel_pairing() -> Result<(), String> {
+    PairingHandler::cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_paired_devices() -> Result<Vec<PairedDevice>, String> {
+    PairedDeviceDao::list().map_err(|err| {
+        error!("call list_paired_devices error: {:?}", err);
+        format!("list paired devices error: {:?}", err)
+    })
+}
+
+#[tauri::command]
+pub fn rename_paired_device(device_id: String, name: String) -> Result<(), String> {
+    PairedDeviceDao::rename(&device_id, name).map_err(|err| {
+        error!("call rename_paired_device error: {:?}", err);
+        format!("rename paired device error: {:?}", err)
+    })
+}
+
+/// The spaces shared with the device get new keys and schemas, as the device and the other
+/// members know the old ones, see `remove_space_member`. The node stops syncing with the
+/// device once restarted, the records it already has stay readable for it
+#[tauri::command]
+pub async fn revoke_paired_device(device_id: String) -> Result<(), String> {
+    revoke(&device_id).await.map_err(|err| {
+        error!("call revoke_paired_device error: {:?}", err);
+        format!("revoke paired device error: {:?}", err)
+    })
+}
+
+async fn revoke(device_id: &str) -> Result<()> {
+    let device = PairedDeviceDao::list()?
+        .into_iter()
+        .find(|d| d.device_id.eq(device_id))
+        .ok_or(anyhow!("paired device not found: {}", device_id))?;
+    for space_id in device.spaces.iter() {
+        rotate_space(space_id).await?;
+    }
+    // Removed with its last space, unless it shared none
+    if PairedDeviceDao::list()?
+        .iter()
+        .any(|d| d.device_id.eq(device_id))
+    {
+        PairedDeviceDao::remove(device_id)?;
+    }
+    NodeServer::restart().await?;
+    MaintenanceListener::trigger();
+    Ok(())
+}
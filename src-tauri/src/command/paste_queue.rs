@@ -0,0 +1,29 @@
+use log::error;
+
+use crate::handler::paste_queue::PasteQueueHandler;
+use crate::models::paste_queue::{PasteQueueMode, PasteQueueState};
+
+#[tauri::command]
+pub fn start_paste_queue(mode: PasteQueueMode) -> Result<PasteQueueState, String> {
+    Ok(PasteQueueHandler::start(mode))
+}
+
+#[tauri::command]
+pub fn stop_paste_queue() -> Result<PasteQueueState, String> {
+    Ok(PasteQueueHandler::stop())
+}
+
+#[tauri::command]
+pub fn paste_queue_state() -> Result<PasteQueueState, String> {
+    Ok(PasteQueueHandler::state())
+}
+
+#[tauri::command]
+pub fn paste_next() -> Result<PasteQueueState, String> {
+    if let Err(err) = PasteQueueHandler::paste_next() {
+        let err_msg = format!("paste next item error: {:?}", err);
+        error!("call paste_next error: {:?}", err);
+        return Err(err_msg);
+    }
+    Ok(PasteQueueHandler::state())
+}
@@ -3,11 +3,14 @@ use p2panda_rs::document::DocumentViewId;
 use p2panda_rs::operation::{OperationId, OperationValue};
 use std::str::FromStr;
 
+use crate::config::app_config::AppConfig;
 use crate::dao::record_dao::RecordDao;
 use crate::handler::global_handler::GlobalHandler;
 use crate::handler::model::MessageTypeEnum;
+use crate::models::batch::{BatchItemResult, MergeResult};
 use crate::models::image_data::ImageData;
-use crate::models::record::DataTypeEnum;
+use crate::models::record::{DataTypeEnum, RecordVersion};
+use crate::models::record_list::{RecordContent, RecordFilter, RecordPage, RecordSort};
 use crate::utils::clipboard::ClipBoardOperator;
 use crate::utils::ip::local_ip;
 use crate::utils::json;
@@ -43,30 +46,72 @@ pub async fn tap_change_clipboard(content: String, data_type: String) -> Result<
     Ok(())
 }
 
+// Page size used when `limit` is not given
+const DEFAULT_PAGE_SIZE: i64 = 20;
+
+#[tauri::command]
+pub async fn list_records(
+    filter: Option<RecordFilter>,
+    sort: Option<RecordSort>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+) -> Result<RecordPage, String> {
+    match record_dao()?
+        .list_records(
+            filter.unwrap_or_default(),
+            sort.unwrap_or_default(),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            cursor,
+        )
+        .await
+    {
+        Ok(page) => Ok(page),
+        Err(err) => {
+            let err_msg = format!("list records error: {:?}", err);
+            error!("call list_records error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_record_content(view_id: String) -> Result<RecordContent, String> {
+    match record_dao()?.get_record_content(view_id).await {
+        Ok(content) => Ok(content),
+        Err(err) => {
+            let err_msg = format!("load record content error: {:?}", err);
+            error!("call get_record_content error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn delete_record(view_id: String) -> Result<(), String> {
     debug!("got view_id {:?}", view_id);
 
-    let document_views = match OperationId::from_str(&view_id) {
-        Ok(res) => DocumentViewId::from(res),
+    let document_views = match DocumentViewId::from_str(&view_id) {
+        Ok(res) => res,
         Err(err) => {
             let err_msg = format!("parse document view id error: {:?}", err);
             error!(
-                "call OperationId::from_str in delete_record error: {:?}",
+                "call DocumentViewId::from_str in delete_record error: {:?}",
                 err
             );
             return Err(err_msg);
         }
     };
 
-    let delete_res = RecordDao::delete_record(&document_views).await;
-    if delete_res.is_err() {
-        error!("call delete instance error: {:?}", delete_res.unwrap_err())
+    // Move the record to trash instead of deleting it on every synced device
+    if let Err(err) = record_dao()?.trash_record(&document_views).await {
+        let err_msg = format!("move record to trash error: {:?}", err);
+        error!("call trash_record error: {:?}", err);
+        return Err(err_msg);
     }
 
     if let Err(e) = GlobalHandler::push_message_to_window(
         MessageTypeEnum::DeleteClipboardRecordBackend,
-        format!("delete view_id: {} success", view_id),
+        format!("move view_id: {} to trash success", view_id),
     ) {
         error!("send DeleteClipboardRecordBackend message err: {:?}", e)
     };
@@ -74,6 +119,59 @@ pub async fn delete_record(view_id: String) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn restore_record(view_id: String) -> Result<(), String> {
+    debug!("got view_id {:?}", view_id);
+
+    let document_views = match DocumentViewId::from_str(&view_id) {
+        Ok(res) => res,
+        Err(err) => {
+            let err_msg = format!("parse document view id error: {:?}", err);
+            error!(
+                "call DocumentViewId::from_str in restore_record error: {:?}",
+                err
+            );
+            return Err(err_msg);
+        }
+    };
+
+    if let Err(err) = record_dao()?.restore_record(&document_views).await {
+        let err_msg = format!("restore record error: {:?}", err);
+        error!("call restore_record error: {:?}", err);
+        return Err(err_msg);
+    }
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::UpdateClipboardRecordBackend,
+        format!("restore view_id: {} from trash success", view_id),
+    ) {
+        error!("send UpdateClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn empty_trash() -> Result<usize, String> {
+    let purged_cnt = match record_dao()?.empty_trash().await {
+        Ok(cnt) => cnt,
+        Err(err) => {
+            let err_msg = format!("empty trash error: {:?}", err);
+            error!("call empty_trash error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::DeleteClipboardRecordBackend,
+        format!("empty trash success, {} records purged", purged_cnt),
+    ) {
+        error!("send DeleteClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(purged_cnt)
+}
+
 #[tauri::command]
 pub async fn toggle_favorite_record(view_id: String, old_favorite: i32) -> Result<(), String> {
     let favorite = if old_favorite.ne(&0) { 0 } else { 1 };
@@ -87,23 +185,25 @@ pub async fn toggle_favorite_record(view_id: String, old_favorite: i32) -> Resul
         }
     };
 
-    match RecordDao::update_record_with_fields(
-        &document_views,
-        &[
-            ("is_favorite", OperationValue::Integer(favorite)),
-            (
-                "latest_addr",
-                OperationValue::String(local_ip().to_string()),
-            ),
-        ],
-    )
-    .await
+    match record_dao()?
+        .update_record_with_fields(
+            &document_views,
+            &[
+                ("is_favorite", OperationValue::Integer(favorite)),
+                (
+                    "latest_addr",
+                    OperationValue::String(local_ip().to_string()),
+                ),
+            ],
+        )
+        .await
     {
         Ok(opt_id) => {
             info!(
                 "call update_record_with_fields successfully: opt_id={}",
                 opt_id
-            )
+            );
+            follow_favorite(&opt_id, favorite != 0).await;
         }
         Err(err) => {
             let err_msg = format!("call delete instance error: {:?}", err);
@@ -121,3 +221,246 @@ pub async fn toggle_favorite_record(view_id: String, old_favorite: i32) -> Resul
 
     Ok(())
 }
+
+#[tauri::command]
+pub async fn edit_record(view_id: String, content: String) -> Result<String, String> {
+    let document_views = parse_view_id(&view_id)?;
+
+    let new_view_id = match record_dao()?.edit_record(&document_views, content).await {
+        Ok(res) => res,
+        Err(err) => {
+            let err_msg = format!("edit record error: {:?}", err);
+            error!("call edit_record error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::UpdateClipboardRecordBackend,
+        format!("edit record success, view_id: {}", new_view_id),
+    ) {
+        error!("send UpdateClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(new_view_id)
+}
+
+#[tauri::command]
+pub async fn record_history(view_id: String) -> Result<Vec<RecordVersion>, String> {
+    let document_views = parse_view_id(&view_id)?;
+
+    match record_dao()?.record_history(&document_views).await {
+        Ok(versions) => Ok(versions),
+        Err(err) => {
+            let err_msg = format!("load record history error: {:?}", err);
+            error!("call record_history error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Restore the content of an earlier version, the current content is kept in the history
+#[tauri::command]
+pub async fn revert_record(view_id: String, version_view_id: String) -> Result<String, String> {
+    let versions = record_history(view_id.clone()).await?;
+    let version = versions
+        .into_iter()
+        .find(|v| v.view_id.eq(&version_view_id))
+        .ok_or_else(|| format!("version {} not found in record history", version_view_id))?;
+
+    edit_record(view_id, version.content).await
+}
+
+pub(crate) fn record_dao() -> Result<&'static RecordDao, String> {
+    RecordDao::global().map_err(|err| {
+        error!("call RecordDao::global error: {:?}", err);
+        format!("load records error: {:?}", err)
+    })
+}
+
+pub(crate) fn parse_view_id(view_id: &str) -> Result<DocumentViewId, String> {
+    DocumentViewId::from_str(view_id).map_err(|err| {
+        error!("call DocumentViewId::from_str error: {:?}", err);
+        format!("parse document view id error: {:?}", err)
+    })
+}
+
+#[tauri::command]
+pub async fn batch_delete(view_ids: Vec<String>) -> Result<Vec<BatchItemResult>, String> {
+    let results = record_dao()?.batch_trash_records(view_ids).await;
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::DeleteClipboardRecordBackend,
+        format!(
+            "batch move {}/{} records to trash success",
+            BatchItemResult::success_count(&results),
+            results.len()
+        ),
+    ) {
+        error!("send DeleteClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn batch_set_favorite(
+    view_ids: Vec<String>,
+    favorite: bool,
+) -> Result<Vec<BatchItemResult>, String> {
+    let mut results = record_dao()?
+        .batch_set_favorite(view_ids, favorite as i64)
+        .await;
+    for result in results.iter_mut() {
+        if let Some(view_id) = &result.new_view_id {
+            if let Some(moved) = follow_favorite(view_id, favorite).await {
+                result.new_view_id = Some(moved);
+            }
+        }
+    }
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::UpdateClipboardRecordBackend,
+        format!(
+            "batch set favorite {}/{} records success",
+            BatchItemResult::success_count(&results),
+            results.len()
+        ),
+    ) {
+        error!("send UpdateClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(results)
+}
+
+/// Keep the record on this device only or sync it with the paired devices. The record is
+/// moved to a new document, so the new view id is returned
+#[tauri::command]
+pub async fn set_record_sync(view_id: String, sync: bool) -> Result<String, String> {
+    let new_view_id = match record_dao()?.set_record_sync(&view_id, sync).await {
+        Ok(res) => res,
+        Err(err) => {
+            let err_msg = format!("set record sync error: {:?}", err);
+            error!("call set_record_sync error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::UpdateClipboardRecordBackend,
+        format!("set record sync success, view_id: {}", new_view_id),
+    ) {
+        error!("send UpdateClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(new_view_id)
+}
+
+/// With `favorites_only` in the sync policy the copies are kept on this device and a record
+/// is synced once it is a favorite. Return the view id of the moved record. Removing the
+/// favorite leaves the record synced, moving it back would delete the copies of the peers
+/// and create the document again on each favorite
+async fn follow_favorite(view_id: &str, favorite: bool) -> Option<String> {
+    let favorites_only = AppConfig::latest()
+        .read()
+        .sync_policy
+        .as_ref()
+        .is_some_and(|policy| policy.favorites_only);
+    // The update is queued while the node is unavailable, it has no view id yet
+    if !favorites_only || !favorite || view_id.is_empty() {
+        return None;
+    }
+
+    match record_dao().ok()?.set_record_sync(view_id, true).await {
+        Ok(new_view_id) => Some(new_view_id),
+        Err(err) => {
+            error!("call set_record_sync error: {:?}", err);
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn batch_tag(
+    view_ids: Vec<String>,
+    add_tags: Vec<String>,
+    remove_tags: Vec<String>,
+) -> Result<Vec<BatchItemResult>, String> {
+    let results = match record_dao()?
+        .batch_tag(view_ids, add_tags, remove_tags)
+        .await
+    {
+        Ok(results) => results,
+        Err(err) => {
+            let err_msg = format!("batch tag records error: {:?}", err);
+            error!("call batch_tag error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::UpdateClipboardRecordBackend,
+        format!(
+            "batch tag {}/{} records success",
+            BatchItemResult::success_count(&results),
+            results.len()
+        ),
+    ) {
+        error!("send UpdateClipboardRecordBackend message err: {:?}", e)
+    };
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn merge_records(
+    view_ids: Vec<String>,
+    separator: Option<String>,
+    template: Option<String>,
+    delete_sources: bool,
+) -> Result<MergeResult, String> {
+    let dao = record_dao()?;
+    let merged_id = match dao.merge_records(&view_ids, separator, template).await {
+        Ok(document_id) => document_id,
+        Err(err) => {
+            let err_msg = format!("merge records error: {:?}", err);
+            error!("call merge_records error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    let mut result = MergeResult {
+        document_id: merged_id.clone(),
+        ..Default::default()
+    };
+    // The sources are kept when the merged record is queued, it may turn out to be one of them
+    if delete_sources && merged_id.is_empty() {
+        info!("merge records queued, keep the source records");
+        result.sources_kept = true;
+    } else if delete_sources {
+        let mut sources = Vec::with_capacity(view_ids.len());
+        for view_id in view_ids {
+            match dao.find_record_by_view_id(&view_id).await {
+                Ok(Some(doc)) if doc.document_id.eq(&merged_id) => {
+                    info!("merged record is the source {}, keep it", view_id)
+                }
+                _ => sources.push(view_id),
+            }
+        }
+        result.trashed_sources = dao.batch_trash_records(sources).await;
+        info!(
+            "move {}/{} merged source records to trash",
+            BatchItemResult::success_count(&result.trashed_sources),
+            result.trashed_sources.len()
+        );
+    }
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::ChangeClipboardBackend,
+        "merge records success",
+    ) {
+        error!("send ChangeClipboardBackend message err: {:?}", e)
+    };
+
+    Ok(result)
+}
@@ -0,0 +1,70 @@
+use log::error;
+
+use crate::dao::register_dao::RegisterDao;
+use crate::handler::register::RegisterHandler;
+use crate::models::register::{parse_register_name, RegisterEntry};
+
+#[tauri::command]
+pub async fn list_registers() -> Result<Vec<RegisterEntry>, String> {
+    match RegisterDao::list_registers().await {
+        Ok(entries) => Ok(entries),
+        Err(err) => {
+            let err_msg = format!("list registers error: {:?}", err);
+            error!("call list_registers error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn register_history(name: String) -> Result<Vec<RegisterEntry>, String> {
+    let name = parse_register_name(&name).map_err(|err| err.to_string())?;
+
+    match RegisterDao::register_history(&name).await {
+        Ok(entries) => Ok(entries),
+        Err(err) => {
+            let err_msg = format!("load register history error: {:?}", err);
+            error!("call register_history error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn copy_to_register(name: String) -> Result<String, String> {
+    match RegisterHandler::copy_from_clipboard(&name).await {
+        Ok(view_id) => Ok(view_id),
+        Err(err) => {
+            let err_msg = format!("copy to register error: {:?}", err);
+            error!("call copy_to_register error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn paste_from_register(
+    name: String,
+    view_id: Option<String>,
+) -> Result<Option<RegisterEntry>, String> {
+    match RegisterHandler::paste(&name, view_id).await {
+        Ok(entry) => Ok(entry),
+        Err(err) => {
+            let err_msg = format!("paste from register error: {:?}", err);
+            error!("call paste_from_register error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn clear_register(name: String) -> Result<usize, String> {
+    match RegisterHandler::clear(&name).await {
+        Ok(cnt) => Ok(cnt),
+        Err(err) => {
+            let err_msg = format!("clear register error: {:?}", err);
+            error!("call clear_register error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
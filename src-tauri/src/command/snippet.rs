@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use log::error;
+
+use crate::command::record::parse_view_id;
+use crate::dao::snippet_dao::SnippetDao;
+use crate::models::snippet;
+use crate::models::snippet::{RenderContext, RenderedSnippet, Snippet};
+use crate::utils::clipboard::ClipBoardOperator;
+
+#[tauri::command]
+pub async fn list_snippets() -> Result<Vec<Snippet>, String> {
+    match SnippetDao::list_snippets().await {
+        Ok(snippets) => Ok(snippets),
+        Err(err) => {
+            let err_msg = format!("list snippets error: {:?}", err);
+            error!("call list_snippets error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn create_snippet(name: String, content: String) -> Result<String, String> {
+    match SnippetDao::create_snippet(name, content).await {
+        Ok(view_id) => Ok(view_id),
+        Err(err) => {
+            let err_msg = format!("create snippet error: {:?}", err);
+            error!("call create_snippet error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn update_snippet(
+    view_id: String,
+    name: String,
+    content: String,
+) -> Result<String, String> {
+    let document_views = parse_view_id(&view_id)?;
+
+    match SnippetDao::update_snippet(&document_views, name, content).await {
+        Ok(new_view_id) => Ok(new_view_id),
+        Err(err) => {
+            let err_msg = format!("update snippet error: {:?}", err);
+            error!("call update_snippet error: {:?}", err);
+            Err(err_msg)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn delete_snippet(view_id: String) -> Result<(), String> {
+    let document_views = parse_view_id(&view_id)?;
+
+    if let Err(err) = SnippetDao::delete_snippet(&document_views).await {
+        let err_msg = format!("delete snippet error: {:?}", err);
+        error!("call delete_snippet error: {:?}", err);
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Expand the placeholders of the snippet and put the result on the clipboard,
+/// `prompts` holds the values of the `{prompt:name}` placeholders
+#[tauri::command]
+pub async fn render_snippet(
+    view_id: String,
+    prompts: Option<HashMap<String, String>>,
+) -> Result<RenderedSnippet, String> {
+    let snippet = SnippetDao::find_snippet_by_view_id(view_id)
+        .await
+        .map_err(|err| {
+            error!("call find_snippet_by_view_id error: {:?}", err);
+            format!("load snippet error: {:?}", err)
+        })?;
+
+    let ctx = RenderContext {
+        now: chrono::Local::now(),
+        clipboard: ClipBoardOperator::get_text().ok(),
+        prompts: prompts.unwrap_or_default(),
+    };
+    let rendered = snippet::render(&snippet.content, &ctx).map_err(|err| {
+        error!("render snippet {} error: {:?}", snippet.name, err);
+        format!("render snippet error: {}", err)
+    })?;
+
+    if let Err(err) = ClipBoardOperator::set_text(rendered.text.clone()) {
+        let err_msg = format!("Set text to clipboard err: {}", err);
+        error!("{}", err_msg);
+        return Err(err_msg);
+    }
+    Ok(rendered)
+}
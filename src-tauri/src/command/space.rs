@@ -0,0 +1,132 @@
+use anyhow::{bail, Result};
+use log::error;
+
+use crate::dao::paired_device_dao::PairedDeviceDao;
+use crate::dao::record_dao::RecordDao;
+use crate::dao::space_dao::SpaceDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+use crate::listener::maintenance::MaintenanceListener;
+use crate::models::space::{Space, SpaceList};
+use crate::p2panda::group_key::{remove_space_keys, set_space_key};
+use crate::p2panda::node::NodeServer;
+use crate::p2panda::space_schema::create_schemas;
+use crate::utils::crypto;
+
+#[tauri::command]
+pub fn list_spaces() -> Result<SpaceList, String> {
+    SpaceDao::list().map_err(|err| {
+        error!("call list_spaces error: {:?}", err);
+        format!("list spaces error: {:?}", err)
+    })
+}
+
+/// The new space gets its own key and schemas, other devices join it by pairing while it
+/// is active
+#[tauri::command]
+pub async fn create_space(name: String) -> Result<Space, String> {
+    create(&name).await.map_err(|err| {
+        error!("call create_space error: {:?}", err);
+        format!("create space error: {:?}", err)
+    })
+}
+
+#[tauri::command]
+pub fn rename_space(id: String, name: String) -> Result<(), String> {
+    SpaceDao::rename(&id, name).map_err(|err| {
+        error!("call rename_space error: {:?}", err);
+        format!("rename space error: {:?}", err)
+    })
+}
+
+/// The key of the space is forgotten, so its records can not be read on this device any more.
+/// The node stops syncing its schemas once restarted
+#[tauri::command]
+pub async fn delete_space(id: String) -> Result<(), String> {
+    delete(&id).await.map_err(|err| {
+        error!("call delete_space error: {:?}", err);
+        format!("delete space error: {:?}", err)
+    })
+}
+
+/// Records copied from now on go to the space, the record list shows its records only
+#[tauri::command]
+pub async fn switch_space(id: String) -> Result<(), String> {
+    if let Err(err) = switch(&id).await {
+        let err_msg = format!("switch space error: {:?}", err);
+        error!("call switch_space error: {:?}", err);
+        return Err(err_msg);
+    }
+
+    if let Err(e) = GlobalHandler::push_message_to_window(
+        MessageTypeEnum::ChangeClipboardBackend,
+        "switch space success",
+    ) {
+        error!("send ChangeClipboardBackend message err: {:?}", e)
+    };
+    Ok(())
+}
+
+/// Stop sharing the space with the device. The device still knows the key and the schemas
+/// of the space, and the other members relay the schemas to it, so the space gets a new key
+/// and new schemas on this device. The other members pair again to keep syncing the space,
+/// the device removed keeps the records it received before
+#[tauri::command]
+pub async fn remove_space_member(space_id: String, device_id: String) -> Result<(), String> {
+    remove_member(&space_id, &device_id).await.map_err(|err| {
+        error!("call remove_space_member error: {:?}", err);
+        format!("remove space member error: {:?}", err)
+    })
+}
+
+async fn create(name: &str) -> Result<Space> {
+    let schemas = create_schemas().await?;
+    let space = SpaceDao::create(name, schemas)?;
+    // The node supports the schemas of the spaces it was started with
+    NodeServer::restart().await?;
+    Ok(space)
+}
+
+async fn delete(id: &str) -> Result<()> {
+    SpaceDao::remove(id)?;
+    remove_space_keys(id)?;
+    PairedDeviceDao::remove_space(id)?;
+    NodeServer::restart().await
+}
+
+async fn remove_member(space_id: &str, device_id: &str) -> Result<()> {
+    if !PairedDeviceDao::members(space_id)?
+        .iter()
+        .any(|d| d.device_id.eq(device_id))
+    {
+        bail!("device {} is not a member of space {}", device_id, space_id);
+    }
+    rotate_space(space_id).await?;
+    NodeServer::restart().await?;
+    // The records of this device are moved into the new schemas, sealed with the new key
+    MaintenanceListener::trigger();
+    Ok(())
+}
+
+/// Give the space a new key and new schemas on this device. All the members know the
+/// replaced ones, so they are removed from the space, the devices sharing no other space
+/// are removed. The node supports the new schemas once restarted by the caller
+pub(crate) async fn rotate_space(space_id: &str) -> Result<()> {
+    let schemas = create_schemas().await?;
+    PairedDeviceDao::remove_space(space_id)?;
+    set_space_key(space_id, crypto::generate_key())?;
+    SpaceDao::replace_schemas(space_id, schemas)
+}
+
+async fn switch(id: &str) -> Result<()> {
+    // The queued writes are written to the schemas of the active space when they are
+    // replayed, they must not end up in the new space
+    let dao = RecordDao::global()?;
+    if dao.has_pending() {
+        dao.replay_pending().await?;
+    }
+    if dao.has_pending() {
+        bail!("copies made while the node was unavailable are not saved yet, try again later");
+    }
+    SpaceDao::switch(id)
+}
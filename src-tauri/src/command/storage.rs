@@ -0,0 +1,44 @@
+use std::fs;
+
+use log::error;
+
+use crate::command::record::record_dao;
+use crate::config::app_config::AppConfig;
+use crate::consts::SQLITE_FILE;
+use crate::models::storage::StorageUsage;
+use crate::utils::dir::app_data_dir;
+
+#[tauri::command]
+pub async fn storage_usage() -> Result<StorageUsage, String> {
+    let sizes = match record_dao()?.record_sizes().await {
+        Ok(sizes) => sizes,
+        Err(err) => {
+            let err_msg = format!("load record sizes error: {:?}", err);
+            error!("call record_sizes error: {:?}", err);
+            return Err(err_msg);
+        }
+    };
+
+    let mut usage = StorageUsage::from_records(&sizes);
+    usage.quota_bytes = AppConfig::latest().read().storage_quota_bytes();
+    usage.sqlite_file_bytes = sqlite_file_bytes();
+
+    Ok(usage)
+}
+
+/// Size of the node database, including the write-ahead log if exists
+fn sqlite_file_bytes() -> u64 {
+    let data_dir = match app_data_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            error!("get app_data_dir error: {:?}", err);
+            return 0;
+        }
+    };
+
+    [SQLITE_FILE.to_string(), format!("{}-wal", SQLITE_FILE)]
+        .iter()
+        .filter_map(|file| fs::metadata(data_dir.join(file)).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
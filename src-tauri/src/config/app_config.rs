@@ -6,6 +6,7 @@ use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 
 use crate::config::configure::Configure;
+use crate::listener::maintenance::MaintenanceListener;
 
 #[derive(Debug)]
 pub struct AppConfig {
@@ -18,6 +19,8 @@ impl AppConfig {
     }
 
     pub async fn modify_config(patch: Configure) -> Result<()> {
+        let mut need_maintenance = false;
+
         // Before merging opt
         match {
             let old_cfg = AppConfig::latest().read().clone();
@@ -33,8 +36,29 @@ impl AppConfig {
             }
 
             if patch.store_limit.is_some() && old_cfg.store_limit.ne(&patch.store_limit) {
-                // todo
-                info!("delete and refresh over-limit store and cache")
+                info!("Config store_limit changed, delete and refresh over-limit store and cache");
+                need_maintenance = true;
+            }
+
+            if patch.retention_rules.is_some()
+                && old_cfg.retention_rules.ne(&patch.retention_rules)
+            {
+                info!("Config retention_rules changed, expire records with new rules");
+                need_maintenance = true;
+            }
+
+            if patch.storage_quota_mb.is_some()
+                && old_cfg.storage_quota_mb.ne(&patch.storage_quota_mb)
+            {
+                info!("Config storage_quota_mb changed, evict records over the new quota");
+                need_maintenance = true;
+            }
+
+            if patch.trash_retention_days.is_some()
+                && old_cfg.trash_retention_days.ne(&patch.trash_retention_days)
+            {
+                info!("Config trash_retention_days changed, purge trash with new retention");
+                need_maintenance = true;
             }
 
             <Result<()>>::Ok(())
@@ -51,6 +75,11 @@ impl AppConfig {
                     error!("Modify configuration save to file failed: {}", e);
                     return Err(e);
                 };
+
+                // Apply the new limits right away instead of waiting for the next round
+                if need_maintenance {
+                    MaintenanceListener::trigger();
+                }
                 Ok(())
             }
             Err(e) => Err(e),
@@ -81,7 +110,15 @@ mod tests {
             store_limit: Some(101),
             sync_port: None,
             graphql_port: None,
+            pairing_port: None,
             toggle_window_hotkey: None,
+            paste_next_hotkey: None,
+            trash_retention_days: None,
+            retention_rules: None,
+            storage_quota_mb: None,
+            register_hotkeys: None,
+            verify_publish: None,
+            sync_policy: None,
         })
         .await
         .unwrap();
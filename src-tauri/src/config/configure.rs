@@ -6,20 +6,87 @@ use anyhow::Result;
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
+use crate::models::record::{DataTypeEnum, Record};
+use crate::models::register::RegisterHotkey;
 use crate::utils::dir::config_path;
 use crate::utils::json;
 
 pub const DEFAULT_STORE_LIMIT: u32 = 100;
 pub const DEFAULT_SYNC_PORT: u16 = 12022;
 pub const DEFAULT_GRAPHQL_PORT: u16 = 12020;
+pub const DEFAULT_PAIRING_PORT: u16 = 12024;
 pub const DEFAULT_TOGGLE_WINDOW_HOTKEY: &str = "CommandOrControl+Shift+k";
+pub const DEFAULT_PASTE_NEXT_HOTKEY: &str = "CommandOrControl+Shift+n";
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+pub const DEFAULT_STORAGE_QUOTA_MB: u32 = 1024;
+
+/// Records of `data_type` older than `max_age_days` will be moved to trash, favorites are exempt
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetentionRule {
+    pub data_type: String,
+    pub max_age_days: u32,
+}
+
+impl RetentionRule {
+    /// Records created before the returned timestamp are expired
+    pub fn expire_before(&self, now: i64) -> i64 {
+        now - chrono::Duration::days(self.max_age_days as i64).num_seconds()
+    }
+}
+
+/// Decides whether a new copy is synced with the paired devices or kept on this device
+/// only, a record can still be switched with `set_record_sync` afterwards
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncPolicy {
+    // 只同步收藏的记录，收藏时开始同步，取消收藏后仍然同步，避免在配对设备上重建文档
+    pub favorites_only: bool,
+    // 超过该大小的图片只保存在本机，0 表示不限制
+    pub max_image_mb: u32,
+    // 这些类型的记录只保存在本机
+    pub local_data_types: Vec<String>,
+    // 从这些应用复制的记录只保存在本机，不区分大小写
+    pub local_source_apps: Vec<String>,
+}
+
+impl SyncPolicy {
+    pub fn should_sync(&self, record: &Record) -> bool {
+        if self.favorites_only && record.is_favorite == 0 {
+            return false;
+        }
+        if self.max_image_mb > 0
+            && record.data_type.eq(&String::from(DataTypeEnum::IMAGE))
+            && record.content.len() as i64 > self.max_image_mb as i64 * 1024 * 1024
+        {
+            return false;
+        }
+        if self.local_data_types.contains(&record.data_type) {
+            return false;
+        }
+        record.source_app.is_empty()
+            || !self
+                .local_source_apps
+                .iter()
+                .any(|app| app.eq_ignore_ascii_case(&record.source_app))
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Configure {
     pub store_limit: Option<u32>,
     pub sync_port: Option<u16>,
     pub graphql_port: Option<u16>,
+    // 设备配对时监听的端口
+    pub pairing_port: Option<u16>,
     pub toggle_window_hotkey: Option<String>,
+    pub paste_next_hotkey: Option<String>,
+    pub trash_retention_days: Option<u32>,
+    pub retention_rules: Option<Vec<RetentionRule>>,
+    pub storage_quota_mb: Option<u32>,
+    pub register_hotkeys: Option<Vec<RegisterHotkey>>,
+    // 发布后等待 node 物化文档视图，确认写入成功
+    pub verify_publish: Option<bool>,
+    pub sync_policy: Option<SyncPolicy>,
 }
 
 impl Default for Configure {
@@ -28,12 +95,24 @@ impl Default for Configure {
             store_limit: Some(DEFAULT_STORE_LIMIT),
             sync_port: Some(DEFAULT_SYNC_PORT),
             graphql_port: Some(DEFAULT_GRAPHQL_PORT),
+            pairing_port: Some(DEFAULT_PAIRING_PORT),
             toggle_window_hotkey: Some(DEFAULT_TOGGLE_WINDOW_HOTKEY.to_string()),
+            paste_next_hotkey: Some(DEFAULT_PASTE_NEXT_HOTKEY.to_string()),
+            trash_retention_days: Some(DEFAULT_TRASH_RETENTION_DAYS),
+            retention_rules: Some(vec![]),
+            storage_quota_mb: Some(DEFAULT_STORAGE_QUOTA_MB),
+            register_hotkeys: Some(vec![]),
+            verify_publish: Some(false),
+            sync_policy: Some(SyncPolicy::default()),
         }
     }
 }
 
 impl Configure {
+    pub fn storage_quota_bytes(&self) -> i64 {
+        self.storage_quota_mb.unwrap_or(DEFAULT_STORAGE_QUOTA_MB) as i64 * 1024 * 1024
+    }
+
     pub fn new() -> Self {
         let config_path = config_path().unwrap();
         fs::create_dir_all(config_path.parent().unwrap()).unwrap();
@@ -84,7 +163,15 @@ impl Configure {
         merge!(store_limit);
         merge!(sync_port);
         merge!(graphql_port);
+        merge!(pairing_port);
         merge!(toggle_window_hotkey);
+        merge!(paste_next_hotkey);
+        merge!(trash_retention_days);
+        merge!(retention_rules);
+        merge!(storage_quota_mb);
+        merge!(register_hotkeys);
+        merge!(verify_publish);
+        merge!(sync_policy);
     }
 }
 
@@ -92,7 +179,8 @@ impl Configure {
 mod tests {
     use std::path::Path;
 
-    use crate::config::configure::Configure;
+    use crate::config::configure::{Configure, RetentionRule, SyncPolicy};
+    use crate::models::record::{DataTypeEnum, Record};
     use crate::utils::dir::config_path;
 
     #[test]
@@ -101,4 +189,62 @@ mod tests {
         println!("{:?}", cfg);
         assert!(Path::new(&config_path().unwrap()).exists())
     }
+
+    #[test]
+    fn test_retention_rule_expire_before() {
+        let rule = RetentionRule {
+            data_type: "image".to_string(),
+            max_age_days: 7,
+        };
+        assert_eq!(rule.expire_before(1_000_000), 1_000_000 - 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn test_sync_policy() {
+        let image = Record {
+            content: "a".repeat(3 * 1024 * 1024),
+            data_type: DataTypeEnum::IMAGE.into(),
+            source_app: "Slack".to_string(),
+            ..Default::default()
+        };
+        assert!(SyncPolicy::default().should_sync(&image));
+
+        let policy = SyncPolicy {
+            max_image_mb: 2,
+            ..Default::default()
+        };
+        assert!(!policy.should_sync(&image));
+
+        let policy = SyncPolicy {
+            local_data_types: vec![DataTypeEnum::IMAGE.into()],
+            ..Default::default()
+        };
+        assert!(!policy.should_sync(&image));
+        assert!(policy.should_sync(&Record {
+            data_type: DataTypeEnum::TEXT.into(),
+            ..Default::default()
+        }));
+
+        let policy = SyncPolicy {
+            local_source_apps: vec!["1Password".to_string()],
+            ..Default::default()
+        };
+        assert!(policy.should_sync(&image));
+        assert!(!policy.should_sync(&Record {
+            source_app: "1password".to_string(),
+            ..Default::default()
+        }));
+        // The source app is unknown on some platforms
+        assert!(policy.should_sync(&Record::default()));
+
+        let policy = SyncPolicy {
+            favorites_only: true,
+            ..Default::default()
+        };
+        assert!(!policy.should_sync(&image));
+        assert!(policy.should_sync(&Record {
+            is_favorite: 1,
+            ..image
+        }));
+    }
 }
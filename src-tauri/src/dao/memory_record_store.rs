@@ -0,0 +1,519 @@
+//!
+//! Records kept in memory, the record logic can be tested without starting the node. Opened
+//! on a directory the documents are saved to it as well, the app keeps the local-only
+//! records this way so they never reach the node. They are not in the schemas of a space,
+//! so each one keeps the id of the space it was written to and only the records of the
+//! active space are listed
+//!
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::error;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::operation::OperationValue;
+use parking_lot::Mutex;
+
+use crate::dao::record_history_dao::RecordHistoryDao;
+use crate::dao::record_store::{
+    InvalidOperation, RecordStore, StoreUnavailable, StoredPage, StoredRecord,
+};
+use crate::models::record::{Record, RecordVersion};
+use crate::models::record_list::{RecordQuery, RecordSort};
+use crate::models::space::DEFAULT_SPACE_ID;
+use crate::utils::json;
+
+pub struct MemoryRecordStore {
+    // public key of the device writing the records
+    owner: String,
+    documents: Mutex<Vec<StoredRecord>>,
+    // each document is saved to `{document_id}.json` in the dir
+    dir: Option<PathBuf>,
+    // all the calls fail with `StoreUnavailable`, like the node is down
+    offline: Mutex<bool>,
+    // previous versions by document id, opened on a dir they are in `RecordHistoryDao`
+    versions: Mutex<HashMap<String, Vec<RecordVersion>>>,
+    // id of the active space, the records of all the spaces are listed when it is not set
+    active_space: Option<fn() -> Result<String>>,
+}
+
+impl MemoryRecordStore {
+    pub fn new(owner: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            documents: Mutex::new(vec![]),
+            dir: None,
+            offline: Mutex::new(false),
+            versions: Mutex::new(HashMap::new()),
+            active_space: None,
+        }
+    }
+
+    /// Load the documents saved in the dir. A file which can not be read is renamed to
+    /// `.bad` and skipped, the other records are still loaded
+    pub fn open(owner: &str, dir: PathBuf) -> Result<Self> {
+        let mut documents: Vec<StoredRecord> = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            match json::read(&path) {
+                Ok(doc) => documents.push(doc),
+                Err(e) => {
+                    error!("skip local record {} err: {:?}", path.display(), e);
+                    if let Err(e) = fs::rename(&path, path.with_extension("json.bad")) {
+                        error!("move bad local record {} err: {:?}", path.display(), e);
+                    }
+                }
+            }
+        }
+        documents.sort_by_key(|doc| doc.record.create_time);
+
+        Ok(Self {
+            owner: owner.to_string(),
+            documents: Mutex::new(documents),
+            dir: Some(dir),
+            offline: Mutex::new(false),
+            versions: Mutex::new(HashMap::new()),
+            active_space: None,
+        })
+    }
+
+    /// Keep the records apart by space, they are written to and listed from the space
+    /// returned by `active_space`
+    pub fn with_active_space(mut self, active_space: fn() -> Result<String>) -> Self {
+        self.active_space = Some(active_space);
+        self
+    }
+
+    fn space(&self) -> Result<Option<String>> {
+        self.active_space
+            .map(|active_space| active_space())
+            .transpose()
+    }
+
+    pub fn set_offline(&self, offline: bool) {
+        *self.offline.lock() = offline;
+    }
+
+    fn not_found(view_id: &str) -> InvalidOperation {
+        InvalidOperation(format!("record not found for view id: {}", view_id))
+    }
+
+    fn check_online(&self) -> Result<()> {
+        if *self.offline.lock() {
+            return Err(StoreUnavailable("memory store offline".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// All the records with the full content, in the order they were created
+    pub fn records(&self) -> Vec<StoredRecord> {
+        self.documents.lock().clone()
+    }
+
+    pub fn contains(&self, view_id: &str) -> bool {
+        self.documents
+            .lock()
+            .iter()
+            .any(|doc| doc.view_id.eq(view_id))
+    }
+
+    pub fn contains_document(&self, document_id: &str) -> bool {
+        self.documents
+            .lock()
+            .iter()
+            .any(|doc| doc.document_id.eq(document_id))
+    }
+
+    // Random, so the view ids are unique across restarts and stores
+    fn next_view_id(&self) -> String {
+        Hash::new_from_bytes(uuid::Uuid::new_v4().as_bytes()).to_string()
+    }
+
+    fn save(&self, doc: &StoredRecord) -> Result<()> {
+        match &self.dir {
+            Some(dir) => json::save(&dir.join(format!("{}.json", doc.document_id)), doc),
+            None => Ok(()),
+        }
+    }
+
+    fn remove(&self, document_id: &str) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            let path = dir.join(format!("{}.json", document_id));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(record: &mut Record, name: &str, value: &OperationValue) -> Result<()> {
+        match (name, value) {
+            ("content", OperationValue::String(v)) => record.content = v.clone(),
+            ("content_preview", OperationValue::String(v)) => {
+                record.content_preview = Some(v.clone())
+            }
+            ("data_type", OperationValue::String(v)) => record.data_type = v.clone(),
+            ("md5", OperationValue::String(v)) => record.md5 = v.clone(),
+            ("create_time", OperationValue::Integer(v)) => record.create_time = *v,
+            ("is_favorite", OperationValue::Integer(v)) => record.is_favorite = *v,
+            ("tags", OperationValue::String(v)) => record.tags = v.clone(),
+            ("latest_addr", OperationValue::String(v)) => record.latest_addr = v.clone(),
+            ("is_deleted", OperationValue::Integer(v)) => record.is_deleted = *v,
+            ("device_id", OperationValue::String(v)) => record.device_id = v.clone(),
+            ("source_app", OperationValue::String(v)) => record.source_app = v.clone(),
+            ("mime_type", OperationValue::String(v)) => record.mime_type = v.clone(),
+            ("byte_size", OperationValue::Integer(v)) => record.byte_size = *v,
+            ("subtype", OperationValue::String(v)) => record.subtype = v.clone(),
+            ("expires_at", OperationValue::Integer(v)) => record.expires_at = *v,
+            ("content_hash", OperationValue::String(v)) => record.content_hash = v.clone(),
+            _ => {
+                return Err(InvalidOperation(format!("field {}: {:?}", name, value)).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Same conditions as `RecordQuery::graphql_filter` and `RecordQuery::graphql_meta`, in
+    /// the space when it is given
+    fn matches(filter: &RecordQuery, space: Option<&str>, doc: &StoredRecord) -> bool {
+        if let Some(space) = space {
+            let doc_space = match doc.space_id.as_str() {
+                "" => DEFAULT_SPACE_ID,
+                space_id => space_id,
+            };
+            if doc_space.ne(space) {
+                return false;
+            }
+        }
+        let r = &doc.record;
+        if !filter.include_trash {
+            let deleted_matches = match (filter.in_trash, filter.deleted_before) {
+                (false, _) => r.is_deleted == 0,
+                (true, None) => r.is_deleted > 0,
+                (true, Some(deleted_before)) => r.is_deleted > 0 && r.is_deleted < deleted_before,
+            };
+            if !deleted_matches {
+                return false;
+            }
+        }
+        if let Some(content_hash) = &filter.content_hash {
+            if r.content_hash.ne(content_hash) {
+                return false;
+            }
+        }
+        if let Some(data_types) = &filter.data_types {
+            if !data_types.contains(&r.data_type) {
+                return false;
+            }
+        }
+        if let Some(favorite) = filter.favorite {
+            if r.is_favorite != favorite as i64 {
+                return false;
+            }
+        }
+        if let Some(tag) = filter.tags.as_ref().and_then(|tags| tags.first()) {
+            if !r.tags.contains(tag.as_str()) {
+                return false;
+            }
+        }
+        if let Some(created_after) = filter.created_after {
+            if r.create_time < created_after {
+                return false;
+            }
+        }
+        if let Some(created_before) = filter.created_before {
+            if r.create_time >= created_before {
+                return false;
+            }
+        }
+        if let Some(keyword) = filter.keyword.as_ref().filter(|k| !k.is_empty()) {
+            if !r.content.contains(keyword.as_str()) {
+                return false;
+            }
+        }
+        if let Some(device) = &filter.device {
+            if doc.owner.ne(device) {
+                return false;
+            }
+        }
+        if let Some(view_ids) = &filter.view_ids {
+            if !view_ids.contains(&doc.view_id) {
+                return false;
+            }
+        }
+        if let Some(document_ids) = &filter.document_ids {
+            if !document_ids.contains(&doc.document_id) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // The cursor is the sort key of the document, so the page stays stable when the
+    // documents before it are changed or deleted
+    fn encode_cursor(key: &(i64, String)) -> String {
+        format!("{}:{}", key.0, key.1)
+    }
+
+    fn decode_cursor(cursor: &str) -> Result<(i64, String)> {
+        let (value, document_id) = cursor
+            .split_once(':')
+            .ok_or(anyhow!("invalid cursor: {}", cursor))?;
+        Ok((value.parse()?, document_id.to_string()))
+    }
+}
+
+#[async_trait]
+impl RecordStore for MemoryRecordStore {
+    async fn create(&self, mut record: Record) -> Result<String> {
+        self.check_online()?;
+        record.fill_defaults(&self.owner);
+        let view_id = self.next_view_id();
+        let doc = StoredRecord {
+            view_id: view_id.clone(),
+            // the document id is the id of the create operation
+            document_id: view_id.clone(),
+            owner: self.owner.clone(),
+            cursor: String::new(),
+            record,
+            space_id: self.space()?.unwrap_or_default(),
+        };
+        self.save(&doc)?;
+        self.documents.lock().push(doc);
+        Ok(view_id)
+    }
+
+    async fn update(&self, view_id: &str, fields: &[(&str, OperationValue)]) -> Result<String> {
+        self.check_online()?;
+        let mut record = self
+            .documents
+            .lock()
+            .iter()
+            .find(|doc| doc.view_id.eq(view_id))
+            .map(|doc| doc.record.clone())
+            .ok_or_else(|| Self::not_found(view_id))?;
+        for (name, value) in fields {
+            Self::apply(&mut record, name, value)?;
+        }
+
+        let new_view_id = self.next_view_id();
+        let mut documents = self.documents.lock();
+        let doc = documents
+            .iter_mut()
+            .find(|doc| doc.view_id.eq(view_id))
+            .ok_or_else(|| Self::not_found(view_id))?;
+        doc.view_id = new_view_id.clone();
+        doc.record = record;
+        self.save(doc)?;
+        Ok(new_view_id)
+    }
+
+    async fn delete(&self, view_id: &str) -> Result<String> {
+        self.check_online()?;
+        let mut documents = self.documents.lock();
+        let idx = documents
+            .iter()
+            .position(|doc| doc.view_id.eq(view_id))
+            .ok_or_else(|| Self::not_found(view_id))?;
+        self.remove(&documents[idx].document_id)?;
+        documents.remove(idx);
+        drop(documents);
+        Ok(self.next_view_id())
+    }
+
+    async fn find_by_hash(&self, content_hash: &str) -> Result<Vec<StoredRecord>> {
+        self.check_online()?;
+        let filter = RecordQuery {
+            content_hash: Some(content_hash.to_string()),
+            include_trash: true,
+            ..Default::default()
+        };
+        let page = self
+            .page(&filter, &RecordSort::default(), i64::MAX, None)
+            .await?;
+        Ok(page.records)
+    }
+
+    async fn find_by_view_id(&self, view_id: &str) -> Result<Option<StoredRecord>> {
+        self.check_online()?;
+        Ok(self
+            .documents
+            .lock()
+            .iter()
+            .find(|doc| doc.view_id.eq(view_id))
+            .cloned())
+    }
+
+    async fn page(
+        &self,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+    ) -> Result<StoredPage> {
+        self.check_online()?;
+        let after = after.as_deref().map(Self::decode_cursor).transpose()?;
+        let space = self.space()?;
+
+        let mut matched: Vec<((i64, String), StoredRecord)> = self
+            .documents
+            .lock()
+            .iter()
+            .filter(|doc| Self::matches(filter, space.as_deref(), doc))
+            .map(|doc| (sort.key(&doc.record, &doc.document_id), doc.clone()))
+            .collect();
+        matched.sort_by(|(a, _), (b, _)| sort.compare(a, b));
+        let total_count = matched.len() as i64;
+
+        let mut remaining = matched
+            .into_iter()
+            .filter(|(key, _)| match &after {
+                Some(after) => sort.compare(key, after) == Ordering::Greater,
+                None => true,
+            })
+            .peekable();
+
+        let mut records = vec![];
+        while records.len() < limit.max(0) as usize {
+            match remaining.next() {
+                Some((key, mut doc)) => {
+                    doc.cursor = Self::encode_cursor(&key);
+                    // The content is loaded with `find_by_view_id`
+                    doc.record.content = String::new();
+                    records.push(doc);
+                }
+                None => break,
+            }
+        }
+
+        let end_cursor = match remaining.peek() {
+            Some(_) => records.last().map(|doc| doc.cursor.clone()),
+            None => None,
+        };
+        Ok(StoredPage {
+            records,
+            end_cursor,
+            total_count,
+        })
+    }
+
+    async fn count(&self, filter: &RecordQuery) -> Result<i64> {
+        self.check_online()?;
+        let space = self.space()?;
+        Ok(self
+            .documents
+            .lock()
+            .iter()
+            .filter(|doc| Self::matches(filter, space.as_deref(), doc))
+            .count() as i64)
+    }
+
+    async fn append_version(&self, document_id: &str, version: RecordVersion) -> Result<()> {
+        self.check_online()?;
+        if self.dir.is_some() {
+            return RecordHistoryDao::append(document_id, version);
+        }
+        let mut versions = self.versions.lock();
+        versions
+            .entry(document_id.to_string())
+            .or_default()
+            .insert(0, version);
+        Ok(())
+    }
+
+    async fn versions(&self, document_id: &str) -> Result<Vec<RecordVersion>> {
+        self.check_online()?;
+        if self.dir.is_some() {
+            return RecordHistoryDao::list(document_id);
+        }
+        let versions = self.versions.lock();
+        Ok(versions.get(document_id).cloned().unwrap_or_default())
+    }
+
+    async fn remove_versions(&self, document_id: &str) -> Result<()> {
+        self.check_online()?;
+        if self.dir.is_some() {
+            return RecordHistoryDao::remove(document_id);
+        }
+        self.versions.lock().remove(document_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use std::sync::Mutex;
+
+    use anyhow::Result;
+
+    use crate::dao::memory_record_store::MemoryRecordStore;
+    use crate::dao::record_store::RecordStore;
+    use crate::models::record::Record;
+    use crate::models::record_list::{RecordQuery, RecordSort};
+    use crate::models::space::DEFAULT_SPACE_ID;
+
+    static ACTIVE_SPACE: Mutex<&str> = Mutex::new(DEFAULT_SPACE_ID);
+
+    fn active_space() -> Result<String> {
+        Ok(ACTIVE_SPACE.lock().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_open_skips_bad_files() {
+        let dir = std::env::temp_dir().join(format!("local_records_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let store = MemoryRecordStore::open("owner", dir.clone()).unwrap();
+        let record = Record {
+            content: "local".to_string(),
+            ..Default::default()
+        };
+        store.create(record).await.unwrap();
+        fs::write(dir.join("cut.json"), "{\"view_id\":").unwrap();
+
+        let reopened = MemoryRecordStore::open("owner", dir.clone()).unwrap();
+        let records = reopened.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.content, "local");
+        assert!(!dir.join("cut.json").exists());
+        assert!(dir.join("cut.json.bad").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_records_by_space() {
+        let store = MemoryRecordStore::new("owner").with_active_space(active_space);
+        for content in ["a", "b"] {
+            let record = Record {
+                content: content.to_string(),
+                ..Default::default()
+            };
+            store.create(record).await.unwrap();
+        }
+        *ACTIVE_SPACE.lock().unwrap() = "work";
+        let record = Record {
+            content: "c".to_string(),
+            ..Default::default()
+        };
+        store.create(record).await.unwrap();
+
+        let (filter, sort) = (RecordQuery::default(), RecordSort::default());
+        let page = store.page(&filter, &sort, 10, None).await.unwrap();
+        assert_eq!(page.total_count, 1);
+        assert_eq!(page.records[0].space_id, "work");
+
+        *ACTIVE_SPACE.lock().unwrap() = DEFAULT_SPACE_ID;
+        assert_eq!(store.count(&filter).await.unwrap(), 2);
+        assert_eq!(store.records().len(), 3);
+    }
+}
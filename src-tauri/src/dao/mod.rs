@@ -1 +1,12 @@
+pub mod memory_record_store;
+pub mod p2panda_record_store;
+pub mod paired_device_dao;
+pub mod pending_queue;
 pub mod record_dao;
+pub mod record_history_dao;
+pub mod record_migration_dao;
+pub mod record_store;
+pub mod register_dao;
+pub mod selective_record_store;
+pub mod snippet_dao;
+pub mod space_dao;
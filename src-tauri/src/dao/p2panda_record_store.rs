@@ -0,0 +1,963 @@
+//!
+//! Records stored as documents of the record schema of the active space in the p2panda node
+//!
+//! Each space has its own schemas, the node only replicates them with the members of the
+//! space, see `space_schema`. The peers relaying the documents still store them, so
+//! `content` and `content_preview` are encrypted with the key of the space and `md5` is left
+//! empty. The content hash is keyed with the same key, the other fields stay in cleartext as
+//! the node filters, sorts and counts by them. The records not encrypted yet still carry the
+//! hash without a key, so the copies are merged with them too. The node can not search the
+//! encrypted content, so the keyword search is done here after decrypting.
+//!
+//! The node does not expose the earlier views of a document, so the content replaced by an
+//! edit is kept in a document of the record version schema, encrypted the same way.
+//!
+//! The content of a record is read from the database of the node in this process, see
+//! `node_store`, the other reads and the writes go through the GraphQL API.
+//!
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationValue};
+use p2panda_rs::schema::SchemaId;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::dao::record_history_dao::RecordHistoryDao;
+use crate::dao::record_store::{
+    InvalidOperation, RecordStore, StoreUnavailable, StoredPage, StoredRecord,
+};
+use crate::dao::space_dao::SpaceDao;
+use crate::graphql::{
+    record_by_view_id, record_list, record_version_list, GraphRecordListDocuments,
+    GraphRecordVersionDocuments, GraphRecordView,
+};
+use crate::models::record::{DataTypeEnum, Record, RecordVersion};
+use crate::models::record_list::{RecordQuery, RecordSort, MAX_PAGE_SIZE};
+use crate::models::space::{Space, SpaceSchemas, DEFAULT_SPACE_ID};
+use crate::p2panda::graphql::{GraphQLHandler, NodeError};
+use crate::p2panda::group_key::space_keys;
+use crate::p2panda::key_pair::device_id;
+use crate::p2panda::node_store::{DocumentView, NodeStore};
+use crate::p2panda::space_schema::{schema_ids, space_query};
+use crate::utils::crypto::{self, GroupKey};
+use crate::utils::string;
+
+pub struct P2pandaRecordStore;
+
+// Set once `encrypt_legacy_records` encrypted all the records of this device, no record in
+// cleartext is accepted afterwards
+static LEGACY_ENCRYPTED: AtomicBool = AtomicBool::new(false);
+
+/// Schemas and keys of the space the records are read from and written to
+#[derive(Clone)]
+struct SpaceContext {
+    record: SchemaId,
+    record_version: SchemaId,
+    // the current key first, then the keys replaced by pairing
+    keys: Vec<GroupKey>,
+}
+
+impl SpaceContext {
+    fn active() -> Result<Self> {
+        Self::of(&SpaceDao::active()?)
+    }
+
+    fn of(space: &Space) -> Result<Self> {
+        Self::with_schemas(space.schemas.as_ref(), space_keys(&space.id)?)
+    }
+
+    fn with_schemas(schemas: Option<&SpaceSchemas>, keys: Vec<GroupKey>) -> Result<Self> {
+        let (record, record_version) = schema_ids(schemas)?;
+        Ok(Self {
+            record,
+            record_version,
+            keys,
+        })
+    }
+
+    fn key(&self) -> Result<&GroupKey> {
+        self.keys
+            .first()
+            .ok_or(InvalidOperation("no key of the space".to_string()).into())
+    }
+
+    /// Request of the generated query, with the schema ids of the space filled in
+    fn request_body<V: Serialize>(
+        &self,
+        query: &str,
+        operation_name: &str,
+        variables: V,
+    ) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({
+            "query": space_query(query, &self.record, &self.record_version)?,
+            "operationName": operation_name,
+            "variables": variables,
+        }))
+    }
+}
+
+impl P2pandaRecordStore {
+    // The content of images is large, so the legacy records are loaded in small pages
+    const LEGACY_BATCH_SIZE: i64 = 20;
+
+    async fn query<Q: Serialize, R: DeserializeOwned>(request_body: &Q) -> Result<Option<R>> {
+        GraphQLHandler::global()?
+            .post(request_body)
+            .await
+            .map_err(Self::store_error)
+    }
+
+    async fn send(opt: PlainOperation) -> Result<String> {
+        GraphQLHandler::global()?
+            .send_to_node(opt)
+            .await
+            .map_err(Self::store_error)
+    }
+
+    // The node rejecting the operation is not retried by the queue of `RecordDao`, the
+    // conflicts and views not materialized in time are
+    fn store_error(err: anyhow::Error) -> anyhow::Error {
+        match err.downcast_ref::<NodeError>() {
+            Some(NodeError::Unreachable(e)) => StoreUnavailable(e.clone()).into(),
+            Some(NodeError::GraphQL(_) | NodeError::Encoding(_)) => {
+                InvalidOperation(err.to_string()).into()
+            }
+            _ => err,
+        }
+    }
+
+    fn previous(view_id: &str) -> Result<DocumentViewId> {
+        DocumentViewId::from_str(view_id)
+            .map_err(|e| InvalidOperation(format!("view id {}: {}", view_id, e)).into())
+    }
+
+    fn from_list_document(doc: GraphRecordListDocuments) -> StoredRecord {
+        let (fields, meta) = (doc.fields.unwrap(), doc.meta.unwrap());
+        StoredRecord {
+            view_id: meta.view_id,
+            document_id: meta.document_id.to_string(),
+            owner: meta.owner.to_string(),
+            cursor: doc.cursor.unwrap_or_default(),
+            record: Record {
+                content: fields.content.unwrap_or_default(),
+                content_preview: fields.content_preview,
+                data_type: fields.data_type.unwrap_or_default(),
+                md5: fields.md5.unwrap_or_default(),
+                create_time: fields.create_time.unwrap_or_default(),
+                is_favorite: fields.is_favorite.unwrap_or_default(),
+                tags: fields.tags.unwrap_or_default(),
+                latest_addr: fields.latest_addr.unwrap_or_default(),
+                is_deleted: fields.is_deleted.unwrap_or_default(),
+                device_id: fields.device_id.unwrap_or_default(),
+                source_app: fields.source_app.unwrap_or_default(),
+                mime_type: fields.mime_type.unwrap_or_default(),
+                byte_size: fields.byte_size.unwrap_or_default(),
+                subtype: fields.subtype.unwrap_or_default(),
+                expires_at: fields.expires_at.unwrap_or_default(),
+                content_hash: fields.content_hash.unwrap_or_default(),
+                sync: true,
+            },
+            space_id: String::new(),
+        }
+    }
+
+    fn from_view(view: GraphRecordView) -> StoredRecord {
+        let (fields, meta) = (view.fields.unwrap(), view.meta.unwrap());
+        StoredRecord {
+            view_id: meta.view_id,
+            document_id: meta.document_id.to_string(),
+            owner: meta.owner.to_string(),
+            cursor: String::new(),
+            record: Record {
+                content: fields.content.unwrap_or_default(),
+                content_preview: fields.content_preview,
+                data_type: fields.data_type.unwrap_or_default(),
+                md5: fields.md5.unwrap_or_default(),
+                create_time: fields.create_time.unwrap_or_default(),
+                is_favorite: fields.is_favorite.unwrap_or_default(),
+                tags: fields.tags.unwrap_or_default(),
+                latest_addr: fields.latest_addr.unwrap_or_default(),
+                is_deleted: fields.is_deleted.unwrap_or_default(),
+                device_id: fields.device_id.unwrap_or_default(),
+                source_app: fields.source_app.unwrap_or_default(),
+                mime_type: fields.mime_type.unwrap_or_default(),
+                byte_size: fields.byte_size.unwrap_or_default(),
+                subtype: fields.subtype.unwrap_or_default(),
+                expires_at: fields.expires_at.unwrap_or_default(),
+                content_hash: fields.content_hash.unwrap_or_default(),
+                sync: true,
+            },
+            space_id: String::new(),
+        }
+    }
+
+    fn from_node_view(view: DocumentView) -> Result<StoredRecord> {
+        let record = Record {
+            content: view.text("content").unwrap_or_default(),
+            content_preview: view.text("content_preview"),
+            data_type: view.text("data_type").unwrap_or_default(),
+            md5: view.text("md5").unwrap_or_default(),
+            create_time: view.integer("create_time")?.unwrap_or_default(),
+            is_favorite: view.integer("is_favorite")?.unwrap_or_default(),
+            tags: view.text("tags").unwrap_or_default(),
+            latest_addr: view.text("latest_addr").unwrap_or_default(),
+            is_deleted: view.integer("is_deleted")?.unwrap_or_default(),
+            device_id: view.text("device_id").unwrap_or_default(),
+            source_app: view.text("source_app").unwrap_or_default(),
+            mime_type: view.text("mime_type").unwrap_or_default(),
+            byte_size: view.integer("byte_size")?.unwrap_or_default(),
+            subtype: view.text("subtype").unwrap_or_default(),
+            expires_at: view.integer("expires_at")?.unwrap_or_default(),
+            content_hash: view.text("content_hash").unwrap_or_default(),
+            sync: true,
+        };
+        Ok(StoredRecord {
+            view_id: view.view_id,
+            document_id: view.document_id,
+            owner: view.owner,
+            cursor: String::new(),
+            record,
+            space_id: String::new(),
+        })
+    }
+
+    /// The record read from the database of the node, none when the view is not materialized
+    /// or is not a record of the space
+    async fn node_view(ctx: &SpaceContext, view_id: &str) -> Result<Option<StoredRecord>> {
+        match NodeStore::global()?.view(view_id).await? {
+            Some(view) if view.schema_id.eq(&ctx.record.to_string()) => {
+                Ok(Some(Self::from_node_view(view)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Decrypt the content and preview with the group keys. A record in cleartext is only
+    /// accepted from this device, it was written before the encryption and is returned as it
+    /// is until `encrypt_legacy_records` encrypted it. Any peer can publish a record in
+    /// cleartext, so the ones of the other devices are rejected
+    fn open(keys: &[GroupKey], mut doc: StoredRecord) -> Result<StoredRecord> {
+        if !Self::is_sealed(&doc.record) && !Self::accepts_cleartext(&doc.owner)? {
+            bail!("record in cleartext from {}", doc.owner);
+        }
+        doc.record.content = crypto::decrypt_with_any(keys, &doc.record.content)?;
+        doc.record.content_preview = doc
+            .record
+            .content_preview
+            .map(|preview| crypto::decrypt_with_any(keys, &preview))
+            .transpose()?;
+        Ok(doc)
+    }
+
+    // The content is empty when it is not loaded, the preview is always published
+    fn is_sealed(r: &Record) -> bool {
+        let preview_sealed = r
+            .content_preview
+            .as_ref()
+            .is_some_and(|p| crypto::is_encrypted(p));
+        preview_sealed && (r.content.is_empty() || crypto::is_encrypted(&r.content))
+    }
+
+    fn accepts_cleartext(owner: &str) -> Result<bool> {
+        Ok(!LEGACY_ENCRYPTED.load(Ordering::SeqCst) && owner == device_id()?)
+    }
+
+    /// Decrypt the listed records, the ones of the other spaces, of the devices not paired or
+    /// in cleartext from the other devices are skipped
+    fn open_all(keys: &[GroupKey], docs: Vec<StoredRecord>) -> Vec<StoredRecord> {
+        docs.into_iter()
+            .filter_map(|doc| {
+                let document_id = doc.document_id.clone();
+                Self::open(keys, doc)
+                    .map_err(|e| debug!("skip record {} err: {:?}", document_id, e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Encrypt the content fields, md5 is not published as it reveals the content
+    fn seal<'a>(
+        key: &GroupKey,
+        fields: &[(&'a str, OperationValue)],
+    ) -> Result<Vec<(&'a str, OperationValue)>> {
+        let mut sealed = Vec::with_capacity(fields.len());
+        for (name, value) in fields {
+            let value = match (*name, value) {
+                ("content" | "content_preview", OperationValue::String(v)) => {
+                    OperationValue::String(crypto::encrypt(key, v)?)
+                }
+                ("md5", _) => OperationValue::String(String::new()),
+                _ => value.clone(),
+            };
+            sealed.push((*name, value));
+        }
+        Ok(sealed)
+    }
+
+    /// Documents as stored in the node, the content is only loaded with `with_content`
+    async fn list(
+        ctx: &SpaceContext,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+        with_content: bool,
+    ) -> Result<StoredPage> {
+        let (order_by, order_dir) = sort.graphql_order();
+        // Filters are built dynamically, absent conditions must not be sent as null
+        let request_body = ctx.request_body(
+            record_list::QUERY,
+            record_list::OPERATION_NAME,
+            serde_json::json!({
+                "limit": limit,
+                "start_cursor": after,
+                "filter": filter.graphql_filter(),
+                "meta": filter.graphql_meta(),
+                "order_by": order_by,
+                "order_dir": order_dir,
+                "with_content": with_content,
+            }),
+        )?;
+        let data: Option<record_list::ResponseData> = Self::query(&request_body).await?;
+
+        match data {
+            None => Ok(StoredPage::default()),
+            Some(res) => {
+                let res = res.records;
+                Ok(StoredPage {
+                    records: res
+                        .documents
+                        .into_iter()
+                        .map(Self::from_list_document)
+                        .collect(),
+                    end_cursor: res.has_next_page.then_some(res.end_cursor),
+                    total_count: res.total_count,
+                })
+            }
+        }
+    }
+
+    /// Records searched by keyword are text records, none when the filter excludes them
+    fn scan_filter(filter: &RecordQuery, keyword: Option<&str>) -> Option<RecordQuery> {
+        if keyword.is_none() {
+            return Some(filter.clone());
+        }
+        let text = String::from(DataTypeEnum::TEXT);
+        if let Some(data_types) = &filter.data_types {
+            if !data_types.contains(&text) {
+                return None;
+            }
+        }
+        Some(RecordQuery {
+            data_types: Some(vec![text]),
+            keyword: None,
+            ..filter.clone()
+        })
+    }
+
+    /// Decrypt the documents, the ones not readable with the keys of the space or not
+    /// matching the keyword are dropped
+    fn open_matched(
+        keys: &[GroupKey],
+        docs: Vec<StoredRecord>,
+        keyword: Option<&str>,
+    ) -> Vec<StoredRecord> {
+        let mut docs = Self::open_all(keys, docs);
+        if let Some(keyword) = keyword {
+            docs.retain(|doc| doc.record.content.contains(keyword));
+        }
+        docs
+    }
+
+    /// The node can not search the encrypted content nor drop the documents the keys of the
+    /// space do not open, so the pages of the node are decrypted and filtered here. The
+    /// pages go on from the node cursor, and more documents are loaded when some are
+    /// dropped. The content is only loaded to match the keyword
+    async fn scan(
+        ctx: &SpaceContext,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+        keyword: Option<&str>,
+    ) -> Result<StoredPage> {
+        let filter = match Self::scan_filter(filter, keyword) {
+            Some(filter) => filter,
+            None => return Ok(StoredPage::default()),
+        };
+        let limit = limit.max(1) as usize;
+
+        let mut page = StoredPage::default();
+        let mut start_cursor = after;
+        let mut fetch_size = limit;
+        loop {
+            let res = Self::list(
+                ctx,
+                &filter,
+                sort,
+                fetch_size.min(MAX_PAGE_SIZE as usize) as i64,
+                start_cursor,
+                keyword.is_some(),
+            )
+            .await?;
+            let fetched_cnt = res.records.len();
+            let docs = Self::open_matched(&ctx.keys, res.records, keyword);
+            let dropped = docs.len() < fetched_cnt;
+
+            let mut docs = docs.into_iter().peekable();
+            while page.records.len() < limit {
+                match docs.next() {
+                    Some(mut doc) => {
+                        doc.record.content = String::new();
+                        page.records.push(doc);
+                    }
+                    None => break,
+                }
+            }
+
+            let more = docs.peek().is_some() || res.end_cursor.is_some();
+            if page.records.len() == limit || !more {
+                if more {
+                    page.end_cursor = page.records.last().map(|last| last.cursor.clone());
+                }
+                return Ok(page);
+            }
+            // Twice of the missing count are loaded when the page had documents dropped
+            let missing = limit - page.records.len();
+            fetch_size = if dropped { missing * 2 } else { missing };
+            start_cursor = res.end_cursor;
+        }
+    }
+
+    /// The schemas hold the records of the space only, so the node counts them. The
+    /// records matching a keyword are counted after decrypting all of them
+    async fn count_in(
+        ctx: &SpaceContext,
+        filter: &RecordQuery,
+        keyword: Option<&str>,
+    ) -> Result<i64> {
+        if keyword.is_some() {
+            return Self::count_readable(ctx, filter, keyword).await;
+        }
+        let page = Self::list(ctx, filter, &RecordSort::default(), 1, None, false).await?;
+        Ok(page.total_count)
+    }
+
+    /// Readable records matching the filter and the keyword, all of them are loaded
+    async fn count_readable(
+        ctx: &SpaceContext,
+        filter: &RecordQuery,
+        keyword: Option<&str>,
+    ) -> Result<i64> {
+        let filter = match Self::scan_filter(filter, keyword) {
+            Some(filter) => filter,
+            None => return Ok(0),
+        };
+
+        let mut count = 0;
+        let mut start_cursor = None;
+        loop {
+            let res = Self::list(
+                ctx,
+                &filter,
+                &RecordSort::default(),
+                MAX_PAGE_SIZE,
+                start_cursor,
+                keyword.is_some(),
+            )
+            .await?;
+            count += Self::open_matched(&ctx.keys, res.records, keyword).len() as i64;
+            match res.end_cursor {
+                None => return Ok(count),
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+    }
+
+    fn keyword(filter: &RecordQuery) -> Option<&str> {
+        filter.keyword.as_deref().filter(|k| !k.is_empty())
+    }
+
+    async fn create_in(ctx: &SpaceContext, mut record: Record) -> Result<String> {
+        // Records are signed by the key pair of the node, which is the id of this device
+        record.fill_defaults(&device_id()?);
+        let fields = Self::seal(
+            ctx.key()?,
+            &[
+                ("content", OperationValue::String(record.content)),
+                (
+                    "content_preview",
+                    OperationValue::String(record.content_preview.unwrap_or_default()),
+                ),
+                ("data_type", OperationValue::String(record.data_type)),
+                ("md5", OperationValue::String(record.md5)),
+                ("create_time", OperationValue::Integer(record.create_time)),
+                ("is_favorite", OperationValue::Integer(record.is_favorite)),
+                ("tags", OperationValue::String(record.tags)),
+                ("latest_addr", OperationValue::String(record.latest_addr)),
+                ("is_deleted", OperationValue::Integer(record.is_deleted)),
+                ("device_id", OperationValue::String(record.device_id)),
+                ("source_app", OperationValue::String(record.source_app)),
+                ("mime_type", OperationValue::String(record.mime_type)),
+                ("byte_size", OperationValue::Integer(record.byte_size)),
+                ("subtype", OperationValue::String(record.subtype)),
+                ("expires_at", OperationValue::Integer(record.expires_at)),
+                ("content_hash", OperationValue::String(record.content_hash)),
+            ],
+        )?;
+        let opt = OperationBuilder::new(&ctx.record)
+            .action(OperationAction::Create)
+            .fields(&fields)
+            .build()?;
+
+        let res = Self::send(PlainOperation::from(&opt)).await?;
+        info!("create record success, opt id: {}", res);
+        Ok(res)
+    }
+
+    async fn delete_in(ctx: &SpaceContext, view_id: &str) -> Result<String> {
+        let opt = OperationBuilder::new(&ctx.record)
+            .action(OperationAction::Delete)
+            .previous(&Self::previous(view_id)?)
+            .build()?;
+
+        let res = Self::send(PlainOperation::from(&opt)).await?;
+        info!("delete record success, opt id: {}", res);
+        Ok(res)
+    }
+
+    async fn append_version_in(
+        ctx: &SpaceContext,
+        document_id: &str,
+        version: RecordVersion,
+    ) -> Result<()> {
+        let fields = Self::seal(
+            ctx.key()?,
+            &[
+                ("record_id", OperationValue::String(document_id.to_string())),
+                ("view_id", OperationValue::String(version.view_id)),
+                ("content", OperationValue::String(version.content)),
+                (
+                    "content_preview",
+                    OperationValue::String(version.content_preview.unwrap_or_default()),
+                ),
+                ("content_hash", OperationValue::String(version.content_hash)),
+                ("edit_time", OperationValue::Integer(version.edit_time)),
+            ],
+        )?;
+        let opt = OperationBuilder::new(&ctx.record_version)
+            .action(OperationAction::Create)
+            .fields(&fields)
+            .build()?;
+
+        let res = Self::send(PlainOperation::from(&opt)).await?;
+        info!("append record version success, opt id: {}", res);
+        Ok(())
+    }
+
+    /// Version documents of the record written by all the devices, still encrypted
+    async fn version_documents(
+        ctx: &SpaceContext,
+        document_id: &str,
+    ) -> Result<Vec<GraphRecordVersionDocuments>> {
+        let mut docs = vec![];
+        let mut start_cursor = None;
+        loop {
+            let request_body = ctx.request_body(
+                record_version_list::QUERY,
+                record_version_list::OPERATION_NAME,
+                record_version_list::Variables {
+                    record_id: document_id.to_string(),
+                    limit: Some(MAX_PAGE_SIZE),
+                    start_cursor,
+                },
+            )?;
+            let data: Option<record_version_list::ResponseData> =
+                Self::query(&request_body).await?;
+            let res = match data {
+                None => break,
+                Some(res) => res.versions,
+            };
+            docs.extend(res.documents);
+            if !res.has_next_page {
+                break;
+            }
+            start_cursor = Some(res.end_cursor);
+        }
+        Ok(docs)
+    }
+
+    /// Versions are always encrypted, the ones in cleartext or of the other spaces are skipped
+    fn open_version(keys: &[GroupKey], doc: GraphRecordVersionDocuments) -> Result<RecordVersion> {
+        let fields = doc.fields.unwrap();
+        let decrypt = |value: String| {
+            if !crypto::is_encrypted(&value) {
+                bail!("version in cleartext");
+            }
+            crypto::decrypt_with_any(keys, &value)
+        };
+        Ok(RecordVersion {
+            view_id: fields.view_id.unwrap_or_default(),
+            content: decrypt(fields.content.unwrap_or_default())?,
+            content_preview: fields.content_preview.map(decrypt).transpose()?,
+            content_hash: fields.content_hash.unwrap_or_default(),
+            edit_time: fields.edit_time.unwrap_or_default(),
+        })
+    }
+
+    async fn versions_in(ctx: &SpaceContext, document_id: &str) -> Result<Vec<RecordVersion>> {
+        let mut versions: Vec<RecordVersion> = Self::version_documents(ctx, document_id)
+            .await?
+            .into_iter()
+            .filter_map(|doc| {
+                let view_id = doc.meta.as_ref().map(|meta| meta.view_id.clone());
+                Self::open_version(&ctx.keys, doc)
+                    .map_err(|e| debug!("skip record version {:?} err: {:?}", view_id, e))
+                    .ok()
+            })
+            .collect();
+        for legacy in RecordHistoryDao::list(document_id)? {
+            if !versions.iter().any(|v| v.view_id.eq(&legacy.view_id)) {
+                versions.push(legacy);
+            }
+        }
+        versions.sort_by(|a, b| b.edit_time.cmp(&a.edit_time));
+        Ok(versions)
+    }
+
+    async fn remove_versions_in(ctx: &SpaceContext, document_id: &str) -> Result<()> {
+        for doc in Self::version_documents(ctx, document_id).await? {
+            let view_id = doc.meta.unwrap().view_id;
+            let opt = OperationBuilder::new(&ctx.record_version)
+                .action(OperationAction::Delete)
+                .previous(&DocumentViewId::from_str(&view_id)?)
+                .build()?;
+            Self::send(PlainOperation::from(&opt)).await?;
+        }
+        RecordHistoryDao::remove(document_id)
+    }
+
+    /// Space of the record of this device moved from old schemas, the one whose keys open
+    /// it. The records in cleartext were written before the spaces, they go to the default
+    /// space
+    fn move_target<'a>(
+        targets: &'a [(Space, SpaceContext)],
+        doc: &StoredRecord,
+    ) -> Option<(&'a SpaceContext, StoredRecord)> {
+        let open = |keys: &[GroupKey]| -> Result<StoredRecord> {
+            let mut doc = doc.clone();
+            doc.record.content = crypto::decrypt_with_any(keys, &doc.record.content)?;
+            doc.record.content_preview = doc
+                .record
+                .content_preview
+                .map(|preview| crypto::decrypt_with_any(keys, &preview))
+                .transpose()?;
+            Ok(doc)
+        };
+        if !Self::is_sealed(&doc.record) {
+            return targets
+                .iter()
+                .find(|(space, _)| space.id.eq(DEFAULT_SPACE_ID))
+                .and_then(|(_, ctx)| open(&ctx.keys).ok().map(|doc| (ctx, doc)));
+        }
+        targets
+            .iter()
+            .find_map(|(_, ctx)| open(&ctx.keys).ok().map(|doc| (ctx, doc)))
+    }
+
+    /// Move the records of this device from the schemas into the schemas of the spaces.
+    /// Each record is created again with its versions, sealed with the current key of the
+    /// space, and deleted from the old schemas once all of them are copied. A record whose
+    /// hash is already in the space was copied by an interrupted run, it is not copied again
+    async fn move_records(from: &SpaceContext, targets: &[(Space, SpaceContext)]) -> Result<usize> {
+        let own = device_id()?;
+        let filter = RecordQuery {
+            device: Some(own.clone()),
+            include_trash: true,
+            ..Default::default()
+        };
+
+        let mut moved = vec![];
+        let mut start_cursor = None;
+        loop {
+            let page = Self::list(
+                from,
+                &filter,
+                &RecordSort::default(),
+                Self::LEGACY_BATCH_SIZE,
+                start_cursor,
+                true,
+            )
+            .await?;
+
+            for doc in page.records {
+                // The device field is written by the peers, the owner is the signer
+                if doc.owner.ne(&own) {
+                    continue;
+                }
+                let (to, opened) = match Self::move_target(targets, &doc) {
+                    Some(target) => target,
+                    None => {
+                        debug!("no space opens record {}, keep it", doc.document_id);
+                        continue;
+                    }
+                };
+                let mut record = opened.record;
+                record.md5 = String::new();
+                record.content_hash = crypto::keyed_content_hash(to.key()?, &record.content);
+
+                let hash_filter = RecordQuery {
+                    content_hash: Some(record.content_hash.clone()),
+                    include_trash: true,
+                    ..Default::default()
+                };
+                let copied = Self::list(to, &hash_filter, &RecordSort::default(), 1, None, false)
+                    .await?
+                    .records
+                    .into_iter()
+                    .find(|copied| copied.owner.eq(&own));
+                if copied.is_none() {
+                    // The versions are in the old schemas, sealed with the keys of the space
+                    let versions_from = SpaceContext {
+                        keys: to.keys.clone(),
+                        ..from.clone()
+                    };
+                    let versions = Self::versions_in(&versions_from, &doc.document_id).await?;
+                    let document_id = Self::create_in(to, record).await?;
+                    for version in versions.into_iter().rev() {
+                        Self::append_version_in(to, &document_id, version).await?;
+                    }
+                }
+                moved.push((doc.view_id, doc.document_id));
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+
+        // Deleted after the scan, the pages would move under the cursor
+        for (view_id, document_id) in moved.iter() {
+            Self::delete_in(from, view_id).await?;
+            Self::remove_versions_in(from, document_id).await?;
+        }
+        if !moved.is_empty() {
+            info!("move {} records from schema {}", moved.len(), from.record);
+        }
+        Ok(moved.len())
+    }
+}
+
+#[async_trait]
+impl RecordStore for P2pandaRecordStore {
+    async fn create(&self, record: Record) -> Result<String> {
+        Self::create_in(&SpaceContext::active()?, record).await
+    }
+
+    async fn update(&self, view_id: &str, fields: &[(&str, OperationValue)]) -> Result<String> {
+        let ctx = SpaceContext::active()?;
+        let fields = Self::seal(ctx.key()?, fields)?;
+        let opt = OperationBuilder::new(&ctx.record)
+            .action(OperationAction::Update)
+            .previous(&Self::previous(view_id)?)
+            .fields(&fields)
+            .build()?;
+
+        let res = Self::send(PlainOperation::from(&opt)).await?;
+        info!("update record success, opt id: {}", res);
+        Ok(res)
+    }
+
+    async fn delete(&self, view_id: &str) -> Result<String> {
+        Self::delete_in(&SpaceContext::active()?, view_id).await
+    }
+
+    async fn find_by_hash(&self, content_hash: &str) -> Result<Vec<StoredRecord>> {
+        let filter = RecordQuery {
+            content_hash: Some(content_hash.to_string()),
+            include_trash: true,
+            ..Default::default()
+        };
+        let ctx = SpaceContext::active()?;
+        let page = Self::scan(
+            &ctx,
+            &filter,
+            &RecordSort::default(),
+            MAX_PAGE_SIZE,
+            None,
+            None,
+        )
+        .await?;
+        Ok(page.records)
+    }
+
+    async fn find_by_view_id(&self, view_id: &str) -> Result<Option<StoredRecord>> {
+        let ctx = SpaceContext::active()?;
+        // The GraphQL API answers when the database of the node can not be read
+        match Self::node_view(&ctx, view_id).await {
+            Ok(doc) => return doc.map(|doc| Self::open(&ctx.keys, doc)).transpose(),
+            Err(e) => warn!("read view {} from the node database err: {:?}", view_id, e),
+        }
+        let request_body = ctx.request_body(
+            record_by_view_id::QUERY,
+            record_by_view_id::OPERATION_NAME,
+            record_by_view_id::Variables {
+                view_id: view_id.to_string(),
+            },
+        )?;
+        let data: Option<record_by_view_id::ResponseData> = Self::query(&request_body).await?;
+
+        match data.and_then(|res| res.record) {
+            None => Ok(None),
+            Some(view) => Ok(Some(Self::open(&ctx.keys, Self::from_view(view))?)),
+        }
+    }
+
+    async fn page(
+        &self,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+    ) -> Result<StoredPage> {
+        let ctx = SpaceContext::active()?;
+        let keyword = Self::keyword(filter);
+        // Counting by keyword loads all the records, so only the first page is counted
+        let total_count = match (keyword, &after) {
+            (Some(_), Some(_)) => 0,
+            _ => Self::count_in(&ctx, filter, keyword).await?,
+        };
+        let mut page = Self::scan(&ctx, filter, sort, limit, after, keyword).await?;
+        page.total_count = total_count;
+        Ok(page)
+    }
+
+    async fn count(&self, filter: &RecordQuery) -> Result<i64> {
+        Self::count_in(&SpaceContext::active()?, filter, Self::keyword(filter)).await
+    }
+
+    /// The version is a document of its own, so it is replicated with the record and the
+    /// edits of all the devices are listed
+    async fn append_version(&self, document_id: &str, version: RecordVersion) -> Result<()> {
+        Self::append_version_in(&SpaceContext::active()?, document_id, version).await
+    }
+
+    /// The versions kept on this device before they were documents are listed too
+    async fn versions(&self, document_id: &str) -> Result<Vec<RecordVersion>> {
+        Self::versions_in(&SpaceContext::active()?, document_id).await
+    }
+
+    async fn remove_versions(&self, document_id: &str) -> Result<()> {
+        Self::remove_versions_in(&SpaceContext::active()?, document_id).await
+    }
+
+    fn content_hash(&self, content: &str) -> Result<String> {
+        let ctx = SpaceContext::active()?;
+        Ok(crypto::keyed_content_hash(ctx.key()?, content))
+    }
+
+    /// The versions before the encryption published the hash without a key
+    fn legacy_content_hash(&self, content: &str) -> Option<String> {
+        Some(string::content_hash(content))
+    }
+
+    /// Each device encrypts the records it created in the active space, in cleartext or with
+    /// a group key replaced by pairing. Only the latest view is replaced. The earlier
+    /// operations are not scrubbed, they still carry the old content in the node database
+    /// and replicate with it
+    async fn encrypt_legacy_records(&self) -> Result<usize> {
+        let ctx = SpaceContext::active()?;
+        let filter = RecordQuery {
+            device: Some(device_id()?),
+            include_trash: true,
+            ..Default::default()
+        };
+        let (mut encrypted_cnt, mut skipped_cnt) = (0, 0);
+        let mut start_cursor = None;
+        loop {
+            let page = Self::list(
+                &ctx,
+                &filter,
+                &RecordSort::default(),
+                Self::LEGACY_BATCH_SIZE,
+                start_cursor,
+                true,
+            )
+            .await?;
+
+            for doc in page.records {
+                let content = &doc.record.content;
+                if crypto::is_encrypted(content) && crypto::decrypt(ctx.key()?, content).is_ok() {
+                    continue;
+                }
+                let (view_id, document_id) = (doc.view_id.clone(), doc.document_id.clone());
+                let r = match Self::open(&ctx.keys, doc) {
+                    Ok(doc) => doc.record,
+                    Err(e) => {
+                        warn!("skip legacy record {} err: {:?}", document_id, e);
+                        skipped_cnt += 1;
+                        continue;
+                    }
+                };
+                let content_hash = self.content_hash(&r.content)?;
+                let fields = [
+                    ("content", OperationValue::String(r.content)),
+                    (
+                        "content_preview",
+                        OperationValue::String(r.content_preview.unwrap_or_default()),
+                    ),
+                    ("md5", OperationValue::String(String::new())),
+                    ("content_hash", OperationValue::String(content_hash)),
+                ];
+                self.update(&view_id, &fields).await?;
+                encrypted_cnt += 1;
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+        if encrypted_cnt > 0 {
+            info!("encrypt {} legacy records", encrypted_cnt);
+        }
+        if skipped_cnt == 0 {
+            LEGACY_ENCRYPTED.store(true, Ordering::SeqCst);
+        }
+        Ok(encrypted_cnt)
+    }
+
+    /// Before each space had its schemas, the records of all the spaces were in the schemas
+    /// of the lock file. The schemas replaced by pairing or by removing a member of a space
+    /// are only read to move the records out of them. The records of the other devices are
+    /// moved by their devices, the old documents stay in the node database and replicate
+    /// with the devices still supporting the old schemas
+    async fn move_space_records(&self) -> Result<usize> {
+        let spaces = SpaceDao::list()?.spaces;
+        let mut targets = vec![];
+        for space in spaces.into_iter().filter(|s| s.schemas.is_some()) {
+            let ctx = SpaceContext::of(&space)?;
+            targets.push((space, ctx));
+        }
+
+        let mut moved_cnt = 0;
+        if !targets.is_empty() {
+            let lock = SpaceContext::with_schemas(None, vec![])?;
+            moved_cnt += Self::move_records(&lock, &targets).await?;
+        }
+        for target in targets.iter() {
+            for replaced in target.0.replaced_schemas.iter() {
+                let from = SpaceContext::with_schemas(Some(replaced), vec![])?;
+                moved_cnt += Self::move_records(&from, std::slice::from_ref(target)).await?;
+            }
+        }
+        Ok(moved_cnt)
+    }
+}
@@ -0,0 +1,119 @@
+//!
+//! Local list of the paired devices, the node only replicates with them
+//!
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use parking_lot::Mutex;
+
+use crate::models::pairing::PairedDevice;
+use crate::utils::dir::app_data_dir;
+use crate::utils::json;
+
+pub struct PairedDeviceDao;
+
+impl PairedDeviceDao {
+    const FILE_NAME: &'static str = "paired_devices.json";
+
+    pub fn list() -> Result<Vec<PairedDevice>> {
+        Self::read(&Self::path()?)
+    }
+
+    /// Devices sharing the space
+    pub fn members(space_id: &str) -> Result<Vec<PairedDevice>> {
+        let mut devices = Self::list()?;
+        devices.retain(|d| d.spaces.iter().any(|s| s.eq(space_id)));
+        Ok(devices)
+    }
+
+    /// Add the device or update it when it is paired again, e.g. in another space
+    pub fn save(mut device: PairedDevice) -> Result<()> {
+        Self::modify(|devices| {
+            if let Some(idx) = devices
+                .iter()
+                .position(|d| d.device_id.eq(&device.device_id))
+            {
+                let old = devices.remove(idx);
+                for space in old.spaces {
+                    if !device.spaces.contains(&space) {
+                        device.spaces.push(space);
+                    }
+                }
+            }
+            info!("save paired device: {}", device.device_id);
+            devices.push(device);
+            Ok(())
+        })
+    }
+
+    pub fn rename(device_id: &str, name: String) -> Result<()> {
+        Self::modify(|devices| {
+            let device = devices
+                .iter_mut()
+                .find(|d| d.device_id.eq(device_id))
+                .ok_or(anyhow!("paired device not found: {}", device_id))?;
+            device.name = name;
+            Ok(())
+        })
+    }
+
+    /// Stop sharing the space with the device, the device is removed when it shares no
+    /// space any more
+    pub fn remove_from_space(device_id: &str, space_id: &str) -> Result<()> {
+        Self::modify(|devices| {
+            let device = devices
+                .iter_mut()
+                .find(|d| d.device_id.eq(device_id))
+                .ok_or(anyhow!("paired device not found: {}", device_id))?;
+            device.spaces.retain(|s| s.ne(space_id));
+            devices.retain(|d| !d.spaces.is_empty());
+            info!("remove paired device {} from space {}", device_id, space_id);
+            Ok(())
+        })
+    }
+
+    /// Forget the removed space, the devices sharing no other space are removed
+    pub fn remove_space(space_id: &str) -> Result<()> {
+        Self::modify(|devices| {
+            for device in devices.iter_mut() {
+                device.spaces.retain(|s| s.ne(space_id));
+            }
+            devices.retain(|d| !d.spaces.is_empty());
+            Ok(())
+        })
+    }
+
+    pub fn remove(device_id: &str) -> Result<()> {
+        Self::modify(|devices| {
+            let len = devices.len();
+            devices.retain(|d| d.device_id.ne(device_id));
+            if devices.len() == len {
+                return Err(anyhow!("paired device not found: {}", device_id));
+            }
+            info!("remove paired device: {}", device_id);
+            Ok(())
+        })
+    }
+
+    fn modify<F: FnOnce(&mut Vec<PairedDevice>) -> Result<()>>(f: F) -> Result<()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+
+        let _guard = LOCK.lock();
+        let path = Self::path()?;
+        let mut devices = Self::read(&path)?;
+        f(&mut devices)?;
+        json::save(&path, &devices)
+    }
+
+    fn read(path: &PathBuf) -> Result<Vec<PairedDevice>> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        json::read(path)
+    }
+
+    fn path() -> Result<PathBuf> {
+        Ok(app_data_dir()?.join(Self::FILE_NAME))
+    }
+}
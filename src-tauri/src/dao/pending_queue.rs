@@ -0,0 +1,285 @@
+//!
+//! Write-ahead log of the record writes which could not reach the node
+//!
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use log::{error, warn};
+use parking_lot::Mutex;
+
+use crate::models::pending_operation::{PendingEntry, PendingOperation};
+use crate::utils::json;
+
+/// The operations are appended to a JSON lines file before the write returns, so they
+/// survive a restart of the app, and removed once they are replayed
+pub struct PendingQueue {
+    path: PathBuf,
+    entries: Mutex<Vec<PendingEntry>>,
+}
+
+impl PendingQueue {
+    pub const FILE_NAME: &'static str = "pending_operations.jsonl";
+
+    /// A log which can not be read is moved aside, so the queue does not overwrite the
+    /// operations in it
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let entries = match Self::load(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let now = chrono::Local::now().timestamp();
+                let bad_path = path.with_extension(format!("jsonl.{}.bad", now));
+                error!(
+                    "load pending operations from {} err, moved to {}: {:?}",
+                    path.display(),
+                    bad_path.display(),
+                    e
+                );
+                fs::rename(&path, &bad_path)
+                    .context(format!("failed to move the file \"{}\"", path.display()))?;
+                vec![]
+            }
+        };
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    /// Queued operations in the order they were written
+    pub fn entries(&self) -> Vec<PendingEntry> {
+        self.entries.lock().clone()
+    }
+
+    /// Whether an operation can be replayed now, the others wait for their retry time
+    pub fn has_due(&self, now: i64) -> bool {
+        self.entries.lock().iter().any(|entry| entry.is_due(now))
+    }
+
+    /// Queue the operation. A copy of content which is already queued replaces the queued
+    /// copy, the replay would merge them into one record anyway
+    pub fn push(&self, operation: PendingOperation) -> Result<()> {
+        let mut entries = self.entries.lock();
+        let replaced = match &operation {
+            PendingOperation::Insert { record, .. } => {
+                let len = entries.len();
+                entries.retain(|entry| match &entry.operation {
+                    PendingOperation::Insert { record: queued, .. } => {
+                        queued.content_hash.ne(&record.content_hash)
+                    }
+                    _ => true,
+                });
+                entries.len() != len
+            }
+            _ => false,
+        };
+
+        let entry = PendingEntry {
+            seq: entries.last().map(|entry| entry.seq + 1).unwrap_or(1),
+            queued_at: chrono::Local::now().timestamp(),
+            operation,
+            attempts: 0,
+            retry_at: 0,
+        };
+        if replaced {
+            entries.push(entry);
+            Self::rewrite(&self.path, &entries)
+        } else {
+            Self::append(&self.path, &entry)?;
+            entries.push(entry);
+            Ok(())
+        }
+    }
+
+    pub fn remove(&self, seq: u64) -> Result<()> {
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.seq != seq);
+        Self::rewrite(&self.path, &entries)
+    }
+
+    /// Keep the operation whose replay failed, it is replayed again after a delay
+    pub fn retry_later(&self, seq: u64, now: i64) -> Result<()> {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.seq == seq) {
+            entry.retry_later(now);
+        }
+        Self::rewrite(&self.path, &entries)
+    }
+
+    fn load(path: &PathBuf) -> Result<Vec<PendingEntry>> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let mut entries = vec![];
+        let mut skipped = false;
+        for line in fs::read_to_string(path)?.lines() {
+            match json::parse(line) {
+                Ok(entry) => entries.push(entry),
+                // The last line is cut when the app stopped while appending it
+                Err(e) => {
+                    warn!("skip invalid pending operation {}: {:?}", line, e);
+                    skipped = true;
+                }
+            }
+        }
+        // Drop the invalid lines, the next operation would be appended to the cut line
+        if skipped {
+            Self::rewrite(path, &entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn append(path: &PathBuf, entry: &PendingEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("failed to open the file \"{}\"", path.display()))?;
+        writeln!(file, "{}", json::stringify(entry)?)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    // Write a new file and rename it, the log is never left half written
+    fn rewrite(path: &PathBuf, entries: &[PendingEntry]) -> Result<()> {
+        let tmp_path = path.with_extension("jsonl.tmp");
+        let mut file = fs::File::create(&tmp_path).context(format!(
+            "failed to create the file \"{}\"",
+            tmp_path.display()
+        ))?;
+        for entry in entries {
+            writeln!(file, "{}", json::stringify(entry)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Queue in a new file of the temp dir
+    #[cfg(test)]
+    pub fn temporary() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "pending_operations_{}_{}.jsonl",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_file(&path);
+        Self::open(path).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use crate::dao::pending_queue::PendingQueue;
+    use crate::models::pending_operation::PendingOperation;
+    use crate::models::record::Record;
+
+    fn insert(content: &str) -> PendingOperation {
+        PendingOperation::Insert {
+            record: Box::new(Record {
+                content: content.to_string(),
+                content_hash: content.to_string(),
+                ..Default::default()
+            }),
+            space_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_push_and_reopen() {
+        let queue = PendingQueue::temporary();
+        assert!(queue.is_empty());
+        queue.push(insert("a")).unwrap();
+        queue
+            .push(PendingOperation::Delete {
+                view_id: "view".to_string(),
+            })
+            .unwrap();
+        // The same content copied again replaces the queued copy
+        queue.push(insert("a")).unwrap();
+
+        let reopened = PendingQueue::open(queue.path.clone()).unwrap();
+        let entries = reopened.entries();
+        assert_eq!(entries, queue.entries());
+        let seqs: Vec<u64> = entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+
+        reopened.remove(2).unwrap();
+        let reopened = PendingQueue::open(queue.path.clone()).unwrap();
+        assert_eq!(reopened.entries()[0].operation, insert("a"));
+    }
+
+    #[test]
+    fn test_skip_cut_line() {
+        let queue = PendingQueue::temporary();
+        queue.push(insert("a")).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&queue.path).unwrap();
+        write!(file, "{{\"seq\":2,\"queued_at\"").unwrap();
+
+        let reopened = PendingQueue::open(queue.path.clone()).unwrap();
+        assert_eq!(reopened.entries(), queue.entries());
+        reopened.push(insert("b")).unwrap();
+        assert_eq!(
+            PendingQueue::open(queue.path.clone())
+                .unwrap()
+                .entries()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_retry_later() {
+        let queue = PendingQueue::temporary();
+        queue.push(insert("a")).unwrap();
+        assert!(queue.has_due(0));
+        queue.retry_later(1, 100).unwrap();
+        assert!(!queue.has_due(100));
+
+        let reopened = PendingQueue::open(queue.path.clone()).unwrap();
+        let entries = reopened.entries();
+        assert_eq!((entries[0].attempts, entries[0].retry_at), (1, 130));
+        assert!(reopened.has_due(130));
+    }
+
+    #[test]
+    fn test_move_unreadable_log() {
+        let queue = PendingQueue::temporary();
+        fs::write(&queue.path, [0xff, 0xfe, b'\n']).unwrap();
+
+        let reopened = PendingQueue::open(queue.path.clone()).unwrap();
+        assert!(reopened.is_empty());
+        assert!(!queue.path.exists());
+        let dir = queue.path.parent().unwrap();
+        let stem = queue
+            .path
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let bad: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                name.starts_with(&format!("{}.", stem)) && name.ends_with(".bad")
+            })
+            .collect();
+        assert_eq!(bad.len(), 1);
+        assert_eq!(fs::read(&bad[0]).unwrap(), vec![0xff, 0xfe, b'\n']);
+        fs::remove_file(&bad[0]).unwrap();
+    }
+}
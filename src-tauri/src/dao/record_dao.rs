@@ -1,139 +1,1071 @@
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use anyhow::Result;
-use graphql_client::{GraphQLQuery, Response};
-use log::{debug, error, info};
+use anyhow::{anyhow, bail, Result};
+use log::{debug, error, info, warn};
 use p2panda_rs::document::DocumentViewId;
-use p2panda_rs::operation::plain::PlainOperation;
-use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationId, OperationValue};
-use p2panda_rs::schema::SchemaId;
-
-use crate::graphql::record_by_pages::OrderDirection;
-use crate::graphql::{
-    record_by_md5_query, record_by_pages, record_counts, GraphRecordDocuments, GraphRecordOrderBy,
-    GraphRecordPageDocuments, RecordByMd5Query, RecordByPages, RecordCounts,
-};
+use p2panda_rs::operation::OperationValue;
+
+use crate::dao::memory_record_store::MemoryRecordStore;
+use crate::dao::p2panda_record_store::P2pandaRecordStore;
+use crate::dao::pending_queue::PendingQueue;
+use crate::dao::record_store::{InvalidOperation, RecordStore, StoreUnavailable, StoredRecord};
+use crate::dao::selective_record_store::SelectiveRecordStore;
+use crate::dao::space_dao::SpaceDao;
 use crate::handler::global_handler::GlobalHandler;
 use crate::handler::model::MessageTypeEnum;
+use crate::models::batch::BatchItemResult;
+use crate::models::eviction::EvictionProgress;
+use crate::models::image_data::ImageData;
+use crate::models::pending_operation::PendingOperation;
 use crate::models::record;
-use crate::models::record::Record;
-use crate::p2panda::graphql::GraphQLHandler;
+use crate::models::record::{DataTypeEnum, Record, RecordVersion};
+use crate::models::record_list::{
+    PageCursor, RecordContent, RecordFilter, RecordItem, RecordPage, RecordQuery, RecordSort,
+    RecordSortField, SortDirection, MAX_PAGE_SIZE,
+};
+use crate::models::storage::{select_quota_evictions, RecordSize};
+use crate::p2panda::group_key::space_keys;
+use crate::p2panda::key_pair::device_id;
+use crate::utils::dir::{app_data_dir, app_data_local_records_dir};
 use crate::utils::ip::local_ip;
-use crate::utils::string;
+use crate::utils::{image, json, string};
 
-pub struct RecordDao;
+/// Record logic on top of a `RecordStore`, the app uses the records in the p2panda node and
+/// the local-only ones
+pub struct RecordDao {
+    store: Arc<dyn RecordStore>,
+    // writes made while the store was unavailable, see `replay_pending`
+    pending: PendingQueue,
+    replaying: tokio::sync::Mutex<()>,
+}
 
 impl RecordDao {
-    pub async fn insert_if_not_exist(mut r: Record) -> Result<()> {
-        let now = chrono::Local::now().timestamp();
-        let md5_str = string::md5(r.content.as_str());
-        r.md5 = md5_str.clone();
-        r.create_time = now;
+    const PURGE_BATCH_SIZE: i64 = 50;
+
+    const EVICTION_BATCH_SIZE: usize = 50;
+    // Publishing fails now and then while the node is busy, e.g. replicating with the peers
+    const EVICTION_MAX_ATTEMPTS: u32 = 3;
+    const EVICTION_RETRY_MILLIS: u64 = 200;
+
+    // Oldest records first, they are removed first by the retention rules
+    const OLDEST_FIRST: RecordSort = RecordSort {
+        field: RecordSortField::CreateTime,
+        direction: SortDirection::Asc,
+    };
+
+    pub fn new(store: Arc<dyn RecordStore>, pending: PendingQueue) -> Self {
+        Self {
+            store,
+            pending,
+            replaying: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// It is built when the listeners are registered at setup, a failure stops the setup
+    pub fn global() -> Result<&'static RecordDao> {
+        static RECORD_DAO: OnceLock<RecordDao> = OnceLock::new();
+
+        if let Some(dao) = RECORD_DAO.get() {
+            return Ok(dao);
+        }
+        let dao = Self::open()?;
+        Ok(RECORD_DAO.get_or_init(|| dao))
+    }
+
+    fn open() -> Result<Self> {
+        let pending_path = app_data_dir()?.join(PendingQueue::FILE_NAME);
+        let local = MemoryRecordStore::open(&device_id()?, app_data_local_records_dir()?)?
+            .with_active_space(SpaceDao::active_id);
+        Ok(RecordDao::new(
+            Arc::new(SelectiveRecordStore::new(
+                Arc::new(P2pandaRecordStore),
+                Arc::new(local),
+            )),
+            PendingQueue::open(pending_path)?,
+        ))
+    }
+
+    /// Save the copied content, return the id of the document holding it. It is queued when
+    /// the node can not be reached, so the copy is not lost, and the id is empty
+    pub async fn insert_if_not_exist(&self, mut r: Record) -> Result<String> {
+        // md5 is only kept by the local-only records, the p2panda store leaves it empty as it
+        // reveals the content. The versions matching on md5 can not pair, so they never sync
+        // with this device; the records they wrote before are matched by the legacy hash
+        r.md5 = string::md5(r.content.as_str());
+        r.content_hash = self.content_hash(&r.content)?;
+        r.create_time = chrono::Local::now().timestamp();
 
-        let res = RecordDao::find_record_by_md5(md5_str).await?;
+        self.submit(PendingOperation::Insert {
+            record: Box::new(r),
+            space_id: String::new(),
+        })
+        .await
+    }
+
+    /// Return the document id, the copy of an existing content updates its document
+    async fn insert_record(&self, r: Record) -> Result<String> {
+        let mut res = self.find_record_by_content(&r.content).await?;
+        // A synced and a local-only copy are not merged, the one the copy belongs to is kept
+        if res.iter().any(|doc| doc.record.sync == r.sync) {
+            res.retain(|doc| doc.record.sync == r.sync);
+        }
         match res.len() {
             // no record
             0 => {
-                Self::create_record(r).await?;
+                // The document id is the id of the create operation
+                let document_id = self.create_record(r).await?;
                 debug!("insert new record successfully with len 0");
+                Ok(document_id)
             }
-            // find record
+            // find record, the duplicates created by other devices are merged first
             _ => {
-                Self::update_record_with_fields(
-                    &OperationId::from_str(&res[0].meta.as_ref().unwrap().view_id)?.into(),
-                    &[
-                        ("create_time", OperationValue::Integer(now)),
-                        (
-                            "latest_addr",
-                            OperationValue::String(local_ip().to_string()),
-                        ),
-                    ],
-                )
-                .await?;
-                debug!("update record successfully: {:?}", r);
+                let existing = self.merge_duplicates(res).await?;
+                // A queued copy replayed later keeps the time it was copied
+                let create_time = r.create_time.max(existing.record.create_time);
+                let view_id = self
+                    .store
+                    .update(
+                        &existing.view_id,
+                        &[
+                            ("create_time", OperationValue::Integer(create_time)),
+                            (
+                                "latest_addr",
+                                OperationValue::String(local_ip().to_string()),
+                            ),
+                            // Copy the record again will restore it from trash
+                            ("is_deleted", OperationValue::Integer(0)),
+                        ],
+                    )
+                    .await?;
+                debug!("update record successfully: {:?}, view id: {}", r, view_id);
+                Ok(existing.document_id)
+            }
+        }
+    }
+
+    /// Replay the writes queued while the store was unavailable, in the order they were
+    /// made. It stops at the first write the store is still unavailable for. A write the
+    /// store rejects is dropped, e.g. the record was deleted meanwhile, a write failing for
+    /// other reasons is kept and replayed again after a delay, the later writes go on
+    pub async fn replay_pending(&self) -> Result<usize> {
+        // A replay is running, the writes must not be applied twice
+        let _guard = match self.replaying.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return Ok(0),
+        };
+
+        let now = chrono::Local::now().timestamp();
+        let mut replayed_cnt = 0;
+        for entry in self.pending.entries() {
+            if !entry.is_due(now) {
+                continue;
+            }
+            let res = match Self::open_pending(entry.operation) {
+                Ok(operation) => self.apply(&operation).await,
+                // The keys of the space are gone, the copy can not be read any more
+                Err(err) => Err(InvalidOperation(format!("open the queued copy: {}", err)).into()),
+            };
+            match res {
+                Err(err) if StoreUnavailable::is(&err) => {
+                    debug!("store still unavailable: {}", err);
+                    break;
+                }
+                Err(err) if InvalidOperation::is(&err) => {
+                    error!(
+                        "replay pending operation {} rejected, dropped: {:?}",
+                        entry.seq, err
+                    );
+                    self.pending.remove(entry.seq)?;
+                }
+                Err(err) => {
+                    warn!(
+                        "replay pending operation {} error, attempts {}: {:?}",
+                        entry.seq,
+                        entry.attempts + 1,
+                        err
+                    );
+                    self.pending.retry_later(entry.seq, now)?;
+                }
+                Ok(_) => {
+                    replayed_cnt += 1;
+                    self.pending.remove(entry.seq)?;
+                }
+            }
+        }
+
+        if replayed_cnt > 0 {
+            info!("[replay_pending] {} operations replayed", replayed_cnt);
+        }
+        Ok(replayed_cnt)
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Writes are queued when the store is unavailable, or earlier writes are still queued so
+    // they are applied in order. The writes waiting for a retry after a failed replay do not
+    // hold the new writes back. A queued write returns an empty view id
+    async fn submit(&self, operation: PendingOperation) -> Result<String> {
+        if self.has_pending() {
+            self.replay_pending().await?;
+        }
+        if !self.pending.has_due(chrono::Local::now().timestamp()) {
+            match self.apply(&operation).await {
+                Err(err) if StoreUnavailable::is(&err) => {
+                    warn!("queue the operation until the store is available: {}", err);
+                }
+                res => return res,
+            }
+        }
+
+        self.pending.push(Self::seal_pending(operation)?)?;
+        Ok(String::new())
+    }
+
+    // The queued copies are sealed with the key of the active space, the queue is a file of
+    // the app data dir
+    fn seal_pending(operation: PendingOperation) -> Result<PendingOperation> {
+        let space_id = SpaceDao::active_id()?;
+        operation.seal(&space_id, &space_keys(&space_id)?[0])
+    }
+
+    fn open_pending(operation: PendingOperation) -> Result<PendingOperation> {
+        match &operation {
+            PendingOperation::Insert { space_id, .. } if !space_id.is_empty() => {
+                let keys = space_keys(space_id)?;
+                operation.open(&keys)
             }
+            _ => Ok(operation),
+        }
+    }
+
+    async fn apply(&self, operation: &PendingOperation) -> Result<String> {
+        match operation {
+            PendingOperation::Insert { record, .. } => self.insert_record(*record.clone()).await,
+            PendingOperation::Update { view_id, fields } => {
+                self.store
+                    .update(view_id, &PendingOperation::operation_fields(fields))
+                    .await
+            }
+            PendingOperation::Delete { view_id } => self.store.delete(view_id).await,
+        }
+    }
+
+    pub async fn create_record(&self, record: Record) -> Result<String> {
+        self.store.create(record).await
+    }
+
+    /// Hash identifying the content in the store, see `RecordStore::content_hash`
+    pub fn content_hash(&self, content: &str) -> Result<String> {
+        self.store.content_hash(content)
+    }
+
+    /// Encrypt the records written in cleartext or with a replaced group key
+    pub async fn encrypt_legacy_records(&self) -> Result<usize> {
+        self.store.encrypt_legacy_records().await
+    }
+
+    /// Move the records of this device into the schemas of their spaces
+    pub async fn move_space_records(&self) -> Result<usize> {
+        self.store.move_space_records().await
+    }
+
+    /// Merge the records with the same content. Devices copying the same content before
+    /// the documents are replicated create a document each, the merge runs on all of them
+    /// and they all keep the same document, so the history converges
+    pub async fn reconcile_duplicates(&self) -> Result<usize> {
+        let filter = RecordQuery {
+            include_trash: true,
+            ..Default::default()
         };
-        Ok(())
-    }
-
-    pub async fn create_record(record: Record) -> Result<String> {
-        let opt = OperationBuilder::new(&SchemaId::new(record::SCHEMA_ID).unwrap())
-            .action(OperationAction::Create)
-            .fields(&[
-                ("content", OperationValue::String(record.content)),
-                (
-                    "content_preview",
-                    OperationValue::String(record.content_preview.unwrap_or_default()),
-                ),
-                ("data_type", OperationValue::String(record.data_type)),
-                ("md5", OperationValue::String(record.md5)),
-                ("create_time", OperationValue::Integer(record.create_time)),
-                ("is_favorite", OperationValue::Integer(record.is_favorite)),
-                ("tags", OperationValue::String(record.tags)),
-                ("latest_addr", OperationValue::String(record.latest_addr)),
-                ("is_deleted", OperationValue::Integer(record.is_deleted)),
-            ])
-            .build()?;
-
-        let mut handler = GraphQLHandler::global().lock().await;
-        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
-        info!("create record success, opt id: {}", res);
+        // The local-only records are not merged with the synced ones
+        let mut by_hash: HashMap<(String, bool), Vec<StoredRecord>> = HashMap::new();
+        let mut start_cursor = None;
+
+        loop {
+            let page = self
+                .store
+                .page(&filter, &Self::OLDEST_FIRST, MAX_PAGE_SIZE, start_cursor)
+                .await?;
+
+            for doc in page.records {
+                by_hash
+                    .entry((doc.record.content_hash.clone(), doc.record.sync))
+                    .or_default()
+                    .push(doc);
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+
+        let mut removed_cnt = 0;
+        for duplicates in by_hash.into_values().filter(|docs| docs.len() > 1) {
+            removed_cnt += duplicates.len() - 1;
+            self.merge_duplicates(duplicates).await?;
+        }
+
+        if removed_cnt > 0 {
+            info!(
+                "[reconcile_duplicates] {} duplicate records merged",
+                removed_cnt
+            );
+        }
+        Ok(removed_cnt)
+    }
+
+    /// Keep the document with the smallest id, which every device agrees on, carry over
+    /// the favorite and tags of the others and delete them. Return the kept record
+    async fn merge_duplicates(&self, mut duplicates: Vec<StoredRecord>) -> Result<StoredRecord> {
+        duplicates.sort_by(|a, b| a.document_id.cmp(&b.document_id));
+        let mut kept = duplicates.remove(0);
+        if duplicates.is_empty() {
+            return Ok(kept);
+        }
+
+        let fields = Self::merged_fields(&kept.record, &duplicates);
+        if !fields.is_empty() {
+            kept.view_id = self.store.update(&kept.view_id, &fields).await?;
+        }
+        for duplicate in duplicates {
+            self.store.delete(&duplicate.view_id).await?;
+            self.remove_versions(&duplicate.document_id).await;
+            debug!(
+                "merge duplicate record {} into {}",
+                duplicate.document_id, kept.document_id
+            );
+        }
+        Ok(kept)
+    }
+
+    /// Fields of the kept record which change after merging the duplicates
+    fn merged_fields(
+        kept: &Record,
+        duplicates: &[StoredRecord],
+    ) -> Vec<(&'static str, OperationValue)> {
+        let mut is_favorite = kept.is_favorite;
+        let mut tags = kept.tags.clone();
+        let mut create_time = kept.create_time;
+        let mut is_deleted = kept.is_deleted;
+        for duplicate in duplicates {
+            let r = &duplicate.record;
+            is_favorite = is_favorite.max(r.is_favorite);
+            tags = record::merge_tags(&tags, &record::parse_tags(&r.tags), &[]);
+            // The latest copy decides the position in the history
+            create_time = create_time.max(r.create_time);
+            // Alive on any device means the record is still wanted
+            if r.is_deleted == 0 {
+                is_deleted = 0;
+            }
+        }
+
+        let mut fields = vec![];
+        if is_favorite != kept.is_favorite {
+            fields.push(("is_favorite", OperationValue::Integer(is_favorite)));
+        }
+        if tags.ne(&kept.tags) {
+            fields.push(("tags", OperationValue::String(tags)));
+        }
+        if create_time != kept.create_time {
+            fields.push(("create_time", OperationValue::Integer(create_time)));
+        }
+        if is_deleted != kept.is_deleted {
+            fields.push(("is_deleted", OperationValue::Integer(is_deleted)));
+        }
+        fields
+    }
+
+    pub async fn count_records(&self, filter: &RecordQuery) -> Result<i64> {
+        self.store.count(filter).await
+    }
+
+    /// Records with the same content, the content is not loaded
+    pub async fn find_record_by_hash(&self, content_hash: &str) -> Result<Vec<StoredRecord>> {
+        self.store.find_by_hash(content_hash).await
+    }
+
+    /// Records holding the content, including the ones which still carry the hash of the
+    /// older versions, see `RecordStore::legacy_content_hash`
+    pub async fn find_record_by_content(&self, content: &str) -> Result<Vec<StoredRecord>> {
+        let mut res = self
+            .find_record_by_hash(&self.content_hash(content)?)
+            .await?;
+        if let Some(legacy_hash) = self.store.legacy_content_hash(content) {
+            for doc in self.find_record_by_hash(&legacy_hash).await? {
+                if !res.iter().any(|d| d.document_id.eq(&doc.document_id)) {
+                    res.push(doc);
+                }
+            }
+        }
         Ok(res)
     }
 
-    pub async fn find_record_by_md5(md5: String) -> Result<Vec<GraphRecordDocuments>> {
-        let handler = &mut GraphQLHandler::global().lock().await;
+    /// Page of records matching the filter, `cursor` is the `next_cursor` of the last page
+    pub async fn list_records(
+        &self,
+        filter: RecordFilter,
+        sort: RecordSort,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<RecordPage> {
+        let filter = RecordQuery::from(filter);
+        let limit = limit.clamp(1, MAX_PAGE_SIZE) as usize;
+        let (mut start_cursor, mut total_count) = match cursor {
+            Some(cursor) => {
+                let cursor = PageCursor::decode(&cursor, &filter, &sort)?;
+                (Some(cursor.after), Some(cursor.total_count))
+            }
+            None => (None, None),
+        };
+
+        let mut page = RecordPage::default();
+        // Tags are checked after the query, so more pages may be needed to fill the limit
+        loop {
+            let res = self
+                .store
+                .page(&filter, &sort, limit as i64, start_cursor)
+                .await?;
+            // The count of the first page is passed on with the cursor
+            page.total_count = *total_count.get_or_insert(res.total_count);
+
+            let docs_len = res.records.len();
+            for (idx, doc) in res.records.into_iter().enumerate() {
+                let doc_cursor = doc.cursor.clone();
+                if filter.matches_tags(&doc.record.tags) {
+                    page.items.push(Self::record_item(doc));
+                }
+
+                if page.items.len() >= limit {
+                    if idx + 1 < docs_len || res.end_cursor.is_some() {
+                        let cursor = PageCursor::new(doc_cursor, page.total_count, &filter, &sort);
+                        page.next_cursor = Some(cursor.encode()?);
+                    }
+                    return Ok(page);
+                }
+            }
+
+            match res.end_cursor {
+                None => return Ok(page),
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+    }
+
+    fn record_item(doc: StoredRecord) -> RecordItem {
+        let r = doc.record;
+        RecordItem {
+            view_id: doc.view_id,
+            document_id: doc.document_id,
+            owner: doc.owner,
+            content_preview: r.content_preview,
+            data_type: r.data_type,
+            md5: r.md5,
+            create_time: r.create_time,
+            is_favorite: r.is_favorite != 0,
+            tags: record::parse_tags(&r.tags),
+            latest_addr: r.latest_addr,
+            delete_time: (r.is_deleted != 0).then_some(r.is_deleted),
+            source_app: r.source_app,
+            mime_type: r.mime_type,
+            byte_size: r.byte_size,
+            subtype: r.subtype,
+            expires_at: r.expires_at,
+            sync: r.sync,
+        }
+    }
+
+    /// Load the full content of a listed record
+    pub async fn get_record_content(&self, view_id: String) -> Result<RecordContent> {
+        let doc = self
+            .find_record_by_view_id(&view_id)
+            .await?
+            .ok_or(anyhow!("record not found for view id: {}", view_id))?;
 
-        let request_body = RecordByMd5Query::build_query(record_by_md5_query::Variables { md5 });
+        Ok(RecordContent {
+            view_id,
+            data_type: doc.record.data_type,
+            content: doc.record.content,
+        })
+    }
 
-        let res = handler
-            .cli
-            .post(handler.endpoint())
-            .json(&request_body)
-            .send()
+    pub async fn find_record_by_view_id(&self, view_id: &str) -> Result<Option<StoredRecord>> {
+        self.store.find_by_view_id(view_id).await
+    }
+
+    /// Latest view of the document, alive or in trash, the content is not loaded
+    pub async fn find_record_by_document_id(
+        &self,
+        document_id: &str,
+    ) -> Result<Option<StoredRecord>> {
+        let filter = RecordQuery {
+            document_ids: Some(vec![document_id.to_string()]),
+            include_trash: true,
+            ..Default::default()
+        };
+        let page = self
+            .store
+            .page(&filter, &RecordSort::default(), 1, None)
             .await?;
-        let response_body: Response<record_by_md5_query::ResponseData> = res.json().await?;
+        Ok(page.records.into_iter().next())
+    }
+
+    /// Replace the content of a text record, the replaced version is kept in the history
+    pub async fn edit_record(&self, view_id: &DocumentViewId, content: String) -> Result<String> {
+        let old = self
+            .find_record_by_view_id(&view_id.to_string())
+            .await?
+            .ok_or(anyhow!("record not found for view id: {}", view_id))?;
 
-        match response_body.data {
-            None => Ok(vec![]),
-            Some(res) => Ok(res
-                .all_record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f
-                .documents),
+        if old.record.data_type.ne(&String::from(DataTypeEnum::TEXT)) {
+            bail!("only text record can be edited");
+        }
+
+        let md5_str = string::md5(&content);
+        let content_hash = self.content_hash(&content)?;
+        let version = RecordVersion {
+            view_id: view_id.to_string(),
+            content: old.record.content,
+            content_preview: old.record.content_preview,
+            content_hash: old.record.content_hash,
+            edit_time: chrono::Local::now().timestamp(),
+        };
+        let res = self
+            .update_record_with_fields(
+                view_id,
+                &[
+                    (
+                        "content_preview",
+                        OperationValue::String(record::text_preview(&content)),
+                    ),
+                    ("byte_size", OperationValue::Integer(content.len() as i64)),
+                    ("content", OperationValue::String(content)),
+                    ("md5", OperationValue::String(md5_str)),
+                    ("content_hash", OperationValue::String(content_hash)),
+                    (
+                        "latest_addr",
+                        OperationValue::String(local_ip().to_string()),
+                    ),
+                ],
+            )
+            .await?;
+
+        info!("edit record success, opt id: {}", res);
+        // The edit is already applied, a version which can not be kept is only logged
+        if let Err(err) = self.store.append_version(&old.document_id, version).await {
+            error!("append version of {} error: {:?}", old.document_id, err);
+        }
+        Ok(res)
+    }
+
+    /// Previous versions of the record written by all the devices, the newest first
+    pub async fn record_history(&self, view_id: &DocumentViewId) -> Result<Vec<RecordVersion>> {
+        let current = self
+            .find_record_by_view_id(&view_id.to_string())
+            .await?
+            .ok_or(anyhow!("record not found for view id: {}", view_id))?;
+
+        self.store.versions(&current.document_id).await
+    }
+
+    /// Versions of a deleted record, a failure leaves them behind and is only logged
+    async fn remove_versions(&self, document_id: &str) {
+        if let Err(err) = self.store.remove_versions(document_id).await {
+            error!("remove versions of {} error: {:?}", document_id, err);
         }
     }
 
     pub async fn update_record_with_fields(
+        &self,
         view_id: &DocumentViewId,
-        fields: &[(impl ToString, OperationValue)],
+        fields: &[(&str, OperationValue)],
     ) -> Result<String> {
-        let opt = OperationBuilder::new(&SchemaId::new(record::SCHEMA_ID).unwrap())
-            .action(OperationAction::Update)
-            .previous(view_id)
-            .fields(fields)
-            .build()?;
-
-        let handler = &mut GraphQLHandler::global().lock().await;
-        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
-        info!("update record success, opt id: {}", res);
-        Ok(res)
+        self.submit(PendingOperation::update(&view_id.to_string(), fields)?)
+            .await
     }
 
-    pub async fn delete_record(view_id: &DocumentViewId) -> Result<String> {
-        let opt = OperationBuilder::new(&SchemaId::new(record::SCHEMA_ID).unwrap())
-            .action(OperationAction::Delete)
-            .previous(view_id)
-            .build()?;
+    pub async fn delete_record(&self, view_id: &DocumentViewId) -> Result<String> {
+        self.submit(PendingOperation::Delete {
+            view_id: view_id.to_string(),
+        })
+        .await
+    }
 
-        let handler = &mut GraphQLHandler::global().lock().await;
-        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
-        info!("delete record success, opt id: {}", res);
+    /// Move the record into trash, the record will be purged after the retention days
+    pub async fn trash_record(&self, view_id: &DocumentViewId) -> Result<String> {
+        let now = chrono::Local::now().timestamp();
+        let res = self
+            .update_record_with_fields(
+                view_id,
+                &[
+                    ("is_deleted", OperationValue::Integer(now)),
+                    (
+                        "latest_addr",
+                        OperationValue::String(local_ip().to_string()),
+                    ),
+                ],
+            )
+            .await?;
+        info!("move record to trash success, opt id: {}", res);
         Ok(res)
     }
 
+    pub async fn restore_record(&self, view_id: &DocumentViewId) -> Result<String> {
+        let res = self
+            .update_record_with_fields(
+                view_id,
+                &[
+                    ("is_deleted", OperationValue::Integer(0)),
+                    (
+                        "latest_addr",
+                        OperationValue::String(local_ip().to_string()),
+                    ),
+                ],
+            )
+            .await?;
+        info!("restore record from trash success, opt id: {}", res);
+        Ok(res)
+    }
+
+    /// Move the record between the synced and the local-only records. It is created again
+    /// in the other store and deleted from the one holding it, so the view and document ids
+    /// change. The peers keep the copies they already received when sync is turned off
+    pub async fn set_record_sync(&self, view_id: &str, sync: bool) -> Result<String> {
+        let doc = self
+            .find_record_by_view_id(view_id)
+            .await?
+            .ok_or(anyhow!("record not found for view id: {}", view_id))?;
+        if doc.record.sync == sync {
+            return Ok(doc.view_id);
+        }
+
+        let versions = self.store.versions(&doc.document_id).await?;
+        let mut record = doc.record;
+        record.sync = sync;
+        let new_view_id = self.store.create(record).await?;
+        // The id of the new document is the id of its create operation
+        for version in versions.into_iter().rev() {
+            self.store.append_version(&new_view_id, version).await?;
+        }
+        self.store.delete(&doc.view_id).await?;
+        self.remove_versions(&doc.document_id).await;
+        info!(
+            "set sync of record {} to {}, new view id: {}",
+            doc.document_id, sync, new_view_id
+        );
+        Ok(new_view_id)
+    }
+
+    /// Hard delete all the records in trash
+    pub async fn empty_trash(&self) -> Result<usize> {
+        let now = chrono::Local::now().timestamp();
+        self.purge_trash(now + 1).await
+    }
+
+    /// Hard delete the records which were moved into trash before `deleted_before`
+    pub async fn purge_trash(&self, deleted_before: i64) -> Result<usize> {
+        let filter = RecordQuery {
+            in_trash: true,
+            deleted_before: Some(deleted_before),
+            ..Default::default()
+        };
+        let sort = RecordSort {
+            field: RecordSortField::DeleteTime,
+            direction: SortDirection::Asc,
+        };
+        let mut results = vec![];
+        let mut start_cursor = None;
+
+        loop {
+            let page = self
+                .store
+                .page(&filter, &sort, Self::PURGE_BATCH_SIZE, start_cursor)
+                .await?;
+
+            // A failed record does not stop the purge, it is purged again next time
+            for record in page.records {
+                let res = self.store.delete(&record.view_id).await;
+                match &res {
+                    Ok(_) => self.remove_versions(&record.document_id).await,
+                    Err(err) => error!("purge record {} error: {:?}", record.view_id, err),
+                }
+                results.push(BatchItemResult::from_result(record.view_id, res));
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+
+        let purged_cnt = BatchItemResult::success_count(&results);
+        if purged_cnt > 0 {
+            info!("[purge_trash] {} records purged", purged_cnt);
+        }
+        if purged_cnt < results.len() {
+            warn!(
+                "[purge_trash] {} records failed to purge",
+                results.len() - purged_cnt
+            );
+        }
+        Ok(purged_cnt)
+    }
+
+    /// Move the non-favorite records of `data_type` created before `created_before` to trash
+    pub async fn expire_records(&self, data_type: String, created_before: i64) -> Result<usize> {
+        let filter = RecordQuery {
+            data_types: Some(vec![data_type.clone()]),
+            favorite: Some(false),
+            created_before: Some(created_before),
+            ..Default::default()
+        };
+        let mut results = vec![];
+        let mut start_cursor = None;
+
+        loop {
+            let page = self
+                .store
+                .page(
+                    &filter,
+                    &Self::OLDEST_FIRST,
+                    Self::PURGE_BATCH_SIZE,
+                    start_cursor,
+                )
+                .await?;
+
+            // A failed record does not stop the retention, it is expired again next time
+            for record in page.records {
+                let res = match DocumentViewId::from_str(&record.view_id) {
+                    Ok(view_id) => self.trash_record(&view_id).await,
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = &res {
+                    error!("expire record {} error: {:?}", record.view_id, err);
+                }
+                results.push(BatchItemResult::from_result(record.view_id, res));
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+
+        let expired_cnt = BatchItemResult::success_count(&results);
+        if expired_cnt > 0 {
+            info!(
+                "[expire_records] {} {} records expired",
+                expired_cnt, data_type
+            );
+        }
+        if expired_cnt < results.len() {
+            warn!(
+                "[expire_records] {} {} records failed to expire",
+                results.len() - expired_cnt,
+                data_type
+            );
+        }
+        Ok(expired_cnt)
+    }
+
+    /// Stored byte size of all the records, including the ones in trash
+    pub async fn record_sizes(&self) -> Result<Vec<RecordSize>> {
+        let filter = RecordQuery {
+            include_trash: true,
+            ..Default::default()
+        };
+        let mut sizes = vec![];
+        let mut start_cursor = None;
+
+        loop {
+            let page = self
+                .store
+                .page(&filter, &Self::OLDEST_FIRST, MAX_PAGE_SIZE, start_cursor)
+                .await?;
+
+            for doc in page.records {
+                let r = doc.record;
+                sizes.push(RecordSize {
+                    view_id: doc.view_id,
+                    document_id: doc.document_id,
+                    data_type: r.data_type,
+                    owner: doc.owner,
+                    byte_size: RecordSize::calculate(
+                        r.byte_size,
+                        &r.content_preview.unwrap_or_default(),
+                    ),
+                    create_time: r.create_time,
+                    is_favorite: r.is_favorite,
+                    is_deleted: r.is_deleted,
+                });
+            }
+
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
+
+        Ok(sizes)
+    }
+
+    /// Purge the records in trash, then move the oldest non-favorite records to trash until
+    /// the records fit in `quota_bytes`. Only the trash is hard deleted, the records moved to
+    /// trash are purged by a later run when the records still do not fit
+    pub async fn delete_record_with_quota(&self, quota_bytes: i64) -> Result<bool> {
+        // The sizes do not see the queued writes yet, they are replayed first
+        if self.has_pending() {
+            return Ok(false);
+        }
+        let need_evict_records = select_quota_evictions(&self.record_sizes().await?, quota_bytes);
+        if need_evict_records.is_empty() {
+            return Ok(false);
+        }
+
+        info!(
+            "[delete_record_with_quota] {} records needed to remove",
+            need_evict_records.len()
+        );
+        let mut results = vec![];
+        for record in need_evict_records {
+            let res = match DocumentViewId::from_str(&record.view_id) {
+                Ok(view_id) if record.is_deleted != 0 => {
+                    let res = self.delete_record(&view_id).await;
+                    if res.is_ok() {
+                        self.remove_versions(&record.document_id).await;
+                    }
+                    res
+                }
+                Ok(view_id) => self.trash_record(&view_id).await,
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = &res {
+                error!("evict record {} error: {:?}", record.view_id, err);
+            }
+            results.push(BatchItemResult::from_result(record.view_id, res));
+        }
+        let evicted_cnt = BatchItemResult::success_count(&results);
+        if evicted_cnt < results.len() {
+            warn!(
+                "[delete_record_with_quota] {} records failed to evict",
+                results.len() - evicted_cnt
+            );
+        }
+
+        Ok(evicted_cnt > 0)
+    }
+
+    /// Move the records into trash in one batch
+    pub async fn batch_trash_records(&self, view_ids: Vec<String>) -> Vec<BatchItemResult> {
+        let now = chrono::Local::now().timestamp();
+        let latest_addr = local_ip().to_string();
+        let updates = view_ids
+            .into_iter()
+            .map(|view_id| {
+                let fields = vec![
+                    ("is_deleted", OperationValue::Integer(now)),
+                    ("latest_addr", OperationValue::String(latest_addr.clone())),
+                ];
+                (view_id, fields)
+            })
+            .collect();
+        self.batch_update_record_with_fields(updates).await
+    }
+
+    pub async fn batch_set_favorite(
+        &self,
+        view_ids: Vec<String>,
+        is_favorite: i64,
+    ) -> Vec<BatchItemResult> {
+        let latest_addr = local_ip().to_string();
+        let updates = view_ids
+            .into_iter()
+            .map(|view_id| {
+                let fields = vec![
+                    ("is_favorite", OperationValue::Integer(is_favorite)),
+                    ("latest_addr", OperationValue::String(latest_addr.clone())),
+                ];
+                (view_id, fields)
+            })
+            .collect();
+        self.batch_update_record_with_fields(updates).await
+    }
+
+    /// Add and remove tags of the records, the current tags are loaded in one query
+    pub async fn batch_tag(
+        &self,
+        view_ids: Vec<String>,
+        add_tags: Vec<String>,
+        remove_tags: Vec<String>,
+    ) -> Result<Vec<BatchItemResult>> {
+        let filter = RecordQuery {
+            view_ids: Some(view_ids.clone()),
+            include_trash: true,
+            ..Default::default()
+        };
+        let documents = self
+            .store
+            .page(&filter, &RecordSort::default(), view_ids.len() as i64, None)
+            .await?
+            .records;
+        let latest_addr = local_ip().to_string();
+
+        let mut results = vec![];
+        let mut updates = vec![];
+        for view_id in view_ids {
+            match documents.iter().find(|d| d.view_id.eq(&view_id)) {
+                None => results.push(BatchItemResult::from_result(
+                    view_id.clone(),
+                    Err(anyhow!("record not found for view id: {}", view_id)),
+                )),
+                Some(document) => {
+                    let fields = vec![
+                        (
+                            "tags",
+                            OperationValue::String(record::merge_tags(
+                                &document.record.tags,
+                                &add_tags,
+                                &remove_tags,
+                            )),
+                        ),
+                        ("latest_addr", OperationValue::String(latest_addr.clone())),
+                    ];
+                    updates.push((view_id, fields));
+                }
+            }
+        }
+
+        results.extend(self.batch_update_record_with_fields(updates).await);
+        Ok(results)
+    }
+
+    /// Publish the updates one by one, the failure of one record does not stop the others.
+    /// The updates are queued like the other writes when the store is unavailable
+    async fn batch_update_record_with_fields(
+        &self,
+        updates: Vec<(String, Vec<(&'static str, OperationValue)>)>,
+    ) -> Vec<BatchItemResult> {
+        let mut results = Vec::with_capacity(updates.len());
+        for (view_id, fields) in updates {
+            let res = match PendingOperation::update(&view_id, &fields) {
+                Ok(operation) => self.submit(operation).await,
+                Err(err) => Err(err),
+            };
+
+            if let Err(err) = &res {
+                error!("batch update record {} error: {}", view_id, err);
+            }
+            results.push(BatchItemResult::from_result(view_id, res));
+        }
+
+        info!(
+            "batch update {}/{} records success",
+            BatchItemResult::success_count(&results),
+            results.len()
+        );
+        results
+    }
+
+    /// Combine the records in the order of `view_ids` into a new record, text records are
+    /// joined by separator or template, image records are stacked vertically. Return the
+    /// document id of the merged record, which is an existing record when the merged content
+    /// was copied before, e.g. one of the sources. It is empty when the merge is queued
+    pub async fn merge_records(
+        &self,
+        view_ids: &[String],
+        separator: Option<String>,
+        template: Option<String>,
+    ) -> Result<String> {
+        if view_ids.len() < 2 {
+            bail!("at least 2 records are needed to merge");
+        }
+        let mut data_type = None;
+        // The merged record is kept local when any of the records is
+        let mut sync = true;
+        let mut contents = Vec::with_capacity(view_ids.len());
+        for view_id in view_ids {
+            let doc = self
+                .find_record_by_view_id(view_id)
+                .await?
+                .ok_or(anyhow!("record not found for view id: {}", view_id))?;
+
+            let record_type = doc.record.data_type;
+            if data_type.get_or_insert(record_type.clone()) != &record_type {
+                bail!("text and image records can not be merged together");
+            }
+            sync = sync && doc.record.sync;
+            contents.push(doc.record.content);
+        }
+
+        let data_type = data_type.ok_or(anyhow!("no records to merge"))?;
+        let mut merged = if data_type.eq(&String::from(DataTypeEnum::IMAGE)) {
+            Self::merge_image_records(&contents)?
+        } else {
+            let content = record::merge_text_contents(
+                &contents,
+                separator.as_deref(),
+                template.as_deref(),
+            );
+            Record {
+                content_preview: Some(record::text_preview(&content)),
+                content,
+                data_type: DataTypeEnum::TEXT.into(),
+                latest_addr: local_ip().to_string(),
+                ..Default::default()
+            }
+        };
+
+        merged.sync = sync;
+        let document_id = self.insert_if_not_exist(merged).await?;
+        info!("merge {} records success", view_ids.len());
+        Ok(document_id)
+    }
+
+    fn merge_image_records(contents: &[String]) -> Result<Record> {
+        let mut images = Vec::with_capacity(contents.len());
+        for content in contents {
+            let image_data = json::parse::<ImageData>(content)?;
+            images.push(image::base64_to_rgba8(&image_data.base64)?);
+        }
+        let img = image::stack_images_vertically(&images);
+
+        let content = json::stringify(&ImageData {
+            width: img.width,
+            height: img.height,
+            base64: image::rgba8_to_base64(&img),
+        })?;
+        let content_preview = json::stringify(&ImageData {
+            width: img.width,
+            height: img.height,
+            base64: image::rgba8_to_jpeg_base64(&img, 75),
+        })?;
+
+        Ok(Record {
+            content,
+            content_preview: Some(content_preview),
+            data_type: DataTypeEnum::IMAGE.into(),
+            latest_addr: local_ip().to_string(),
+            ..Default::default()
+        })
+    }
+
     // Delete record with over limit
-    pub async fn delete_record_with_limit(limit: usize) -> Result<bool> {
+    pub async fn delete_record_with_limit(&self, limit: usize) -> Result<bool> {
+        // The count does not see the queued moves to trash yet, they are replayed first
+        if self.has_pending() {
+            return Ok(false);
+        }
+        let filter = RecordQuery {
+            favorite: Some(false),
+            ..Default::default()
+        };
         // 先查询count，如果count - limit > RECORD_LIMIT_THRESHOLD 才删除超出limit部分记录，防止频繁操作
-        let cnt = Self::count_records().await? as usize;
+        let cnt = self.store.count(&filter).await? as usize;
 
         let record_limit_threshold = Self::calculate_record_limit_threshold(limit);
 
@@ -142,28 +1074,21 @@ impl RecordDao {
             return Ok(false);
         }
 
-        let actual_remove_cnt = (cnt - limit) as i64;
+        let actual_remove_cnt = cnt - limit;
         info!(
             "[delete_record_with_limit] {} records needed to remove",
             actual_remove_cnt
         );
 
-        let need_delete_records = Self::record_by_pages(
-            Some(actual_remove_cnt),
-            None,
-            Some(vec![0]),
-            Some(GraphRecordOrderBy::create_time),
-            Some(OrderDirection::ASC),
-        )
-        .await?;
-
-        // Delete records
-        let need_delete_records_len = need_delete_records.len();
-        Self::batch_delete_record(need_delete_records).await?;
+        // Move records to trash, they will be purged after the retention days
+        let progress = self.evict_records(&filter, actual_remove_cnt).await?;
+        if progress.trashed == 0 {
+            return Ok(false);
+        }
 
         if let Err(e) = GlobalHandler::push_message_to_window(
             MessageTypeEnum::DeleteClipboardRecordBackend,
-            format!("batch delete {} records success", need_delete_records_len),
+            format!("batch trash {} records success", progress.trashed),
         ) {
             error!("send DeleteClipboardRecordBackend message err: {:?}", e)
         };
@@ -185,109 +1110,585 @@ impl RecordDao {
         }
     }
 
-    async fn count_records() -> Result<i64> {
-        let handler = &mut GraphQLHandler::global().lock().await;
-        let request_body = RecordCounts::build_query(record_counts::Variables {
-            favorite_filter: Some(vec![0]),
-        });
-        let res = handler
-            .cli
-            .post(handler.endpoint())
-            .json(&request_body)
-            .send()
-            .await?;
-        let response_body: Response<record_counts::ResponseData> = res.json().await?;
-
-        match response_body.data {
-            None => Ok(0),
-            Some(res) => Ok(res
-                .all_record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f
-                .total_count),
-        }
-    }
-
-    async fn record_by_pages(
-        limit: Option<i64>,
-        start_cursor: Option<String>,
-        favorite_filter: Option<Vec<i64>>,
-        order_by: Option<GraphRecordOrderBy>,
-        order_dir: Option<OrderDirection>,
-    ) -> Result<Vec<GraphRecordPageDocuments>> {
-        let handler = &mut GraphQLHandler::global().lock().await;
-        let request_body = RecordByPages::build_query(record_by_pages::Variables {
-            order_by,
-            order_dir,
-            limit,
-            start_cursor,
-            favorite_filter,
-        });
-
-        let res = handler
-            .cli
-            .post(handler.endpoint())
-            .json(&request_body)
-            .send()
-            .await?;
-        let response_body: Response<record_by_pages::ResponseData> = res.json().await?;
+    /// Move the oldest records matching `filter` into trash in batches, until `remove_cnt`
+    /// records are handled. Failed records are retried a few times and then reported in the
+    /// progress, which is pushed to the frontend after each batch. It stops once a move is
+    /// queued, the store is unavailable and the rest would only be queued too
+    async fn evict_records(
+        &self,
+        filter: &RecordQuery,
+        remove_cnt: usize,
+    ) -> Result<EvictionProgress> {
+        let mut progress = EvictionProgress::new(remove_cnt);
+        let mut start_cursor = None;
 
-        match response_body.data {
-            None => Ok(vec![]),
-            Some(res) => Ok(res
-                .all_record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f
-                .documents),
-        }
-    }
+        while progress.remaining() > 0 {
+            let limit = progress.remaining().min(Self::EVICTION_BATCH_SIZE) as i64;
+            let page = self
+                .store
+                .page(filter, &Self::OLDEST_FIRST, limit, start_cursor)
+                .await?;
 
-    async fn batch_delete_record(need_delete_records: Vec<GraphRecordPageDocuments>) -> Result<()> {
-        let handler = &mut GraphQLHandler::global().lock().await;
+            for record in page.records {
+                let res = self.trash_record_with_retry(&record.view_id).await;
+                if let Err(err) = &res {
+                    error!("trash record {} error: {:?}", record.view_id, err);
+                }
+                progress.add_result(BatchItemResult::from_result(record.view_id, res));
+                if progress.queued > 0 {
+                    break;
+                }
+            }
+            Self::push_eviction_progress(&progress);
+            if progress.queued > 0 {
+                warn!("[evict_records] the store is unavailable, eviction stopped");
+                break;
+            }
 
-        for record in need_delete_records {
-            let opt = OperationBuilder::new(&SchemaId::new(record::SCHEMA_ID).unwrap())
-                .action(OperationAction::Delete)
-                .previous(
-                    &OperationId::from_str(record.meta.as_ref().unwrap().document_id.as_str())?
-                        .into(),
-                )
-                .build()?;
+            // The cursor skips the failed records, they are selected again next time
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => start_cursor = Some(end_cursor),
+            }
+        }
 
-            match handler.send_to_node(PlainOperation::from(&opt)).await {
-                Ok(res) => {
-                    info!("delete record success, opt id: {}", res);
-                }
-                Err(err) => {
-                    error!("delete record error: {}", err);
+        progress.finished = true;
+        Self::push_eviction_progress(&progress);
+        Ok(progress)
+    }
+
+    async fn trash_record_with_retry(&self, view_id: &str) -> Result<String> {
+        let view_id = DocumentViewId::from_str(view_id)?;
+        let mut attempt = 1;
+        loop {
+            match self.trash_record(&view_id).await {
+                Err(err) if attempt < Self::EVICTION_MAX_ATTEMPTS => {
+                    warn!(
+                        "trash record {} failed at attempt {}: {:?}",
+                        view_id, attempt, err
+                    );
+                    let wait = Duration::from_millis(Self::EVICTION_RETRY_MILLIS * attempt as u64);
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
                 }
-            };
+                res => return res,
+            }
         }
+    }
 
-        Ok(())
+    fn push_eviction_progress(progress: &EvictionProgress) {
+        if let Err(e) = GlobalHandler::push_message_to_window(
+            MessageTypeEnum::EvictionProgressBackend,
+            progress.clone(),
+        ) {
+            error!("send EvictionProgressBackend message err: {:?}", e)
+        };
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::operation::OperationValue;
+
+    use crate::dao::memory_record_store::MemoryRecordStore;
+    use crate::dao::pending_queue::PendingQueue;
     use crate::dao::record_dao::RecordDao;
-    use crate::models::record::Record;
-    use crate::p2panda::node::NodeServer;
+    use crate::dao::record_store::RecordStore;
+    use crate::dao::selective_record_store::SelectiveRecordStore;
+    use crate::models::record;
+    use crate::models::record::{DataTypeEnum, Record};
+    use crate::models::record_list::{RecordFilter, RecordQuery, RecordSort};
+    use crate::utils::{json, string};
+
+    const OWNER: &str = "memory-device";
+
+    fn new_dao() -> (Arc<MemoryRecordStore>, RecordDao) {
+        let store = Arc::new(MemoryRecordStore::new(OWNER));
+        (
+            store.clone(),
+            RecordDao::new(store, PendingQueue::temporary()),
+        )
+    }
+
+    fn text_record(content: &str, create_time: i64) -> Record {
+        Record {
+            content: content.to_string(),
+            content_preview: Some(content.to_string()),
+            data_type: DataTypeEnum::TEXT.into(),
+            md5: string::md5(content),
+            create_time,
+            ..Default::default()
+        }
+    }
+
+    fn view_id(id: &str) -> DocumentViewId {
+        DocumentViewId::from_str(id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_insert_if_not_exist() {
+        let (store, dao) = new_dao();
+        dao.insert_if_not_exist(text_record("hello", 0))
+            .await
+            .unwrap();
+        dao.insert_if_not_exist(text_record("hello", 0))
+            .await
+            .unwrap();
+
+        let records = store.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].owner, OWNER);
+        assert_eq!(records[0].record.device_id, OWNER);
+        assert_eq!(records[0].record.byte_size, 5);
+
+        // Copy a record in trash again restores it
+        dao.trash_record(&view_id(&records[0].view_id))
+            .await
+            .unwrap();
+        assert_ne!(store.records()[0].record.is_deleted, 0);
+        dao.insert_if_not_exist(text_record("hello", 0))
+            .await
+            .unwrap();
+        let records = store.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.is_deleted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_duplicates() {
+        let (store, dao) = new_dao();
+        for (tags, is_favorite, is_deleted, create_time) in
+            [("a", 0, 5, 1), ("b,a", 1, 6, 3), ("c", 0, 0, 2)]
+        {
+            let mut r = text_record("same", create_time);
+            r.tags = tags.to_string();
+            r.is_favorite = is_favorite;
+            r.is_deleted = is_deleted;
+            dao.create_record(r).await.unwrap();
+        }
+        dao.create_record(text_record("other", 1)).await.unwrap();
+        let kept_id = store
+            .records()
+            .into_iter()
+            .filter(|d| d.record.content.eq("same"))
+            .map(|d| d.document_id)
+            .min()
+            .unwrap();
+
+        assert_eq!(dao.reconcile_duplicates().await.unwrap(), 2);
+        let records = store.records();
+        assert_eq!(records.len(), 2);
+        let kept = records
+            .iter()
+            .find(|d| d.record.content.eq("same"))
+            .unwrap();
+        assert_eq!(kept.document_id, kept_id);
+        assert_eq!(kept.record.is_favorite, 1);
+        assert_eq!(kept.record.is_deleted, 0);
+        assert_eq!(kept.record.create_time, 3);
+        let mut tags = record::parse_tags(&kept.record.tags);
+        tags.sort();
+        assert_eq!(tags, vec!["a", "b", "c"]);
+
+        assert_eq!(dao.reconcile_duplicates().await.unwrap(), 0);
+    }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_create_record() {
-        NodeServer::start().await.unwrap();
-
-        let res = RecordDao::create_record(Record {
-            content: "test".to_string(),
-            content_preview: None,
-            data_type: "".to_string(),
-            md5: "123".to_string(),
-            create_time: 1699845357,
-            tags: "".to_string(),
-            latest_addr: "".to_string(),
+    async fn test_insert_merges_duplicates() {
+        let (store, dao) = new_dao();
+        for is_favorite in [0, 1] {
+            let mut r = text_record("hello", 1);
+            r.is_favorite = is_favorite;
+            dao.create_record(r).await.unwrap();
+        }
+
+        dao.insert_if_not_exist(text_record("hello", 0))
+            .await
+            .unwrap();
+        let records = store.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.is_favorite, 1);
+        assert!(records[0].record.create_time > 1);
+        assert_eq!(records[0].record.md5, string::md5("hello"));
+        assert_eq!(
+            records[0].record.content_hash,
+            string::content_hash("hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_pending() {
+        let (store, dao) = new_dao();
+        let trashed = dao.create_record(text_record("trashed", 1)).await.unwrap();
+        let deleted = dao.create_record(text_record("deleted", 2)).await.unwrap();
+        dao.insert_if_not_exist(text_record("copied", 0))
+            .await
+            .unwrap();
+
+        store.set_offline(true);
+        for content in ["new", "copied", "new"] {
+            dao.insert_if_not_exist(text_record(content, 0))
+                .await
+                .unwrap();
+        }
+        let res = dao.trash_record(&view_id(&trashed)).await.unwrap();
+        assert!(res.is_empty());
+        dao.trash_record(&view_id(&deleted)).await.unwrap();
+        assert!(dao.has_pending());
+        // The queued copies are written to the disk sealed
+        for entry in dao.pending.entries() {
+            let line = json::stringify(&entry).unwrap();
+            assert!(!line.contains("\"new\"") && !line.contains("\"copied\""));
+        }
+        assert_eq!(dao.replay_pending().await.unwrap(), 0);
+
+        store.set_offline(false);
+        // The record is deleted meanwhile, the write to it is rejected and dropped
+        store.delete(&deleted).await.unwrap();
+        assert_eq!(dao.replay_pending().await.unwrap(), 3);
+        assert!(!dao.has_pending());
+        let records = store.records();
+        let mut contents: Vec<&str> = records.iter().map(|d| d.record.content.as_str()).collect();
+        contents.sort();
+        assert_eq!(contents, vec!["copied", "new", "trashed"]);
+        let trashed = records.iter().find(|d| d.record.content.eq("trashed"));
+        assert!(trashed.unwrap().record.is_deleted > 0);
+        assert_eq!(dao.replay_pending().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_records() {
+        let (_, dao) = new_dao();
+        for (idx, tags) in ["work", "work,todo", "home", "todo,work"]
+            .iter()
+            .enumerate()
+        {
+            let mut r = text_record(&format!("record {}", idx), idx as i64);
+            r.tags = tags.to_string();
+            dao.create_record(r).await.unwrap();
+        }
+
+        let page = dao
+            .list_records(RecordFilter::default(), RecordSort::default(), 3, None)
+            .await
+            .unwrap();
+        assert_eq!(page.total_count, 4);
+        let times: Vec<i64> = page.items.iter().map(|item| item.create_time).collect();
+        assert_eq!(times, vec![3, 2, 1]);
+        assert!(page.items[0].content_preview.is_some());
+
+        let next = dao
+            .list_records(
+                RecordFilter::default(),
+                RecordSort::default(),
+                3,
+                page.next_cursor,
+            )
+            .await
+            .unwrap();
+        assert_eq!(next.items.len(), 1);
+        assert_eq!(next.items[0].create_time, 0);
+        assert_eq!(next.next_cursor, None);
+        assert_eq!(next.total_count, 4);
+
+        let filter = RecordFilter {
+            tags: Some(vec!["work".to_string(), "todo".to_string()]),
             ..Default::default()
-        })
-        .await
-        .unwrap();
+        };
+        let page = dao
+            .list_records(filter, RecordSort::default(), 10, None)
+            .await
+            .unwrap();
+        let times: Vec<i64> = page.items.iter().map(|item| item.create_time).collect();
+        assert_eq!(times, vec![3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_record_content() {
+        let (_, dao) = new_dao();
+        let view_id = dao.create_record(text_record("hello", 1)).await.unwrap();
+
+        let content = dao.get_record_content(view_id).await.unwrap();
+        assert_eq!(content.content, "hello");
+        assert!(dao.get_record_content("unknown".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_purge_trash() {
+        let (store, dao) = new_dao();
+        for (content, is_deleted) in [("alive", 0), ("old", 10), ("new", 30)] {
+            let mut r = text_record(content, 1);
+            r.is_deleted = is_deleted;
+            dao.create_record(r).await.unwrap();
+        }
+
+        assert_eq!(dao.purge_trash(20).await.unwrap(), 1);
+        let contents: Vec<String> = store
+            .records()
+            .into_iter()
+            .map(|d| d.record.content)
+            .collect();
+        assert_eq!(contents, vec!["alive", "new"]);
+
+        assert_eq!(dao.empty_trash().await.unwrap(), 1);
+        assert_eq!(store.records().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_records() {
+        let (store, dao) = new_dao();
+        let a = dao.insert_if_not_exist(text_record("a", 0)).await.unwrap();
+        let empty = dao.insert_if_not_exist(text_record("", 0)).await.unwrap();
+        let single = std::slice::from_ref(&a);
+        assert!(dao.merge_records(single, None, None).await.is_err());
+
+        // The merged content is one of the sources, which holds the merged record
+        let merged = dao
+            .merge_records(&[a.clone(), empty.clone()], Some(String::new()), None)
+            .await
+            .unwrap();
+        assert_eq!(merged, a);
+        assert_eq!(store.records().len(), 2);
+
+        // The update moved the source to a new view
+        let view_ids: Vec<String> = store.records().into_iter().map(|d| d.view_id).collect();
+        let merged = dao
+            .merge_records(&view_ids, Some("-".to_string()), None)
+            .await
+            .unwrap();
+        let doc = store
+            .records()
+            .into_iter()
+            .find(|d| d.document_id.eq(&merged))
+            .unwrap();
+        assert_eq!(doc.record.content, "a-");
+    }
+
+    #[tokio::test]
+    async fn test_record_history() {
+        let (store, dao) = new_dao();
+        let first = dao.create_record(text_record("one", 1)).await.unwrap();
+        let second = dao
+            .edit_record(&view_id(&first), "two".to_string())
+            .await
+            .unwrap();
+        let third = dao
+            .edit_record(&view_id(&second), "three".to_string())
+            .await
+            .unwrap();
+
+        let versions = dao.record_history(&view_id(&third)).await.unwrap();
+        let contents: Vec<&str> = versions.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(contents, vec!["two", "one"]);
+        assert_eq!(versions[1].view_id, first);
+
+        // The versions go with the record
+        dao.trash_record(&view_id(&third)).await.unwrap();
+        dao.empty_trash().await.unwrap();
+        assert!(store.records().is_empty());
+        assert!(store.versions(&first).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_record_with_quota() {
+        let (store, dao) = new_dao();
+        let records = [("aaaa", 0), ("bbbb", 10), ("cccc", 0)];
+        for (create_time, (content, is_deleted)) in records.into_iter().enumerate() {
+            let mut r = text_record(content, create_time as i64);
+            r.is_deleted = is_deleted;
+            dao.create_record(r).await.unwrap();
+        }
+        let contents = || -> Vec<String> {
+            store
+                .records()
+                .into_iter()
+                .map(|d| d.record.content)
+                .collect()
+        };
+
+        // Each record takes 8 bytes, the content and the preview. The trash is purged first
+        assert!(!dao.delete_record_with_quota(24).await.unwrap());
+        assert!(dao.delete_record_with_quota(16).await.unwrap());
+        assert_eq!(contents(), vec!["aaaa", "cccc"]);
+
+        // The oldest record is moved to trash, the next run purges it
+        assert!(dao.delete_record_with_quota(8).await.unwrap());
+        assert_eq!(contents(), vec!["aaaa", "cccc"]);
+        assert_ne!(store.records()[0].record.is_deleted, 0);
+        assert!(dao.delete_record_with_quota(8).await.unwrap());
+        assert_eq!(contents(), vec!["cccc"]);
+    }
+
+    #[tokio::test]
+    async fn test_expire_records() {
+        let (store, dao) = new_dao();
+        dao.create_record(text_record("old", 10)).await.unwrap();
+        dao.create_record(text_record("new", 30)).await.unwrap();
+        let mut favorite = text_record("favorite", 10);
+        favorite.is_favorite = 1;
+        dao.create_record(favorite).await.unwrap();
+
+        let expired = dao
+            .expire_records(DataTypeEnum::TEXT.into(), 20)
+            .await
+            .unwrap();
+        assert_eq!(expired, 1);
+        let trashed: Vec<String> = store
+            .records()
+            .into_iter()
+            .filter(|d| d.record.is_deleted != 0)
+            .map(|d| d.record.content)
+            .collect();
+        assert_eq!(trashed, vec!["old"]);
+        assert_eq!(
+            dao.expire_records(DataTypeEnum::IMAGE.into(), 20)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_record_with_limit() {
+        let (store, dao) = new_dao();
+        let mut view_ids = vec![];
+        for idx in 0..5 {
+            let view_id = dao
+                .create_record(text_record(&idx.to_string(), idx))
+                .await
+                .unwrap();
+            view_ids.push(view_id);
+        }
+
+        assert!(!dao.delete_record_with_limit(6).await.unwrap());
+
+        // The queued writes are replayed before the records are counted
+        store.set_offline(true);
+        let tags = [("tags", OperationValue::String("queued".to_string()))];
+        dao.update_record_with_fields(&view_id(&view_ids[4]), &tags)
+            .await
+            .unwrap();
+        store.set_offline(false);
+        assert!(!dao.delete_record_with_limit(3).await.unwrap());
+        assert_eq!(dao.replay_pending().await.unwrap(), 1);
+
+        assert!(dao.delete_record_with_limit(3).await.unwrap());
+        let trashed: Vec<i64> = store
+            .records()
+            .into_iter()
+            .filter(|d| d.record.is_deleted != 0)
+            .map(|d| d.record.create_time)
+            .collect();
+        assert_eq!(trashed, vec![0, 1]);
+        assert_eq!(store.count(&RecordQuery::default()).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_evict_records() {
+        let (store, dao) = new_dao();
+        for idx in 0..120 {
+            dao.create_record(text_record(&idx.to_string(), idx))
+                .await
+                .unwrap();
+        }
+        let mut favorite = text_record("favorite", -1);
+        favorite.is_favorite = 1;
+        dao.create_record(favorite).await.unwrap();
+
+        let filter = RecordQuery {
+            favorite: Some(false),
+            ..Default::default()
+        };
+        let progress = dao.evict_records(&filter, 110).await.unwrap();
+        assert_eq!((progress.total, progress.trashed), (110, 110));
+        assert!(progress.failed.is_empty());
+        assert!(progress.finished);
+
+        let alive: Vec<i64> = store
+            .records()
+            .into_iter()
+            .filter(|d| d.record.is_deleted == 0)
+            .map(|d| d.record.create_time)
+            .collect();
+        assert_eq!(alive, (110..120).chain([-1]).collect::<Vec<i64>>());
+    }
+
+    #[tokio::test]
+    async fn test_batch_tag() {
+        let (store, dao) = new_dao();
+        let mut r = text_record("tagged", 1);
+        r.tags = "a,b".to_string();
+        let tagged = dao.create_record(r).await.unwrap();
+
+        let results = dao
+            .batch_tag(
+                vec![tagged, "unknown".to_string()],
+                vec!["c".to_string()],
+                vec!["a".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(store.records()[0].record.tags, "b,c");
+    }
+
+    #[tokio::test]
+    async fn test_set_record_sync() {
+        let (synced, local) = (
+            Arc::new(MemoryRecordStore::new(OWNER)),
+            Arc::new(MemoryRecordStore::new(OWNER)),
+        );
+        let store = SelectiveRecordStore::new(synced.clone(), local.clone());
+        let dao = RecordDao::new(Arc::new(store), PendingQueue::temporary());
+
+        let mut r = text_record("secret", 1);
+        r.sync = false;
+        dao.insert_if_not_exist(r).await.unwrap();
+        dao.insert_if_not_exist(text_record("public", 2))
+            .await
+            .unwrap();
+        // Copied again, the local-only record is kept
+        dao.insert_if_not_exist(text_record("secret", 3))
+            .await
+            .unwrap();
+        assert_eq!(local.records().len(), 1);
+        assert_eq!(synced.records().len(), 1);
+
+        let page = dao
+            .list_records(RecordFilter::default(), RecordSort::default(), 10, None)
+            .await
+            .unwrap();
+        assert_eq!(page.total_count, 2);
+        let mut syncs: Vec<bool> = page.items.iter().map(|item| item.sync).collect();
+        syncs.sort();
+        assert_eq!(syncs, vec![false, true]);
+
+        let view_id = local.records()[0].view_id.clone();
+        let new_view_id = dao.set_record_sync(&view_id, true).await.unwrap();
+        assert!(local.records().is_empty());
+        assert_eq!(synced.records().len(), 2);
+        let moved = dao.find_record_by_view_id(&new_view_id).await.unwrap();
+        assert_eq!(moved.unwrap().record.content, "secret");
+        assert_eq!(
+            dao.set_record_sync(&new_view_id, true).await.unwrap(),
+            new_view_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_sizes() {
+        let (_, dao) = new_dao();
+        dao.create_record(text_record("hello", 1)).await.unwrap();
+        let mut trashed = text_record("bye", 2);
+        trashed.is_deleted = 3;
+        dao.create_record(trashed).await.unwrap();
 
-        println!("test_create_record: {}", res);
+        let sizes = dao.record_sizes().await.unwrap();
+        let sizes: Vec<(i64, i64)> = sizes.iter().map(|s| (s.byte_size, s.is_deleted)).collect();
+        assert_eq!(sizes, vec![(10, 0), (6, 3)]);
     }
 }
@@ -0,0 +1,52 @@
+//!
+//! Previous versions of the local-only records, kept on this device. The versions of the
+//! synced records are documents in the node, see `RecordStore::versions`, the ones kept here
+//! before are still listed with them
+//!
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::debug;
+
+use crate::models::record::RecordVersion;
+use crate::utils::dir::app_data_history_dir;
+use crate::utils::json;
+
+pub struct RecordHistoryDao;
+
+impl RecordHistoryDao {
+    // Only keep the latest versions of each record
+    const MAX_VERSIONS: usize = 50;
+
+    pub fn append(document_id: &str, version: RecordVersion) -> Result<()> {
+        let mut versions = Self::list(document_id)?;
+        versions.insert(0, version);
+        versions.truncate(Self::MAX_VERSIONS);
+
+        json::save(&Self::history_path(document_id)?, &versions)?;
+        debug!("append record version for document: {}", document_id);
+        Ok(())
+    }
+
+    /// All the previous versions of the record, the newest first
+    pub fn list(document_id: &str) -> Result<Vec<RecordVersion>> {
+        let path = Self::history_path(document_id)?;
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        json::read(&path)
+    }
+
+    pub fn remove(document_id: &str) -> Result<()> {
+        let path = Self::history_path(document_id)?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn history_path(document_id: &str) -> Result<PathBuf> {
+        Ok(app_data_history_dir()?.join(format!("{}.json", document_id)))
+    }
+}
@@ -0,0 +1,348 @@
+//!
+//! Data migration of the records between the versions of the record schema
+//!
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use graphql_client::GraphQLQuery;
+use log::{debug, info, warn};
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationValue};
+use tokio::sync::Mutex;
+
+use crate::dao::record_dao::RecordDao;
+use crate::dao::record_store::StoredRecord;
+use crate::graphql::{
+    record_v1_by_pages, record_v2_by_pages, GraphRecordV1Documents, GraphRecordV2Documents,
+    RecordV1ByPages, RecordV2ByPages,
+};
+use crate::models::migration::MigrationProgress;
+use crate::models::record;
+use crate::models::record::Record;
+use crate::p2panda::graphql::GraphQLHandler;
+use crate::p2panda::schema_registry::{latest_version, schema_id_version};
+use crate::utils::dir::app_data_dir;
+use crate::utils::json;
+
+pub struct RecordMigrationDao;
+
+impl RecordMigrationDao {
+    const BATCH_SIZE: i64 = 50;
+
+    // Records of these versions are copied into the latest version. The newer version goes
+    // first, so a record edited after it was copied from v1 keeps the edited copy
+    const FROM_VERSIONS: [u32; 2] = [2, 1];
+
+    /// Copy the records of the old versions which are not migrated yet into the latest
+    /// record schema.
+    ///
+    /// The old documents hold the content in cleartext, they are deleted once copied. The
+    /// devices still on the old versions can not pair, so they do not sync them anyway.
+    /// Deleting only removes the documents, the operations which created and edited them
+    /// stay in the node database with the content, they are not scrubbed.
+    /// Records whose content hash already exists in the latest schema are not copied again,
+    /// so a run interrupted before saving the progress does not create duplicates
+    pub async fn migrate_records() -> Result<usize> {
+        let to_version = latest_version(record::SCHEMA_NAME)?;
+
+        // Only one migration at a time, the maintenance task may be triggered while running
+        static LOCK: Mutex<()> = Mutex::const_new(());
+        let _guard = LOCK.lock().await;
+
+        let mut migrated_cnt = 0;
+        for from_version in Self::FROM_VERSIONS {
+            if from_version < to_version {
+                migrated_cnt += Self::migrate_version(from_version, to_version).await?;
+            }
+        }
+        Ok(migrated_cnt)
+    }
+
+    /// The documents are paged without content, only the pages holding new or changed
+    /// documents are loaded again with the content to be copied
+    async fn migrate_version(from_version: u32, to_version: u32) -> Result<usize> {
+        let path = Self::progress_path(from_version)?;
+        let saved = if path.exists() {
+            Some(json::read(&path)?)
+        } else {
+            None
+        };
+        let mut progress = MigrationProgress::resume(saved, from_version, to_version);
+
+        let mut migrated_cnt = 0;
+        let mut copied = vec![];
+        let mut start_cursor = None;
+        loop {
+            let (docs, end_cursor, has_next_page) = Self::old_records_by_pages(
+                from_version,
+                Some(Self::BATCH_SIZE),
+                start_cursor.clone(),
+                false,
+            )
+            .await?;
+
+            let mut adopted = false;
+            for doc in docs.iter() {
+                adopted = progress.adopt_view(&doc.document_id, &doc.view_id) || adopted;
+            }
+            if docs
+                .iter()
+                .any(|doc| progress.needs_copy(&doc.document_id, &doc.view_id))
+            {
+                let (docs, _, _) = Self::old_records_by_pages(
+                    from_version,
+                    Some(Self::BATCH_SIZE),
+                    start_cursor,
+                    true,
+                )
+                .await?;
+                let res = Self::migrate_batch(docs, &mut progress).await;
+                // Save what was done even if the batch failed, the rest is retried next time
+                json::save(&path, &progress)?;
+                migrated_cnt += res?;
+            } else if adopted {
+                json::save(&path, &progress)?;
+            }
+            // Deleted after the scan, the pages would move under the cursor
+            copied.extend(
+                docs.into_iter()
+                    .filter(|doc| !progress.needs_copy(&doc.document_id, &doc.view_id))
+                    .map(|doc| doc.view_id),
+            );
+
+            if !has_next_page {
+                break;
+            }
+            start_cursor = Some(end_cursor);
+        }
+
+        if migrated_cnt > 0 {
+            info!(
+                "[migrate_records] {} records migrated from v{} to v{}",
+                migrated_cnt, from_version, to_version
+            );
+        }
+
+        // A document failing to delete is still listed, it is deleted by the next run
+        let mut deleted_cnt = 0;
+        for view_id in copied {
+            match Self::delete_old_record(from_version, &view_id).await {
+                Ok(_) => deleted_cnt += 1,
+                Err(e) => warn!("delete migrated record {} err: {:?}", view_id, e),
+            }
+        }
+        if deleted_cnt > 0 {
+            info!(
+                "[migrate_records] {} migrated records of v{} deleted",
+                deleted_cnt, from_version
+            );
+        }
+        Ok(migrated_cnt)
+    }
+
+    async fn delete_old_record(version: u32, view_id: &str) -> Result<String> {
+        let opt = OperationBuilder::new(&schema_id_version(record::SCHEMA_NAME, version)?)
+            .action(OperationAction::Delete)
+            .previous(&DocumentViewId::from_str(view_id)?)
+            .build()?;
+        GraphQLHandler::global()?
+            .send_to_node(PlainOperation::from(&opt))
+            .await
+    }
+
+    async fn migrate_batch(
+        docs: Vec<StoredRecord>,
+        progress: &mut MigrationProgress,
+    ) -> Result<usize> {
+        let dao = RecordDao::global()?;
+        let mut migrated_cnt = 0;
+        for doc in docs {
+            if !progress.needs_copy(&doc.document_id, &doc.view_id) {
+                continue;
+            }
+
+            let mut record = doc.record;
+            // The record was copied on the device which authored the old document, the
+            // content hash is added in v3
+            record.content_hash = dao.content_hash(&record.content)?;
+            record.fill_defaults(&doc.owner);
+            let copied = match progress.migrated_id(&doc.document_id) {
+                Some(document_id) => dao.find_record_by_document_id(document_id).await?,
+                None => None,
+            };
+            let new_document_id = match copied {
+                // Changed since it was copied, the copy is updated
+                Some(copied) => {
+                    let view_id = DocumentViewId::from_str(&copied.view_id)?;
+                    dao.update_record_with_fields(&view_id, &Self::changed_fields(record))
+                        .await?;
+                    debug!("record {} changed, copy it again", doc.document_id);
+                    migrated_cnt += 1;
+                    copied.document_id
+                }
+                None => {
+                    let existing = dao.find_record_by_content(&record.content).await?;
+                    match existing.first() {
+                        Some(existing) => {
+                            debug!(
+                                "record {} already exists in the latest schema",
+                                doc.document_id
+                            );
+                            existing.document_id.clone()
+                        }
+                        None => {
+                            let new_document_id = dao.create_record(record).await?;
+                            migrated_cnt += 1;
+                            new_document_id
+                        }
+                    }
+                }
+            };
+            progress.mark_migrated(doc.document_id, doc.view_id, new_document_id);
+        }
+        Ok(migrated_cnt)
+    }
+
+    /// Fields the devices on the old versions can change
+    fn changed_fields(record: Record) -> Vec<(&'static str, OperationValue)> {
+        vec![
+            ("content", OperationValue::String(record.content)),
+            (
+                "content_preview",
+                OperationValue::String(record.content_preview.unwrap_or_default()),
+            ),
+            ("md5", OperationValue::String(record.md5)),
+            ("content_hash", OperationValue::String(record.content_hash)),
+            ("byte_size", OperationValue::Integer(record.byte_size)),
+            ("is_favorite", OperationValue::Integer(record.is_favorite)),
+            ("tags", OperationValue::String(record.tags)),
+            ("latest_addr", OperationValue::String(record.latest_addr)),
+            ("is_deleted", OperationValue::Integer(record.is_deleted)),
+        ]
+    }
+
+    async fn old_records_by_pages(
+        version: u32,
+        limit: Option<i64>,
+        start_cursor: Option<String>,
+        with_content: bool,
+    ) -> Result<(Vec<StoredRecord>, String, bool)> {
+        match version {
+            1 => Self::record_v1_by_pages(limit, start_cursor, with_content).await,
+            2 => Self::record_v2_by_pages(limit, start_cursor, with_content).await,
+            _ => bail!("no migration from record schema v{}", version),
+        }
+    }
+
+    async fn record_v1_by_pages(
+        limit: Option<i64>,
+        start_cursor: Option<String>,
+        with_content: bool,
+    ) -> Result<(Vec<StoredRecord>, String, bool)> {
+        let handler = GraphQLHandler::global()?;
+        let request_body = RecordV1ByPages::build_query(record_v1_by_pages::Variables {
+            limit,
+            start_cursor,
+            with_content: Some(with_content),
+        });
+
+        let data: Option<record_v1_by_pages::ResponseData> = handler.post(&request_body).await?;
+
+        match data {
+            None => Ok((vec![], String::new(), false)),
+            Some(res) => {
+                let res = res.records;
+                let docs = res.documents.into_iter().map(Self::from_v1).collect();
+                Ok((docs, res.end_cursor, res.has_next_page))
+            }
+        }
+    }
+
+    async fn record_v2_by_pages(
+        limit: Option<i64>,
+        start_cursor: Option<String>,
+        with_content: bool,
+    ) -> Result<(Vec<StoredRecord>, String, bool)> {
+        let handler = GraphQLHandler::global()?;
+        let request_body = RecordV2ByPages::build_query(record_v2_by_pages::Variables {
+            limit,
+            start_cursor,
+            with_content: Some(with_content),
+        });
+
+        let data: Option<record_v2_by_pages::ResponseData> = handler.post(&request_body).await?;
+
+        match data {
+            None => Ok((vec![], String::new(), false)),
+            Some(res) => {
+                let res = res.records;
+                let docs = res.documents.into_iter().map(Self::from_v2).collect();
+                Ok((docs, res.end_cursor, res.has_next_page))
+            }
+        }
+    }
+
+    fn from_v1(doc: GraphRecordV1Documents) -> StoredRecord {
+        let (fields, meta) = (doc.fields.unwrap(), doc.meta.unwrap());
+        StoredRecord {
+            view_id: meta.view_id,
+            document_id: meta.document_id.to_string(),
+            owner: meta.owner.to_string(),
+            cursor: String::new(),
+            record: Record {
+                content: fields.content.unwrap_or_default(),
+                content_preview: fields.content_preview,
+                data_type: fields.data_type.unwrap_or_default(),
+                md5: fields.md5.unwrap_or_default(),
+                create_time: fields.create_time.unwrap_or_default(),
+                is_favorite: fields.is_favorite.unwrap_or_default(),
+                tags: fields.tags.unwrap_or_default(),
+                latest_addr: fields.latest_addr.unwrap_or_default(),
+                is_deleted: fields.is_deleted.unwrap_or_default(),
+                ..Default::default()
+            },
+            space_id: String::new(),
+        }
+    }
+
+    fn from_v2(doc: GraphRecordV2Documents) -> StoredRecord {
+        let (fields, meta) = (doc.fields.unwrap(), doc.meta.unwrap());
+        StoredRecord {
+            view_id: meta.view_id,
+            document_id: meta.document_id.to_string(),
+            owner: meta.owner.to_string(),
+            cursor: String::new(),
+            record: Record {
+                content: fields.content.unwrap_or_default(),
+                content_preview: fields.content_preview,
+                data_type: fields.data_type.unwrap_or_default(),
+                md5: fields.md5.unwrap_or_default(),
+                create_time: fields.create_time.unwrap_or_default(),
+                is_favorite: fields.is_favorite.unwrap_or_default(),
+                tags: fields.tags.unwrap_or_default(),
+                latest_addr: fields.latest_addr.unwrap_or_default(),
+                is_deleted: fields.is_deleted.unwrap_or_default(),
+                device_id: fields.device_id.unwrap_or_default(),
+                source_app: fields.source_app.unwrap_or_default(),
+                mime_type: fields.mime_type.unwrap_or_default(),
+                byte_size: fields.byte_size.unwrap_or_default(),
+                subtype: fields.subtype.unwrap_or_default(),
+                expires_at: fields.expires_at.unwrap_or_default(),
+                content_hash: String::new(),
+                sync: true,
+            },
+            space_id: String::new(),
+        }
+    }
+
+    fn progress_path(from_version: u32) -> Result<PathBuf> {
+        // The progress of v1 keeps the file name of the versions which only migrated v1
+        let file_name = match from_version {
+            1 => "record_migration.json".to_string(),
+            _ => format!("record_migration_v{}.json", from_version),
+        };
+        Ok(app_data_dir()?.join(file_name))
+    }
+}
@@ -0,0 +1,141 @@
+//!
+//! Storage of the record documents, the record logic in `RecordDao` only talks to the store
+//! so it can run against the p2panda node or in memory
+//!
+use std::fmt;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use p2panda_rs::operation::OperationValue;
+use serde::{Deserialize, Serialize};
+
+use crate::models::record::{Record, RecordVersion};
+use crate::models::record_list::{RecordQuery, RecordSort};
+use crate::utils::string;
+
+/// Record document with its meta
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct StoredRecord {
+    pub view_id: String,
+    pub document_id: String,
+    // public key of the device which created the record
+    pub owner: String,
+    // position of the document in a page, pass it as `after` to load the next page
+    pub cursor: String,
+    pub record: Record,
+    // space of a local-only record, empty for the default space. The synced records are
+    // kept apart by the schemas of their space, so it is left empty for them
+    #[serde(default)]
+    pub space_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoredPage {
+    pub records: Vec<StoredRecord>,
+    // cursor of the last document, none when there are no more documents
+    pub end_cursor: Option<String>,
+    // count of all the documents matching the filter, only counted for the first page when
+    // the store has to load all the documents to count them
+    pub total_count: i64,
+}
+
+/// The store can not be reached, e.g. the node is down or still starting. The writes
+/// failing with it are queued by `RecordDao` and replayed later
+#[derive(Debug)]
+pub struct StoreUnavailable(pub String);
+
+impl fmt::Display for StoreUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record store unavailable: {}", self.0)
+    }
+}
+
+impl std::error::Error for StoreUnavailable {}
+
+impl StoreUnavailable {
+    pub fn is(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<StoreUnavailable>().is_some()
+    }
+}
+
+/// The store rejected the write itself, e.g. the record was deleted meanwhile or a field is
+/// invalid. Writing it again fails the same way, so a queued write failing with it is dropped
+#[derive(Debug)]
+pub struct InvalidOperation(pub String);
+
+impl fmt::Display for InvalidOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid record operation: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidOperation {}
+
+impl InvalidOperation {
+    pub fn is(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<InvalidOperation>().is_some()
+    }
+}
+
+#[async_trait]
+pub trait RecordStore: Send + Sync {
+    /// Create the record document, return the view id. The fields not set by the caller are
+    /// filled with the defaults of the device writing the record, see `Record::fill_defaults`
+    async fn create(&self, record: Record) -> Result<String>;
+
+    /// Update the fields of the record, return the new view id
+    async fn update(&self, view_id: &str, fields: &[(&str, OperationValue)]) -> Result<String>;
+
+    /// Hard delete the record document
+    async fn delete(&self, view_id: &str) -> Result<String>;
+
+    /// Records with the hash of the content, alive and in trash, the content is not loaded
+    async fn find_by_hash(&self, content_hash: &str) -> Result<Vec<StoredRecord>>;
+
+    /// Record of the view with the full content
+    async fn find_by_view_id(&self, view_id: &str) -> Result<Option<StoredRecord>>;
+
+    /// Records matching the filter after the cursor, the content is not loaded. Tags are
+    /// only narrowed down by the first one, check them with `RecordQuery::matches_tags`
+    async fn page(
+        &self,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+    ) -> Result<StoredPage>;
+
+    async fn count(&self, filter: &RecordQuery) -> Result<i64>;
+
+    /// Keep the content replaced by an edit of the record
+    async fn append_version(&self, document_id: &str, version: RecordVersion) -> Result<()>;
+
+    /// Previous versions of the record, the newest first
+    async fn versions(&self, document_id: &str) -> Result<Vec<RecordVersion>>;
+
+    /// Delete the previous versions of the deleted record
+    async fn remove_versions(&self, document_id: &str) -> Result<()>;
+
+    /// Hash identifying the content, the records with the same hash are merged
+    fn content_hash(&self, content: &str) -> Result<String> {
+        Ok(string::content_hash(content))
+    }
+
+    /// Hash the older versions identified the content with, the records they wrote keep it
+    /// until they are rewritten. None when it is the same as `content_hash`
+    fn legacy_content_hash(&self, _content: &str) -> Option<String> {
+        None
+    }
+
+    /// Rewrite the records stored in cleartext by the older versions or encrypted with a
+    /// replaced key, return the count
+    async fn encrypt_legacy_records(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Move the records of this device from the schemas the spaces do not use any more into
+    /// the current ones, return the count
+    async fn move_space_records(&self) -> Result<usize> {
+        Ok(0)
+    }
+}
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use graphql_client::GraphQLQuery;
+use log::{error, info};
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationValue};
+
+use crate::graphql::{register_by_pages, GraphRegisterPageDocuments, RegisterByPages};
+use crate::models::register;
+use crate::models::register::{RegisterEntry, REGISTER_HISTORY_LEN};
+use crate::p2panda::graphql::GraphQLHandler;
+use crate::p2panda::schema_registry::schema_id;
+use crate::utils::string;
+
+/// Registers live in their own schema, so they are never counted or evicted by the
+/// record maintenance tasks
+pub struct RegisterDao;
+
+impl RegisterDao {
+    const PAGE_SIZE: i64 = 50;
+
+    /// Copy the content into the register, older entries beyond the history length
+    /// are deleted
+    pub async fn copy_into_register(
+        name: &str,
+        content: String,
+        content_preview: Option<String>,
+        data_type: String,
+    ) -> Result<String> {
+        let md5_str = string::md5(&content);
+        let history = Self::register_history(name).await?;
+        if let Some(latest) = history.first() {
+            if latest.md5.eq(&md5_str) {
+                info!("register {} already holds the content", name);
+                return Ok(latest.view_id.clone());
+            }
+        }
+
+        let opt = OperationBuilder::new(&schema_id(register::SCHEMA_NAME)?)
+            .action(OperationAction::Create)
+            .fields(&[
+                ("name", OperationValue::String(name.to_string())),
+                ("content", OperationValue::String(content)),
+                (
+                    "content_preview",
+                    OperationValue::String(content_preview.unwrap_or_default()),
+                ),
+                ("data_type", OperationValue::String(data_type)),
+                ("md5", OperationValue::String(md5_str)),
+                (
+                    "create_time",
+                    OperationValue::Integer(chrono::Local::now().timestamp()),
+                ),
+            ])
+            .build()?;
+
+        let res = GraphQLHandler::global()?
+            .send_to_node(PlainOperation::from(&opt))
+            .await?;
+        info!("copy into register {} success, opt id: {}", name, res);
+
+        // The new entry takes one place of the history
+        for entry in history.iter().skip(REGISTER_HISTORY_LEN - 1) {
+            if let Err(err) = Self::delete_entry(&entry.view_id).await {
+                error!("delete register entry {} error: {}", entry.view_id, err);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Entries of the register, the newest first
+    pub async fn register_history(name: &str) -> Result<Vec<RegisterEntry>> {
+        Self::entries(Some(vec![name.to_string()])).await
+    }
+
+    /// Current value of each non-empty register
+    pub async fn list_registers() -> Result<Vec<RegisterEntry>> {
+        Ok(register::latest_entries(Self::entries(None).await?))
+    }
+
+    /// Delete all entries of the register, return the count of deleted entries
+    pub async fn clear_register(name: &str) -> Result<usize> {
+        let history = Self::register_history(name).await?;
+        for entry in history.iter() {
+            Self::delete_entry(&entry.view_id).await?;
+        }
+        info!("clear register {} success, deleted: {}", name, history.len());
+        Ok(history.len())
+    }
+
+    async fn delete_entry(view_id: &str) -> Result<String> {
+        let opt = OperationBuilder::new(&schema_id(register::SCHEMA_NAME)?)
+            .action(OperationAction::Delete)
+            .previous(&DocumentViewId::from_str(view_id)?)
+            .build()?;
+
+        let handler = GraphQLHandler::global()?;
+        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
+        info!("delete register entry success, opt id: {}", res);
+        Ok(res)
+    }
+
+    async fn entries(name_filter: Option<Vec<String>>) -> Result<Vec<RegisterEntry>> {
+        let mut entries = vec![];
+        let mut start_cursor = None;
+        loop {
+            let (docs, end_cursor, has_next_page) =
+                Self::register_by_pages(Some(Self::PAGE_SIZE), start_cursor, name_filter.clone())
+                    .await?;
+            for doc in docs {
+                let (fields, meta) = (doc.fields.unwrap(), doc.meta.unwrap());
+                entries.push(RegisterEntry {
+                    view_id: meta.view_id,
+                    name: fields.name.unwrap_or_default(),
+                    content: fields.content.unwrap_or_default(),
+                    content_preview: fields.content_preview,
+                    data_type: fields.data_type.unwrap_or_default(),
+                    md5: fields.md5.unwrap_or_default(),
+                    create_time: fields.create_time.unwrap_or_default(),
+                });
+            }
+            if !has_next_page {
+                break;
+            }
+            start_cursor = Some(end_cursor);
+        }
+        Ok(entries)
+    }
+
+    async fn register_by_pages(
+        limit: Option<i64>,
+        start_cursor: Option<String>,
+        name_filter: Option<Vec<String>>,
+    ) -> Result<(Vec<GraphRegisterPageDocuments>, String, bool)> {
+        let handler = GraphQLHandler::global()?;
+        let request_body = RegisterByPages::build_query(register_by_pages::Variables {
+            limit,
+            start_cursor,
+            name_filter,
+        });
+
+        let data: Option<register_by_pages::ResponseData> = handler.post(&request_body).await?;
+
+        match data {
+            None => Ok((vec![], String::new(), false)),
+            Some(res) => {
+                let res = res.registers;
+                Ok((res.documents, res.end_cursor, res.has_next_page))
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//!
+//! Records split between the store synced with the paired devices and the local one. The
+//! records with `sync` off are written to the local store only, so they never reach the node
+//! nor the peers. `RecordDao` sees a single store, the writes go to the store holding the
+//! record and the pages of both are merged. The local-only records are not in the schemas
+//! of a space, the local store lists the ones written to the active space
+//!
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use p2panda_rs::operation::OperationValue;
+use serde::{Deserialize, Serialize};
+
+use crate::dao::memory_record_store::MemoryRecordStore;
+use crate::dao::record_store::{RecordStore, StoredPage, StoredRecord};
+use crate::models::record::{Record, RecordVersion};
+use crate::models::record_list::{RecordQuery, RecordSort};
+use crate::utils::json;
+
+pub struct SelectiveRecordStore {
+    synced: Arc<dyn RecordStore>,
+    local: Arc<MemoryRecordStore>,
+}
+
+/// Cursor of the merged pages, the position in each store
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+struct MergedCursor {
+    // cursors of the last documents taken from the stores, none to start from the first one
+    synced: Option<String>,
+    local: Option<String>,
+}
+
+impl SelectiveRecordStore {
+    pub fn new(synced: Arc<dyn RecordStore>, local: Arc<MemoryRecordStore>) -> Self {
+        Self { synced, local }
+    }
+
+    /// Store holding the view, the local one knows all its views
+    fn store(&self, view_id: &str) -> &dyn RecordStore {
+        if self.local.contains(view_id) {
+            self.local.as_ref()
+        } else {
+            self.synced.as_ref()
+        }
+    }
+
+    fn document_store(&self, document_id: &str) -> &dyn RecordStore {
+        if self.local.contains_document(document_id) {
+            self.local.as_ref()
+        } else {
+            self.synced.as_ref()
+        }
+    }
+}
+
+#[async_trait]
+impl RecordStore for SelectiveRecordStore {
+    async fn create(&self, record: Record) -> Result<String> {
+        if record.sync {
+            self.synced.create(record).await
+        } else {
+            self.local.create(record).await
+        }
+    }
+
+    async fn update(&self, view_id: &str, fields: &[(&str, OperationValue)]) -> Result<String> {
+        self.store(view_id).update(view_id, fields).await
+    }
+
+    async fn delete(&self, view_id: &str) -> Result<String> {
+        self.store(view_id).delete(view_id).await
+    }
+
+    async fn find_by_hash(&self, content_hash: &str) -> Result<Vec<StoredRecord>> {
+        let mut docs = self.synced.find_by_hash(content_hash).await?;
+        docs.extend(self.local.find_by_hash(content_hash).await?);
+        Ok(docs)
+    }
+
+    async fn find_by_view_id(&self, view_id: &str) -> Result<Option<StoredRecord>> {
+        self.store(view_id).find_by_view_id(view_id).await
+    }
+
+    async fn page(
+        &self,
+        filter: &RecordQuery,
+        sort: &RecordSort,
+        limit: i64,
+        after: Option<String>,
+    ) -> Result<StoredPage> {
+        let mut position = match after {
+            Some(after) => json::parse::<MergedCursor>(&after)?,
+            None => MergedCursor::default(),
+        };
+        // Each store returns its first documents after its position, the page takes the
+        // first ones of both
+        let synced_page = match filter.sync {
+            Some(false) => StoredPage::default(),
+            _ => {
+                self.synced
+                    .page(filter, sort, limit, position.synced.clone())
+                    .await?
+            }
+        };
+        let local_page = match filter.sync {
+            Some(true) => StoredPage::default(),
+            _ => {
+                self.local
+                    .page(filter, sort, limit, position.local.clone())
+                    .await?
+            }
+        };
+
+        let mut more = synced_page.end_cursor.is_some() || local_page.end_cursor.is_some();
+        let mut synced_docs = synced_page.records.into_iter().peekable();
+        let mut local_docs = local_page.records.into_iter().peekable();
+        let mut records = vec![];
+        while records.len() < limit.max(0) as usize {
+            let take_local = match (synced_docs.peek(), local_docs.peek()) {
+                (None, None) => break,
+                (Some(s), Some(l)) => {
+                    let (s_key, l_key) = (
+                        sort.key(&s.record, &s.document_id),
+                        sort.key(&l.record, &l.document_id),
+                    );
+                    sort.compare(&l_key, &s_key) == Ordering::Less
+                }
+                (synced, _) => synced.is_none(),
+            };
+            let (doc, store_position) = if take_local {
+                (local_docs.next(), &mut position.local)
+            } else {
+                (synced_docs.next(), &mut position.synced)
+            };
+            if let Some(mut doc) = doc {
+                *store_position = Some(doc.cursor.clone());
+                doc.cursor = json::stringify(&position)?;
+                records.push(doc);
+            }
+        }
+        more = more || synced_docs.peek().is_some() || local_docs.peek().is_some();
+
+        let end_cursor = if more {
+            records.last().map(|doc| doc.cursor.clone())
+        } else {
+            None
+        };
+        Ok(StoredPage {
+            records,
+            end_cursor,
+            total_count: synced_page.total_count + local_page.total_count,
+        })
+    }
+
+    async fn count(&self, filter: &RecordQuery) -> Result<i64> {
+        let synced_cnt = match filter.sync {
+            Some(false) => 0,
+            _ => self.synced.count(filter).await?,
+        };
+        let local_cnt = match filter.sync {
+            Some(true) => 0,
+            _ => self.local.count(filter).await?,
+        };
+        Ok(synced_cnt + local_cnt)
+    }
+
+    async fn append_version(&self, document_id: &str, version: RecordVersion) -> Result<()> {
+        self.document_store(document_id)
+            .append_version(document_id, version)
+            .await
+    }
+
+    async fn versions(&self, document_id: &str) -> Result<Vec<RecordVersion>> {
+        self.document_store(document_id).versions(document_id).await
+    }
+
+    async fn remove_versions(&self, document_id: &str) -> Result<()> {
+        self.document_store(document_id)
+            .remove_versions(document_id)
+            .await
+    }
+
+    fn content_hash(&self, content: &str) -> Result<String> {
+        self.synced.content_hash(content)
+    }
+
+    fn legacy_content_hash(&self, content: &str) -> Option<String> {
+        self.synced.legacy_content_hash(content)
+    }
+
+    async fn encrypt_legacy_records(&self) -> Result<usize> {
+        self.synced.encrypt_legacy_records().await
+    }
+
+    async fn move_space_records(&self) -> Result<usize> {
+        self.synced.move_space_records().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::dao::memory_record_store::MemoryRecordStore;
+    use crate::dao::record_store::RecordStore;
+    use crate::dao::selective_record_store::SelectiveRecordStore;
+    use crate::models::record::Record;
+    use crate::models::record_list::{RecordQuery, RecordSort};
+
+    #[tokio::test]
+    async fn test_merged_pages() {
+        let synced = Arc::new(MemoryRecordStore::new("owner"));
+        let local = Arc::new(MemoryRecordStore::new("owner"));
+        let store = SelectiveRecordStore::new(synced.clone(), local.clone());
+        for create_time in 1..=7 {
+            let record = Record {
+                content: create_time.to_string(),
+                create_time,
+                sync: create_time % 3 != 0,
+                ..Default::default()
+            };
+            store.create(record).await.unwrap();
+        }
+        assert_eq!(synced.records().len(), 5);
+        assert_eq!(local.records().len(), 2);
+
+        // Newest first across both stores
+        let (filter, sort) = (RecordQuery::default(), RecordSort::default());
+        let mut times = vec![];
+        let mut after = None;
+        loop {
+            let page = store.page(&filter, &sort, 3, after).await.unwrap();
+            assert_eq!(page.total_count, 7);
+            times.extend(page.records.iter().map(|doc| doc.record.create_time));
+            match page.end_cursor {
+                None => break,
+                Some(end_cursor) => after = Some(end_cursor),
+            }
+        }
+        assert_eq!(times, vec![7, 6, 5, 4, 3, 2, 1]);
+
+        let local_only = RecordQuery {
+            sync: Some(false),
+            ..Default::default()
+        };
+        let page = store.page(&local_only, &sort, 10, None).await.unwrap();
+        let times: Vec<i64> = page.records.iter().map(|d| d.record.create_time).collect();
+        assert_eq!(times, vec![6, 3]);
+        assert_eq!(store.count(&local_only).await.unwrap(), 2);
+
+        // Writes go to the store holding the record
+        let view_id = page.records[0].view_id.clone();
+        store.delete(&view_id).await.unwrap();
+        assert_eq!(local.records().len(), 1);
+        assert_eq!(synced.records().len(), 5);
+    }
+}
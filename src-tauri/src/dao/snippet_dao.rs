@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use graphql_client::GraphQLQuery;
+use log::info;
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationValue};
+
+use crate::graphql::{
+    snippet_by_pages, snippet_by_view_id, GraphSnippetPageDocuments, SnippetByPages,
+    SnippetByViewId,
+};
+use crate::models::snippet;
+use crate::models::snippet::Snippet;
+use crate::p2panda::graphql::GraphQLHandler;
+use crate::p2panda::schema_registry::schema_id;
+
+/// Snippets live in their own schema, so they are never counted or evicted by the
+/// record maintenance tasks
+pub struct SnippetDao;
+
+impl SnippetDao {
+    const PAGE_SIZE: i64 = 50;
+
+    pub async fn create_snippet(name: String, content: String) -> Result<String> {
+        let now = chrono::Local::now().timestamp();
+        let opt = OperationBuilder::new(&schema_id(snippet::SCHEMA_NAME)?)
+            .action(OperationAction::Create)
+            .fields(&[
+                ("name", OperationValue::String(name)),
+                ("content", OperationValue::String(content)),
+                ("create_time", OperationValue::Integer(now)),
+                ("update_time", OperationValue::Integer(now)),
+            ])
+            .build()?;
+
+        let handler = GraphQLHandler::global()?;
+        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
+        info!("create snippet success, opt id: {}", res);
+        Ok(res)
+    }
+
+    pub async fn update_snippet(
+        view_id: &DocumentViewId,
+        name: String,
+        content: String,
+    ) -> Result<String> {
+        let opt = OperationBuilder::new(&schema_id(snippet::SCHEMA_NAME)?)
+            .action(OperationAction::Update)
+            .previous(view_id)
+            .fields(&[
+                ("name", OperationValue::String(name)),
+                ("content", OperationValue::String(content)),
+                (
+                    "update_time",
+                    OperationValue::Integer(chrono::Local::now().timestamp()),
+                ),
+            ])
+            .build()?;
+
+        let handler = GraphQLHandler::global()?;
+        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
+        info!("update snippet success, opt id: {}", res);
+        Ok(res)
+    }
+
+    pub async fn delete_snippet(view_id: &DocumentViewId) -> Result<String> {
+        let opt = OperationBuilder::new(&schema_id(snippet::SCHEMA_NAME)?)
+            .action(OperationAction::Delete)
+            .previous(view_id)
+            .build()?;
+
+        let handler = GraphQLHandler::global()?;
+        let res = handler.send_to_node(PlainOperation::from(&opt)).await?;
+        info!("delete snippet success, opt id: {}", res);
+        Ok(res)
+    }
+
+    pub async fn find_snippet_by_view_id(view_id: String) -> Result<Snippet> {
+        let handler = GraphQLHandler::global()?;
+
+        let request_body = SnippetByViewId::build_query(snippet_by_view_id::Variables {
+            view_id: view_id.clone(),
+        });
+
+        let data: Option<snippet_by_view_id::ResponseData> = handler.post(&request_body).await?;
+
+        let view = data
+            .and_then(|res| res.snippet)
+            .ok_or(anyhow!("snippet not found for view id: {}", view_id))?;
+        let (fields, meta) = (view.fields.unwrap(), view.meta.unwrap());
+        let content = fields.content.unwrap_or_default();
+        Ok(Snippet {
+            view_id: meta.view_id,
+            document_id: meta.document_id.to_string(),
+            name: fields.name.unwrap_or_default(),
+            prompts: snippet::prompt_names(&content),
+            content,
+            create_time: fields.create_time.unwrap_or_default(),
+            update_time: fields.update_time.unwrap_or_default(),
+        })
+    }
+
+    /// All snippets ordered by name
+    pub async fn list_snippets() -> Result<Vec<Snippet>> {
+        let mut snippets = vec![];
+        let mut start_cursor = None;
+        loop {
+            let (docs, end_cursor, has_next_page) =
+                Self::snippet_by_pages(Some(Self::PAGE_SIZE), start_cursor).await?;
+            for doc in docs {
+                let (fields, meta) = (doc.fields.unwrap(), doc.meta.unwrap());
+                let content = fields.content.unwrap_or_default();
+                snippets.push(Snippet {
+                    view_id: meta.view_id,
+                    document_id: meta.document_id.to_string(),
+                    name: fields.name.unwrap_or_default(),
+                    prompts: snippet::prompt_names(&content),
+                    content,
+                    create_time: fields.create_time.unwrap_or_default(),
+                    update_time: fields.update_time.unwrap_or_default(),
+                });
+            }
+            if !has_next_page {
+                break;
+            }
+            start_cursor = Some(end_cursor);
+        }
+        Ok(snippets)
+    }
+
+    async fn snippet_by_pages(
+        limit: Option<i64>,
+        start_cursor: Option<String>,
+    ) -> Result<(Vec<GraphSnippetPageDocuments>, String, bool)> {
+        let handler = GraphQLHandler::global()?;
+        let request_body = SnippetByPages::build_query(snippet_by_pages::Variables {
+            limit,
+            start_cursor,
+        });
+
+        let data: Option<snippet_by_pages::ResponseData> = handler.post(&request_body).await?;
+
+        match data {
+            None => Ok((vec![], String::new(), false)),
+            Some(res) => {
+                let res = res.snippets;
+                Ok((res.documents, res.end_cursor, res.has_next_page))
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//!
+//! Local list of the sync spaces and the active one
+//!
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use parking_lot::RwLock;
+
+use crate::models::space::{parse_space_name, Space, SpaceList, SpaceSchemas};
+use crate::utils::dir::app_data_dir;
+use crate::utils::json;
+
+static SPACES: RwLock<Option<SpaceList>> = RwLock::new(None);
+
+pub struct SpaceDao;
+
+impl SpaceDao {
+    const FILE_NAME: &'static str = "spaces.json";
+
+    pub fn list() -> Result<SpaceList> {
+        if let Some(list) = SPACES.read().as_ref() {
+            return Ok(list.clone());
+        }
+
+        let mut cached = SPACES.write();
+        let list = match cached.as_ref() {
+            Some(list) => list.clone(),
+            None => Self::load()?,
+        };
+        *cached = Some(list.clone());
+        Ok(list)
+    }
+
+    /// Id of the space the copied records go to
+    pub fn active_id() -> Result<String> {
+        Ok(Self::list()?.active)
+    }
+
+    pub fn get(id: &str) -> Result<Space> {
+        Self::list()?
+            .get(id)
+            .cloned()
+            .ok_or(anyhow!("space not found: {}", id))
+    }
+
+    /// The space the records are written to
+    pub fn active() -> Result<Space> {
+        Self::get(&Self::active_id()?)
+    }
+
+    /// The schemas are published by the caller, see `space_schema::create_schemas`
+    pub fn create(name: &str, schemas: SpaceSchemas) -> Result<Space> {
+        let mut space = Space::new(
+            uuid::Uuid::new_v4().to_string(),
+            parse_space_name(name)?,
+            chrono::Local::now().timestamp(),
+        );
+        space.schemas = Some(schemas);
+        Self::add(space.clone())?;
+        Ok(space)
+    }
+
+    /// Add the space shared by a paired device, the existing one is kept with the schemas
+    /// of the shared one
+    pub fn add(space: Space) -> Result<()> {
+        Self::modify(|list| {
+            info!("add space: {}", space.id);
+            list.add(space);
+            Ok(())
+        })
+    }
+
+    pub fn rename(id: &str, name: String) -> Result<()> {
+        Self::modify(|list| list.rename(id, name))
+    }
+
+    pub fn replace_schemas(id: &str, schemas: SpaceSchemas) -> Result<()> {
+        Self::modify(|list| {
+            info!("replace the schemas of space: {}", id);
+            list.replace_schemas(id, schemas)
+        })
+    }
+
+    pub fn remove(id: &str) -> Result<()> {
+        Self::modify(|list| list.remove(id))
+    }
+
+    pub fn switch(id: &str) -> Result<()> {
+        Self::modify(|list| {
+            info!("switch to space: {}", id);
+            list.switch(id)
+        })
+    }
+
+    fn modify<F: FnOnce(&mut SpaceList) -> Result<()>>(f: F) -> Result<()> {
+        let mut cached = SPACES.write();
+        let mut list = match cached.as_ref() {
+            Some(list) => list.clone(),
+            None => Self::load()?,
+        };
+        f(&mut list)?;
+        json::save(&Self::path()?, &list)?;
+        *cached = Some(list);
+        Ok(())
+    }
+
+    fn load() -> Result<SpaceList> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(SpaceList::default());
+        }
+        json::read(&path)
+    }
+
+    fn path() -> Result<PathBuf> {
+        Ok(app_data_dir()?.join(Self::FILE_NAME))
+    }
+}
@@ -1,69 +1,112 @@
 use graphql_client::GraphQLQuery;
 
-use crate::graphql::record_by_md5_query::{
-    RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f,
-    RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocuments,
-    RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocumentsFields,
-    RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocumentsMeta,
-};
-use crate::graphql::record_by_pages::{
-    record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fOrderBy,
-    RecordByPagesAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocuments,
-};
+use crate::graphql::record_by_view_id::RecordByViewIdRecord;
+use crate::graphql::record_list::RecordListRecordsDocuments;
+use crate::graphql::record_v1_by_pages::RecordV1ByPagesRecordsDocuments;
+use crate::graphql::record_v2_by_pages::RecordV2ByPagesRecordsDocuments;
+use crate::graphql::record_version_list::RecordVersionListVersionsDocuments;
+use crate::graphql::register_by_pages::RegisterByPagesRegistersDocuments;
+use crate::graphql::snippet_by_pages::SnippetByPagesSnippetsDocuments;
+use crate::graphql::snippet_by_view_id::SnippetByViewIdSnippet;
+
+// Query documents are rendered by build.rs, the schema ids of `schema/schema.lock` are filled
+// in and the results are aliased, so the generated types do not depend on the schema hash
 
 #[derive(GraphQLQuery)]
 #[graphql(
-    schema_path = "graphql/schema.graphql",
-    query_path = "graphql/next_args.graphql",
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/next_args.graphql",
     response_derives = "Debug, Serialize, Deserialize"
 )]
 pub struct NextArgsQuery;
 
 #[derive(GraphQLQuery)]
 #[graphql(
-    schema_path = "graphql/schema.graphql",
-    query_path = "graphql/publish.graphql",
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/publish.graphql",
     response_derives = "Debug, Serialize, Deserialize"
 )]
 pub struct PublishMut;
 
 #[derive(GraphQLQuery)]
 #[graphql(
-    schema_path = "graphql/schema.graphql",
-    query_path = "graphql/record.graphql",
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/record.graphql",
     response_derives = "Debug, Serialize, Deserialize"
 )]
-pub struct RecordByMd5Query;
+pub struct RecordByViewId;
 
 #[derive(GraphQLQuery)]
 #[graphql(
-    schema_path = "graphql/schema.graphql",
-    query_path = "graphql/record.graphql",
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/record.graphql",
     response_derives = "Debug, Serialize, Deserialize"
 )]
-pub struct RecordCounts;
+pub struct RecordList;
 
 #[derive(GraphQLQuery)]
 #[graphql(
-    schema_path = "graphql/schema.graphql",
-    query_path = "graphql/record.graphql",
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/record.graphql",
     response_derives = "Debug, Serialize, Deserialize"
 )]
-pub struct RecordByPages;
+pub struct RecordV1ByPages;
 
-pub type GraphRecord =
-    RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f;
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/record.graphql",
+    response_derives = "Debug, Serialize, Deserialize"
+)]
+pub struct RecordV2ByPages;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/snippet.graphql",
+    response_derives = "Debug, Serialize, Deserialize"
+)]
+pub struct SnippetByPages;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/snippet.graphql",
+    response_derives = "Debug, Serialize, Deserialize"
+)]
+pub struct SnippetByViewId;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/register.graphql",
+    response_derives = "Debug, Serialize, Deserialize"
+)]
+pub struct RegisterByPages;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/generated/schema.graphql",
+    query_path = "graphql/generated/record_version.graphql",
+    response_derives = "Debug, Serialize, Deserialize"
+)]
+pub struct RecordVersionList;
+
+pub type GraphRecordView = RecordByViewIdRecord;
+
+pub type GraphRecordListDocuments = RecordListRecordsDocuments;
+
+pub type GraphRecordV1Documents = RecordV1ByPagesRecordsDocuments;
 
-pub type GraphRecordDocuments = RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocuments;
+pub type GraphRecordV2Documents = RecordV2ByPagesRecordsDocuments;
 
-pub type GraphRecordDocumentsFields = RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocumentsFields;
+pub type GraphSnippetPageDocuments = SnippetByPagesSnippetsDocuments;
 
-pub type GraphRecordDocumentsMeta = RecordByMd5QueryAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocumentsMeta;
+pub type GraphSnippetView = SnippetByViewIdSnippet;
 
-pub type GraphRecordPageDocuments = RecordByPagesAllRecord002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fDocuments;
+pub type GraphRegisterPageDocuments = RegisterByPagesRegistersDocuments;
 
-pub type GraphRecordOrderBy =
-    record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099fOrderBy;
+pub type GraphRecordVersionDocuments = RecordVersionListVersionsDocuments;
 
 pub type DocumentId = p2panda_rs::document::DocumentId;
 
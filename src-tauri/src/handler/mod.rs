@@ -1,2 +1,5 @@
 pub mod global_handler;
 pub mod model;
+pub mod pairing;
+pub mod paste_queue;
+pub mod register;
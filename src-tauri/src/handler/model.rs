@@ -7,6 +7,11 @@ pub enum MessageTypeEnum {
     ChangeClipboardBackend,
     UpdateClipboardRecordBackend,
     DeleteClipboardRecordBackend,
+    PasteQueueChangeBackend,
+    RegisterChangeBackend,
+    EvictionProgressBackend,
+    PairingRequestBackend,
+    PairingResultBackend,
 }
 
 impl From<MessageTypeEnum> for &'static str {
@@ -19,6 +24,11 @@ impl From<MessageTypeEnum> for &'static str {
             MessageTypeEnum::DeleteClipboardRecordBackend => {
                 "cbs://delete-clipboard-record-backend"
             }
+            MessageTypeEnum::PasteQueueChangeBackend => "cbs://paste-queue-change-backend",
+            MessageTypeEnum::RegisterChangeBackend => "cbs://register-change-backend",
+            MessageTypeEnum::EvictionProgressBackend => "cbs://eviction-progress-backend",
+            MessageTypeEnum::PairingRequestBackend => "cbs://pairing-request-backend",
+            MessageTypeEnum::PairingResultBackend => "cbs://pairing-result-backend",
         }
     }
 }
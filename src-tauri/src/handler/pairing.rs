@@ -0,0 +1,404 @@
+//!
+//! Pairing of two devices on the LAN. The inviting device shows an invite with the address
+//! of a one-time listener and a commitment to its ephemeral X25519 key, the joining device
+//! connects to it, both derive the session key and show a 6 digit code. After the user
+//! confirms the codes match on both devices, the inviting device sends the key and the
+//! schemas of its active space and both add the other one to the members of the space. The
+//! node is restarted to sync the schemas with the new member
+//!
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use chacha20poly1305::aead::OsRng;
+use log::{error, info};
+use parking_lot::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::config::app_config::AppConfig;
+use crate::config::configure::DEFAULT_PAIRING_PORT;
+use crate::dao::paired_device_dao::PairedDeviceDao;
+use crate::dao::space_dao::SpaceDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+use crate::listener::maintenance::MaintenanceListener;
+use crate::models::pairing::{
+    pairing_code, session_key, PairedDevice, PairingInvite, PairingMessage, PairingRequest,
+    PairingResult,
+};
+use crate::models::space::{check_space_id, Space};
+use crate::p2panda::group_key::{self, set_space_key, space_keys};
+use crate::p2panda::key_pair::device_id;
+use crate::p2panda::node::NodeServer;
+use crate::utils::crypto;
+use crate::utils::ip::local_ip;
+
+// The invite expires and the user has to confirm the code in it
+const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+struct PairingSession {
+    // increased for each session, so a finished session does not clear the next one
+    id: u64,
+    confirm: Option<oneshot::Sender<bool>>,
+    task: Option<JoinHandle<()>>,
+}
+
+/// Json lines on the pairing connection
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, msg: &PairingMessage) -> Result<()> {
+        let mut line = serde_json::to_string(msg)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<PairingMessage> {
+        let line = tokio::time::timeout(PAIRING_TIMEOUT, self.lines.next_line())
+            .await
+            .map_err(|_| anyhow!("pairing timed out"))??
+            .ok_or(anyhow!("pairing connection closed"))?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// Device on the other side after the key exchange
+struct Peer {
+    request: PairingRequest,
+    session_key: [u8; 32],
+}
+
+pub struct PairingHandler;
+
+impl PairingHandler {
+    /// Listen for the joining device, the invite is shown as a QR code or text. The device
+    /// joins the active space
+    pub async fn start(device_name: String) -> Result<PairingInvite> {
+        Self::cancel();
+
+        let spaces = SpaceDao::list()?;
+        let space = spaces
+            .get(&spaces.active)
+            .cloned()
+            .ok_or(anyhow!("active space not found: {}", spaces.active))?;
+        if space.schemas.is_none() {
+            bail!("the schemas of the space are not published yet, restart the app and try again");
+        }
+        let port = AppConfig::latest()
+            .read()
+            .pairing_port
+            .unwrap_or(DEFAULT_PAIRING_PORT);
+        let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let invite = PairingInvite::new(
+            format!("{}:{}", local_ip(), port),
+            device_id()?,
+            public.as_bytes(),
+        );
+        info!("start pairing on port: {}", port);
+
+        Self::spawn(|id| async move {
+            let (stream, addr) = tokio::time::timeout(PAIRING_TIMEOUT, listener.accept())
+                .await
+                .map_err(|_| anyhow!("no device joined the pairing"))??;
+            // One device per invite, the listener is closed once a device connected
+            drop(listener);
+            info!("device joined the pairing from: {}", addr);
+
+            let mut conn = Connection::new(stream);
+            let peer = Self::accept_hello(&mut conn, secret, public, device_name).await?;
+            Self::push_message(MessageTypeEnum::PairingRequestBackend, peer.request.clone());
+            Self::confirm_with_peer(id, &mut conn, &peer).await?;
+
+            let key = group_key::encode(&space_keys(&space.id)?[0]);
+            conn.send(&PairingMessage::GroupKey {
+                space_id: space.id.clone(),
+                space_name: space.name,
+                sealed: crypto::encrypt(&peer.session_key, &key)?,
+                schemas: space.schemas,
+            })
+            .await?;
+            Ok((peer, space.id))
+        });
+        Ok(invite)
+    }
+
+    /// Connect to the inviting device, the returned code is compared with the one shown
+    /// there and confirmed with `confirm`
+    pub async fn join(invite: &str, device_name: String) -> Result<PairingRequest> {
+        Self::cancel();
+
+        let invite = PairingInvite::decode(invite)?;
+        let stream = tokio::time::timeout(PAIRING_TIMEOUT, TcpStream::connect(&invite.address))
+            .await
+            .map_err(|_| anyhow!("connect to {} timed out", invite.address))??;
+        let mut conn = Connection::new(stream);
+        let peer = Self::send_hello(&mut conn, &invite, device_name).await?;
+        let request = peer.request.clone();
+        info!("joined the pairing of: {}", request.device_id);
+
+        Self::spawn(|id| async move {
+            Self::confirm_with_peer(id, &mut conn, &peer).await?;
+
+            let (space_id, space_name, sealed, schemas) = match conn.receive().await? {
+                PairingMessage::GroupKey {
+                    space_id,
+                    space_name,
+                    sealed,
+                    schemas,
+                } => (space_id, space_name, sealed, schemas),
+                msg => bail!("unexpected pairing message: {:?}", msg),
+            };
+            check_space_id(&space_id)?;
+            let schemas = schemas.ok_or(anyhow!("the device did not send the space schemas"))?;
+            let key = group_key::decode(&crypto::decrypt(&peer.session_key, &sealed)?)?;
+            let mut space = Space::new(
+                space_id.clone(),
+                space_name,
+                chrono::Local::now().timestamp(),
+            );
+            space.schemas = Some(schemas);
+            SpaceDao::add(space)?;
+            set_space_key(&space_id, key)?;
+            // The records written to the replaced schemas or with the replaced key are moved
+            // and encrypted again
+            MaintenanceListener::trigger();
+            Ok((peer, space_id))
+        });
+        Ok(request)
+    }
+
+    /// The user compared the codes on both devices
+    pub fn confirm(accepted: bool) -> Result<()> {
+        let confirm = Self::global().lock().confirm.take();
+        match confirm {
+            Some(confirm) => confirm
+                .send(accepted)
+                .map_err(|_| anyhow!("pairing already finished")),
+            None => bail!("no pairing waiting for confirmation"),
+        }
+    }
+
+    pub fn cancel() {
+        let mut session = Self::global().lock();
+        session.confirm = None;
+        if let Some(task) = session.task.take() {
+            task.abort();
+            info!("pairing cancelled");
+        }
+    }
+
+    async fn accept_hello(
+        conn: &mut Connection,
+        secret: EphemeralSecret,
+        public: PublicKey,
+        device_name: String,
+    ) -> Result<Peer> {
+        let (peer_id, peer_name, peer_key) = Self::receive_hello(conn).await?;
+        let own_id = device_id()?;
+        conn.send(&PairingMessage::Hello {
+            device_id: own_id.clone(),
+            device_name,
+            ephemeral_key: hex::encode(public.as_bytes()),
+        })
+        .await?;
+
+        let shared = secret.diffie_hellman(&PublicKey::from(peer_key));
+        if !shared.was_contributory() {
+            bail!("invalid ephemeral key from: {}", peer_id);
+        }
+        let session_key = session_key(
+            shared.as_bytes(),
+            (&own_id, public.as_bytes()),
+            (&peer_id, &peer_key),
+        );
+        Ok(Self::peer(peer_id, peer_name, session_key))
+    }
+
+    async fn send_hello(
+        conn: &mut Connection,
+        invite: &PairingInvite,
+        device_name: String,
+    ) -> Result<Peer> {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let own_id = device_id()?;
+        conn.send(&PairingMessage::Hello {
+            device_id: own_id.clone(),
+            device_name,
+            ephemeral_key: hex::encode(public.as_bytes()),
+        })
+        .await?;
+
+        let (peer_id, peer_name, peer_key) = Self::receive_hello(conn).await?;
+        if !invite.verify(&peer_id, &peer_key) {
+            bail!("the device does not match the invite");
+        }
+        let shared = secret.diffie_hellman(&PublicKey::from(peer_key));
+        if !shared.was_contributory() {
+            bail!("invalid ephemeral key from: {}", peer_id);
+        }
+        let session_key = session_key(
+            shared.as_bytes(),
+            (&peer_id, &peer_key),
+            (&own_id, public.as_bytes()),
+        );
+        Ok(Self::peer(peer_id, peer_name, session_key))
+    }
+
+    async fn receive_hello(conn: &mut Connection) -> Result<(String, String, [u8; 32])> {
+        match conn.receive().await? {
+            PairingMessage::Hello {
+                device_id,
+                device_name,
+                ephemeral_key,
+            } => {
+                let ephemeral_key: [u8; 32] = hex::decode(ephemeral_key)?
+                    .try_into()
+                    .map_err(|_| anyhow!("ephemeral key must be 32 bytes"))?;
+                Ok((device_id, device_name, ephemeral_key))
+            }
+            msg => bail!("unexpected pairing message: {:?}", msg),
+        }
+    }
+
+    fn peer(device_id: String, device_name: String, session_key: [u8; 32]) -> Peer {
+        Peer {
+            request: PairingRequest {
+                device_id,
+                device_name,
+                code: pairing_code(&session_key),
+            },
+            session_key,
+        }
+    }
+
+    /// Wait for the user of this device, then for the other device
+    async fn confirm_with_peer(id: u64, conn: &mut Connection, peer: &Peer) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut session = Self::global().lock();
+            if session.id != id {
+                bail!("pairing cancelled");
+            }
+            session.confirm = Some(sender);
+        }
+        let accepted = tokio::time::timeout(PAIRING_TIMEOUT, receiver)
+            .await
+            .map_err(|_| anyhow!("pairing code not confirmed in time"))?
+            .map_err(|_| anyhow!("pairing cancelled"))?;
+
+        conn.send(&PairingMessage::Confirm { accepted }).await?;
+        if !accepted {
+            bail!("pairing rejected");
+        }
+        match conn.receive().await? {
+            PairingMessage::Confirm { accepted: true } => {
+                info!("pairing confirmed by: {}", peer.request.device_id);
+                Ok(())
+            }
+            PairingMessage::Confirm { accepted: false } => {
+                bail!("pairing rejected by the other device")
+            }
+            msg => bail!("unexpected pairing message: {:?}", msg),
+        }
+    }
+
+    /// Run the rest of the pairing in background, the result is pushed to the window
+    fn spawn<F, Fut>(f: F)
+    where
+        F: FnOnce(u64) -> Fut,
+        Fut: std::future::Future<Output = Result<(Peer, String)>> + Send + 'static,
+    {
+        let mut session = Self::global().lock();
+        let id = session.id + 1;
+        let fut = f(id);
+        let task = tauri::async_runtime::spawn(async move {
+            let res = match fut.await.and_then(Self::save_peer) {
+                // The node reads the paired devices and the schemas when it starts
+                Ok(paired) => NodeServer::restart().await.map(|_| paired),
+                Err(e) => Err(e),
+            };
+            let result = match res {
+                Ok((device, space_id)) => PairingResult {
+                    device_id: device.device_id,
+                    space_id,
+                    paired: true,
+                    message: "配对成功，开始同步".to_string(),
+                },
+                Err(e) => {
+                    error!("pairing err: {:?}", e);
+                    PairingResult {
+                        device_id: String::new(),
+                        space_id: String::new(),
+                        paired: false,
+                        message: format!("配对失败: {}", e),
+                    }
+                }
+            };
+            Self::push_message(MessageTypeEnum::PairingResultBackend, result);
+            Self::finish(id);
+        });
+        *session = PairingSession {
+            id,
+            confirm: None,
+            task: Some(task),
+        };
+    }
+
+    fn save_peer((peer, space_id): (Peer, String)) -> Result<(PairedDevice, String)> {
+        let device = PairedDevice {
+            device_id: peer.request.device_id,
+            name: peer.request.device_name,
+            paired_at: chrono::Local::now().timestamp(),
+            spaces: vec![space_id.clone()],
+        };
+        PairedDeviceDao::save(device.clone())?;
+        Ok((device, space_id))
+    }
+
+    fn finish(id: u64) {
+        let mut session = Self::global().lock();
+        if session.id == id {
+            session.confirm = None;
+            session.task = None;
+        }
+    }
+
+    fn push_message<M: serde::Serialize + Clone + std::fmt::Debug>(
+        msg_type: MessageTypeEnum,
+        msg: M,
+    ) {
+        if let Err(e) = GlobalHandler::push_message_to_window(msg_type, msg) {
+            error!("send pairing message err: {:?}", e)
+        };
+    }
+
+    fn global() -> &'static Mutex<PairingSession> {
+        static SESSION: Mutex<PairingSession> = Mutex::new(PairingSession {
+            id: 0,
+            confirm: None,
+            task: None,
+        });
+
+        &SESSION
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Paste queue collects the copied records and pastes them back one by one
+//!
+use anyhow::Result;
+use log::{error, info};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+use crate::models::image_data::ImageData;
+use crate::models::paste_queue::{PasteQueue, PasteQueueMode, PasteQueueState, QueueItem};
+use crate::models::record::DataTypeEnum;
+use crate::utils::clipboard::ClipBoardOperator;
+use crate::utils::json;
+
+pub struct PasteQueueHandler;
+
+impl PasteQueueHandler {
+    pub fn start(mode: PasteQueueMode) -> PasteQueueState {
+        let state = {
+            let mut queue = Self::global().lock();
+            queue.start(mode);
+            queue.state()
+        };
+        info!("paste queue started with mode: {:?}", mode);
+        Self::notify_state_changed(&state);
+        state
+    }
+
+    pub fn stop() -> PasteQueueState {
+        let state = {
+            let mut queue = Self::global().lock();
+            queue.stop();
+            queue.state()
+        };
+        info!("paste queue stopped");
+        Self::notify_state_changed(&state);
+        state
+    }
+
+    pub fn state() -> PasteQueueState {
+        Self::global().lock().state()
+    }
+
+    /// Push the captured record when the queue mode is active
+    pub fn push(item: QueueItem) {
+        let state = {
+            let mut queue = Self::global().lock();
+            if !queue.push(item) {
+                return;
+            }
+            queue.state()
+        };
+        Self::notify_state_changed(&state);
+    }
+
+    /// Pop the next record onto the system clipboard
+    pub fn paste_next() -> Result<Option<QueueItem>> {
+        let (item, state) = {
+            let mut queue = Self::global().lock();
+            (queue.pop(), queue.state())
+        };
+
+        if let Some(item) = &item {
+            if item.data_type.eq(&String::from(DataTypeEnum::IMAGE)) {
+                ClipBoardOperator::set_image(json::parse::<ImageData>(&item.content)?)?;
+            } else {
+                // The listener only captures changes, the skip would swallow the next copy
+                if ClipBoardOperator::get_text().is_ok_and(|text| text.eq(&item.content)) {
+                    Self::global().lock().clear_skip();
+                }
+                ClipBoardOperator::set_text(item.content.clone())?;
+            }
+            Self::notify_state_changed(&state);
+        }
+        Ok(item)
+    }
+
+    fn notify_state_changed(state: &PasteQueueState) {
+        if let Err(e) = GlobalHandler::push_message_to_window(
+            MessageTypeEnum::PasteQueueChangeBackend,
+            state.clone(),
+        ) {
+            error!("send PasteQueueChangeBackend message err: {:?}", e)
+        };
+    }
+
+    fn global() -> &'static Mutex<PasteQueue> {
+        static QUEUE: OnceCell<Mutex<PasteQueue>> = OnceCell::new();
+
+        QUEUE.get_or_init(|| Mutex::new(PasteQueue::default()))
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Named registers a-z, copy the clipboard content into a register and paste it back later
+//!
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+use log::{error, info};
+
+use crate::dao::register_dao::RegisterDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+use crate::models::image_data::ImageData;
+use crate::models::record;
+use crate::models::record::DataTypeEnum;
+use crate::models::register::{parse_register_name, RegisterEntry};
+use crate::utils::clipboard::ClipBoardOperator;
+use crate::utils::{image, json};
+
+pub struct RegisterHandler;
+
+impl RegisterHandler {
+    /// Copy the current clipboard content into the register
+    pub async fn copy_from_clipboard(name: &str) -> Result<String> {
+        let name = parse_register_name(name)?;
+
+        let (content, content_preview, data_type) = {
+            let mut clipboard = Clipboard::new()?;
+            if let Ok(text) = clipboard.get_text() {
+                let preview = record::text_preview(&text);
+                (text, Some(preview), DataTypeEnum::TEXT)
+            } else {
+                let img = clipboard
+                    .get_image()
+                    .map_err(|e| anyhow!("clipboard is empty: {}", e))?;
+                let content = json::stringify(&ImageData {
+                    width: img.width,
+                    height: img.height,
+                    base64: image::rgba8_to_base64(&img),
+                })?;
+                // 压缩画质作为预览图，防止渲染时非常卡顿
+                let content_preview = json::stringify(&ImageData {
+                    width: img.width,
+                    height: img.height,
+                    base64: image::rgba8_to_jpeg_base64(&img, 75),
+                })?;
+                (content, Some(content_preview), DataTypeEnum::IMAGE)
+            }
+        };
+
+        let res =
+            RegisterDao::copy_into_register(&name, content, content_preview, data_type.into())
+                .await?;
+        Self::notify_register_changed(&name);
+        Ok(res)
+    }
+
+    /// Put the register value onto the clipboard, `view_id` picks an entry of the history
+    /// instead of the latest one
+    pub async fn paste(name: &str, view_id: Option<String>) -> Result<Option<RegisterEntry>> {
+        let name = parse_register_name(name)?;
+        let history = RegisterDao::register_history(&name).await?;
+        let entry = match view_id {
+            Some(view_id) => history.into_iter().find(|e| e.view_id.eq(&view_id)),
+            None => history.into_iter().next(),
+        };
+
+        match &entry {
+            Some(entry) => {
+                if entry.data_type.eq(&String::from(DataTypeEnum::IMAGE)) {
+                    ClipBoardOperator::set_image(json::parse::<ImageData>(&entry.content)?)?;
+                } else {
+                    ClipBoardOperator::set_text(entry.content.clone())?;
+                }
+                info!("paste from register {}", name);
+            }
+            None => info!("register {} is empty", name),
+        }
+        Ok(entry)
+    }
+
+    pub async fn clear(name: &str) -> Result<usize> {
+        let name = parse_register_name(name)?;
+        let res = RegisterDao::clear_register(&name).await?;
+        Self::notify_register_changed(&name);
+        Ok(res)
+    }
+
+    fn notify_register_changed(name: &str) {
+        if let Err(e) =
+            GlobalHandler::push_message_to_window(MessageTypeEnum::RegisterChangeBackend, name)
+        {
+            error!("send RegisterChangeBackend message err: {:?}", e)
+        };
+    }
+}
@@ -6,11 +6,13 @@ use crate::config::app_config::AppConfig;
 use crate::dao::record_dao::RecordDao;
 use crate::handler::global_handler::GlobalHandler;
 use crate::handler::model::MessageTypeEnum;
+use crate::handler::paste_queue::PasteQueueHandler;
 use crate::models::image_data::ImageData;
+use crate::models::paste_queue::QueueItem;
 use crate::models::record;
 use crate::models::record::Record;
 use crate::utils::ip::local_ip;
-use crate::utils::{image, json, string};
+use crate::utils::{image, json, source_app, string};
 
 pub struct ClipboardListener;
 
@@ -18,10 +20,8 @@ impl ClipboardListener {
     // Check clipboard content in each 1 second
     const WAIT_MILLIS: i64 = 1000;
 
-    const TEXT_PREVIEW_LEN: usize = 48;
-
-    pub fn listen() {
-        tauri::async_runtime::spawn(async {
+    pub fn listen(dao: &'static RecordDao) {
+        tauri::async_runtime::spawn(async move {
             let mut last_md5 = String::new();
             let mut clipboard = Clipboard::new().unwrap();
             info!("start clipboard listener");
@@ -29,13 +29,13 @@ impl ClipboardListener {
             loop {
                 let mut need_notify = false;
                 if let Ok(text) = clipboard.get_text() {
-                    Self::handle_text_message(text, &mut last_md5, &mut need_notify).await;
+                    Self::handle_text_message(dao, text, &mut last_md5, &mut need_notify).await;
                 }
                 if let Ok(img) = clipboard.get_image() {
-                    Self::handle_image_message(img, &mut last_md5, &mut need_notify).await;
+                    Self::handle_image_message(dao, img, &mut last_md5, &mut need_notify).await;
                 }
 
-                need_notify = Self::handle_record_limit().await || need_notify;
+                need_notify = Self::handle_record_limit(dao).await || need_notify;
                 if need_notify {
                     GlobalHandler::push_message_to_window(
                         MessageTypeEnum::ChangeClipboardBackend,
@@ -50,6 +50,7 @@ impl ClipboardListener {
     }
 
     async fn handle_text_message(
+        dao: &RecordDao,
         text: String,
         last_content_md5: &mut String,
         need_notify: &mut bool,
@@ -58,28 +59,26 @@ impl ClipboardListener {
         let md5 = string::md5(&content);
         if !content.is_empty() && md5.ne(last_content_md5) {
             // Has new clip contents
-            let content_preview = if content.len() > Self::TEXT_PREVIEW_LEN {
-                Some(
-                    content
-                        .trim()
-                        .chars()
-                        .take(Self::TEXT_PREVIEW_LEN)
-                        .collect::<String>()
-                        + "...",
-                )
-            } else {
-                Some(content.to_string())
-            };
+            let content_preview = Some(record::text_preview(&content));
 
-            let data = Record {
+            PasteQueueHandler::push(QueueItem {
+                content: content.clone(),
+                content_preview: content_preview.clone(),
+                data_type: record::DataTypeEnum::TEXT.into(),
+                md5: md5.clone(),
+            });
+
+            let mut data = Record {
                 content,
                 content_preview,
                 data_type: record::DataTypeEnum::TEXT.into(),
                 latest_addr: local_ip().to_string(),
+                source_app: source_app::frontmost_app(),
                 ..Default::default()
             };
+            Self::apply_sync_policy(&mut data);
             debug!("handle_text_message data: {:?}", data);
-            let res = RecordDao::insert_if_not_exist(data).await;
+            let res = dao.insert_if_not_exist(data).await;
 
             match res {
                 Ok(_) => {
@@ -94,6 +93,7 @@ impl ClipboardListener {
     }
 
     async fn handle_image_message(
+        dao: &RecordDao,
         img: arboard::ImageData<'_>,
         last_img_md5: &mut String,
         need_notify: &mut bool,
@@ -116,14 +116,24 @@ impl ClipboardListener {
             };
             let content = json::stringify(&content_db).unwrap();
             let content_preview = json::stringify(&content_preview_db).unwrap();
-            let data = Record {
+
+            PasteQueueHandler::push(QueueItem {
+                content: content.clone(),
+                content_preview: Some(content_preview.clone()),
+                data_type: record::DataTypeEnum::IMAGE.into(),
+                md5: img_md5.clone(),
+            });
+
+            let mut data = Record {
                 content,
                 content_preview: Some(content_preview),
                 data_type: record::DataTypeEnum::IMAGE.into(),
                 latest_addr: local_ip().to_string(),
+                source_app: source_app::frontmost_app(),
                 ..Default::default()
             };
-            let res = RecordDao::insert_if_not_exist(data).await;
+            Self::apply_sync_policy(&mut data);
+            let res = dao.insert_if_not_exist(data).await;
             match res {
                 Ok(_) => {
                     drop(img);
@@ -137,9 +147,16 @@ impl ClipboardListener {
         }
     }
 
-    async fn handle_record_limit() -> bool {
+    // The copies kept on this device by the sync policy are not written to the node
+    fn apply_sync_policy(record: &mut Record) {
+        if let Some(policy) = AppConfig::latest().read().sync_policy.as_ref() {
+            record.sync = policy.should_sync(record);
+        }
+    }
+
+    async fn handle_record_limit(dao: &RecordDao) -> bool {
         let limit = AppConfig::latest().read().store_limit.unwrap();
-        let res = RecordDao::delete_record_with_limit(limit as usize).await;
+        let res = dao.delete_record_with_limit(limit as usize).await;
         match res {
             Ok(res) => res,
             Err(e) => {
@@ -0,0 +1,164 @@
+use std::sync::OnceLock;
+
+use chrono::Duration;
+use log::{error, info};
+use tokio::sync::Notify;
+
+use crate::config::app_config::AppConfig;
+use crate::config::configure::DEFAULT_TRASH_RETENTION_DAYS;
+use crate::dao::record_dao::RecordDao;
+use crate::dao::record_migration_dao::RecordMigrationDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+
+pub struct MaintenanceListener;
+
+impl MaintenanceListener {
+    // Run maintenance tasks in each 1 hour
+    const WAIT_MILLIS: i64 = 60 * 60 * 1000;
+
+    pub fn listen(dao: &'static RecordDao) {
+        tauri::async_runtime::spawn(async move {
+            info!("start maintenance listener");
+
+            loop {
+                let mut need_notify = false;
+                need_notify = Self::handle_record_migration().await || need_notify;
+                need_notify = Self::handle_space_records(dao).await || need_notify;
+                need_notify = Self::handle_legacy_encryption(dao).await || need_notify;
+                need_notify = Self::handle_record_limit(dao).await || need_notify;
+                need_notify = Self::handle_retention(dao).await || need_notify;
+                need_notify = Self::handle_storage_quota(dao).await || need_notify;
+                need_notify = Self::handle_purge_trash(dao).await || need_notify;
+
+                if need_notify {
+                    if let Err(e) = GlobalHandler::push_message_to_window(
+                        MessageTypeEnum::DeleteClipboardRecordBackend,
+                        "maintenance records changed",
+                    ) {
+                        error!("send DeleteClipboardRecordBackend message err: {:?}", e)
+                    };
+                }
+
+                // Wait for the next round, or run at once when triggered
+                let wait = Duration::milliseconds(Self::WAIT_MILLIS).to_std().unwrap();
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = Self::notifier().notified() => {
+                        info!("maintenance tasks triggered");
+                    }
+                }
+            }
+        });
+    }
+
+    /// Run the maintenance tasks right away, e.g. after retention config changed
+    pub fn trigger() {
+        Self::notifier().notify_one();
+    }
+
+    fn notifier() -> &'static Notify {
+        static NOTIFY: OnceLock<Notify> = OnceLock::new();
+
+        NOTIFY.get_or_init(Notify::new)
+    }
+
+    /// Copy the records of the old schema version, the devices not upgraded yet still
+    /// create them, so it runs in each round
+    async fn handle_record_migration() -> bool {
+        match RecordMigrationDao::migrate_records().await {
+            Ok(migrated_cnt) => migrated_cnt > 0,
+            Err(e) => {
+                error!("migrate_records err: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Move the records of this device out of the schemas of the lock file and the schemas
+    /// replaced in the spaces, the moves interrupted before are finished in the next round
+    async fn handle_space_records(dao: &RecordDao) -> bool {
+        match dao.move_space_records().await {
+            Ok(moved_cnt) => moved_cnt > 0,
+            Err(e) => {
+                error!("move_space_records err: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Encrypt the records created by the versions before the group key or with a key
+    /// replaced by pairing, the records replicated late may still come, so it runs in each
+    /// round
+    async fn handle_legacy_encryption(dao: &RecordDao) -> bool {
+        match dao.encrypt_legacy_records().await {
+            Ok(encrypted_cnt) => encrypted_cnt > 0,
+            Err(e) => {
+                error!("encrypt_legacy_records err: {:?}", e);
+                false
+            }
+        }
+    }
+
+    async fn handle_record_limit(dao: &RecordDao) -> bool {
+        let limit = AppConfig::latest().read().store_limit.unwrap();
+        match dao.delete_record_with_limit(limit as usize).await {
+            Ok(res) => res,
+            Err(e) => {
+                error!("delete_record_with_limit err: {:?}", e);
+                false
+            }
+        }
+    }
+
+    async fn handle_retention(dao: &RecordDao) -> bool {
+        let rules = AppConfig::latest()
+            .read()
+            .retention_rules
+            .clone()
+            .unwrap_or_default();
+        let now = chrono::Local::now().timestamp();
+
+        let mut changed = false;
+        for rule in rules {
+            match dao
+                .expire_records(rule.data_type.clone(), rule.expire_before(now))
+                .await
+            {
+                Ok(expired_cnt) => changed = changed || expired_cnt > 0,
+                Err(e) => {
+                    error!("expire_records for {:?} err: {:?}", rule, e);
+                }
+            }
+        }
+        changed
+    }
+
+    async fn handle_storage_quota(dao: &RecordDao) -> bool {
+        let quota_bytes = AppConfig::latest().read().storage_quota_bytes();
+        match dao.delete_record_with_quota(quota_bytes).await {
+            Ok(res) => res,
+            Err(e) => {
+                error!("delete_record_with_quota err: {:?}", e);
+                false
+            }
+        }
+    }
+
+    async fn handle_purge_trash(dao: &RecordDao) -> bool {
+        let retention_days = AppConfig::latest()
+            .read()
+            .trash_retention_days
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+        let deleted_before = chrono::Local::now().timestamp()
+            - Duration::days(retention_days as i64).num_seconds();
+
+        match dao.purge_trash(deleted_before).await {
+            Ok(purged_cnt) => purged_cnt > 0,
+            Err(e) => {
+                error!("purge_trash err: {:?}", e);
+                false
+            }
+        }
+    }
+}
@@ -1,20 +1,38 @@
 use anyhow::Result;
 use tauri::App;
 
+use crate::dao::record_dao::RecordDao;
 use crate::listener::clipboard::ClipboardListener;
 use crate::listener::global_event::GlobalEventListener;
+use crate::listener::maintenance::MaintenanceListener;
+use crate::listener::pending::PendingListener;
+use crate::listener::reconcile::ReconcileListener;
 use crate::listener::shortcut::ShortcutListener;
 
 mod clipboard;
 mod global_event;
+pub mod maintenance;
+mod pending;
+mod reconcile;
 mod shortcut;
 
 pub fn register_all_listeners(app: &mut App) -> Result<()> {
     // Start global application listener
     GlobalEventListener::register_all_global_listeners(app)?;
 
+    let dao = RecordDao::global()?;
+
     // Start listening for clipboard
-    ClipboardListener::listen();
+    ClipboardListener::listen(dao);
+
+    // Start periodic maintenance tasks, e.g. purge trash
+    MaintenanceListener::listen(dao);
+
+    // Start merging the duplicate records created on several devices
+    ReconcileListener::listen(dao);
+
+    // Start replaying the record writes queued while the node was unreachable
+    PendingListener::listen(dao);
 
     // Start shortcut listener
     if cfg!(not(target_os = "linux")) {
@@ -0,0 +1,43 @@
+use chrono::Duration;
+use log::{error, info};
+
+use crate::dao::record_dao::RecordDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+
+pub struct PendingListener;
+
+impl PendingListener {
+    // Check whether the node is reachable again in each 5 seconds
+    const WAIT_MILLIS: i64 = 5 * 1000;
+
+    pub fn listen(dao: &'static RecordDao) {
+        tauri::async_runtime::spawn(async move {
+            info!("start pending operations listener");
+
+            loop {
+                if dao.has_pending() && Self::handle_pending(dao).await {
+                    if let Err(e) = GlobalHandler::push_message_to_window(
+                        MessageTypeEnum::ChangeClipboardBackend,
+                        "pending records replayed",
+                    ) {
+                        error!("send ChangeClipboardBackend message err: {:?}", e)
+                    };
+                }
+                tokio::time::sleep(Duration::milliseconds(Self::WAIT_MILLIS).to_std().unwrap())
+                    .await;
+            }
+        });
+    }
+
+    /// Replay the writes queued while the node was down or still starting
+    async fn handle_pending(dao: &RecordDao) -> bool {
+        match dao.replay_pending().await {
+            Ok(replayed_cnt) => replayed_cnt > 0,
+            Err(e) => {
+                error!("replay_pending err: {:?}", e);
+                false
+            }
+        }
+    }
+}
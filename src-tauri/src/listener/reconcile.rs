@@ -0,0 +1,66 @@
+use chrono::Duration;
+use log::{error, info};
+
+use crate::dao::record_dao::RecordDao;
+use crate::handler::global_handler::GlobalHandler;
+use crate::handler::model::MessageTypeEnum;
+use crate::models::record_list::RecordQuery;
+
+pub struct ReconcileListener;
+
+impl ReconcileListener {
+    // Check the replicated records in each 30 seconds
+    const WAIT_MILLIS: i64 = 30 * 1000;
+
+    pub fn listen(dao: &'static RecordDao) {
+        tauri::async_runtime::spawn(async move {
+            let mut last_count = None;
+            info!("start reconcile listener");
+
+            loop {
+                if Self::handle_duplicates(dao, &mut last_count).await {
+                    if let Err(e) = GlobalHandler::push_message_to_window(
+                        MessageTypeEnum::ChangeClipboardBackend,
+                        "duplicate records merged",
+                    ) {
+                        error!("send ChangeClipboardBackend message err: {:?}", e)
+                    };
+                }
+                tokio::time::sleep(Duration::milliseconds(Self::WAIT_MILLIS).to_std().unwrap())
+                    .await;
+            }
+        });
+    }
+
+    /// Merge the duplicates when the records changed, e.g. replication delivered the
+    /// records another device copied at the same time
+    async fn handle_duplicates(dao: &RecordDao, last_count: &mut Option<i64>) -> bool {
+        let filter = RecordQuery {
+            include_trash: true,
+            ..Default::default()
+        };
+        let count = match dao.count_records(&filter).await {
+            Ok(count) => count,
+            Err(e) => {
+                error!("count_records err: {:?}", e);
+                return false;
+            }
+        };
+        if last_count.replace(count) == Some(count) {
+            return false;
+        }
+
+        match dao.reconcile_duplicates().await {
+            Ok(removed_cnt) => {
+                *last_count = Some(count - removed_cnt as i64);
+                removed_cnt > 0
+            }
+            Err(e) => {
+                error!("reconcile_duplicates err: {:?}", e);
+                // Retry in the next round
+                *last_count = None;
+                false
+            }
+        }
+    }
+}
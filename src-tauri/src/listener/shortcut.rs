@@ -1,11 +1,16 @@
 use crate::config::app_config::AppConfig;
 use anyhow::{bail, Result};
+use log::{error, info};
 use tauri::{App, GlobalShortcutManager, Manager};
 
 use crate::consts::MAIN_WINDOW;
+use crate::handler::paste_queue::PasteQueueHandler;
+use crate::handler::register::RegisterHandler;
+use crate::models::register::{RegisterAction, RegisterHotkey};
 
 pub enum ShortcutKeymapEnum {
     ToggleWindow,
+    PasteNext,
 }
 
 #[derive(Debug, Default)]
@@ -14,6 +19,62 @@ pub struct ShortcutListener;
 impl ShortcutListener {
     pub fn register_all_hotkey_listeners(app: &mut App) -> Result<()> {
         Self::register_toggle_window(app)?;
+        Self::register_paste_next(app)?;
+        Self::register_registers(app);
+
+        Ok(())
+    }
+
+    /// A broken register binding should not stop the other shortcuts, so errors are logged
+    fn register_registers(app: &mut App) {
+        let app_handle = app.handle();
+        let mut manager = app_handle.global_shortcut_manager();
+
+        let bindings = AppConfig::latest()
+            .read()
+            .register_hotkeys
+            .clone()
+            .unwrap_or_default();
+        for binding in bindings {
+            let RegisterHotkey {
+                register,
+                action,
+                hotkey,
+            } = binding;
+            let res = manager.register(&hotkey, move || {
+                let register = register.clone();
+                tauri::async_runtime::spawn(async move {
+                    let res = match action {
+                        RegisterAction::Copy => RegisterHandler::copy_from_clipboard(&register)
+                            .await
+                            .map(|_| ()),
+                        RegisterAction::Paste => {
+                            RegisterHandler::paste(&register, None).await.map(|_| ())
+                        }
+                    };
+                    if let Err(e) = res {
+                        error!("{:?} register {} err: {:?}", action, register, e);
+                    }
+                });
+            });
+            if let Err(e) = res {
+                error!("register hotkey {} err: {:?}", hotkey, e);
+            }
+        }
+    }
+
+    fn register_paste_next(app: &mut App) -> Result<()> {
+        let app_handle = app.handle();
+        let mut manager = app_handle.global_shortcut_manager();
+
+        manager.register(
+            &Self::get_shortcut_hotkey_config(ShortcutKeymapEnum::PasteNext)?,
+            move || match PasteQueueHandler::paste_next() {
+                Ok(Some(_)) => info!("paste next item of the paste queue"),
+                Ok(None) => info!("paste queue is empty"),
+                Err(e) => error!("paste next item err: {:?}", e),
+            },
+        )?;
 
         Ok(())
     }
@@ -47,6 +108,14 @@ impl ShortcutListener {
                     }
                 }
             }
+            ShortcutKeymapEnum::PasteNext => {
+                match AppConfig::latest().read().paste_next_hotkey.clone() {
+                    Some(hotkey) => Ok(hotkey),
+                    None => {
+                        bail!("paste_next_hotkey not configured!");
+                    }
+                }
+            }
         }
     }
 }
@@ -1,10 +1,10 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use anyhow::Result;
 use app::p2panda::node::NodeServer;
 use app::tray::register_tray;
 use app::{handler, listener, logger};
-use aquadoggo::Node;
 use dotenv::dotenv;
 use log::{error, info};
 use tauri::api::notification::Notification;
@@ -21,8 +21,48 @@ fn main() {
             app::command::config::load_app_config,
             app::command::config::save_app_config,
             app::command::record::tap_change_clipboard,
+            app::command::record::list_records,
+            app::command::record::get_record_content,
             app::command::record::delete_record,
+            app::command::record::restore_record,
+            app::command::record::empty_trash,
             app::command::record::toggle_favorite_record,
+            app::command::record::edit_record,
+            app::command::record::record_history,
+            app::command::record::revert_record,
+            app::command::record::batch_delete,
+            app::command::record::batch_set_favorite,
+            app::command::record::set_record_sync,
+            app::command::record::batch_tag,
+            app::command::record::merge_records,
+            app::command::storage::storage_usage,
+            app::command::paste_queue::start_paste_queue,
+            app::command::paste_queue::stop_paste_queue,
+            app::command::paste_queue::paste_queue_state,
+            app::command::paste_queue::paste_next,
+            app::command::snippet::list_snippets,
+            app::command::snippet::create_snippet,
+            app::command::snippet::update_snippet,
+            app::command::snippet::delete_snippet,
+            app::command::snippet::render_snippet,
+            app::command::register::list_registers,
+            app::command::register::register_history,
+            app::command::register::copy_to_register,
+            app::command::register::paste_from_register,
+            app::command::register::clear_register,
+            app::command::pairing::start_pairing,
+            app::command::pairing::join_pairing,
+            app::command::pairing::confirm_pairing,
+            app::command::pairing::cancel_pairing,
+            app::command::pairing::list_paired_devices,
+            app::command::pairing::rename_paired_device,
+            app::command::pairing::revoke_paired_device,
+            app::command::space::list_spaces,
+            app::command::space::create_space,
+            app::command::space::rename_space,
+            app::command::space::delete_space,
+            app::command::space::switch_space,
+            app::command::space::remove_space_member,
         ])
         .plugin(tauri_plugin_single_instance::init(|app, _, cwd| {
             Notification::new(&app.config().tauri.bundle.identifier)
@@ -48,7 +88,7 @@ fn main() {
                     );
                 }
             }
-            setup_service(app);
+            setup_service(app)?;
             Ok(())
         });
 
@@ -75,7 +115,7 @@ fn main() {
     });
 }
 
-fn setup_service(app: &mut App) {
+fn setup_service(app: &mut App) -> Result<()> {
     // Make the docker NOT to have an active app when started
     #[cfg(target_os = "macos")]
     {
@@ -86,17 +126,10 @@ fn setup_service(app: &mut App) {
     handler::global_handler::GlobalHandler::global().init(app.app_handle());
 
     // Start sync server
-    let mut node: Option<Node> = None;
-    block_on(async {
-        node = Some(NodeServer::start().await.unwrap());
-    });
-    if let Some(node) = node {
-        tauri::async_runtime::spawn(async move {
-            node.on_exit().await;
-        });
-    } else {
-        error!("Start node server failed!")
+    if let Err(err) = block_on(NodeServer::start()) {
+        error!("Start node server failed: {:?}", err);
+        return Err(err);
     }
 
-    listener::register_all_listeners(app).unwrap();
+    listener::register_all_listeners(app)
 }
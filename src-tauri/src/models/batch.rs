@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Result of one record in a batch operation
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct BatchItemResult {
+    pub view_id: String,
+    pub success: bool,
+    // view id after the operation, the frontend should use it for the next operation
+    pub new_view_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn from_result(view_id: String, res: anyhow::Result<String>) -> Self {
+        match res {
+            Ok(new_view_id) => BatchItemResult {
+                view_id,
+                success: true,
+                new_view_id: Some(new_view_id),
+                error: None,
+            },
+            Err(err) => BatchItemResult {
+                view_id,
+                success: false,
+                new_view_id: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+
+    pub fn success_count(results: &[BatchItemResult]) -> usize {
+        results.iter().filter(|r| r.success).count()
+    }
+}
+
+/// Result of merging records. When the merge is queued the sources are kept, even if they
+/// were asked to be deleted, as the merged record may turn out to be one of them
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MergeResult {
+    // document id of the merged record, empty when the merge is queued
+    pub document_id: String,
+    // moves of the sources to trash
+    pub trashed_sources: Vec<BatchItemResult>,
+    // the sources were asked to be deleted but are kept as the merge is queued
+    pub sources_kept: bool,
+}
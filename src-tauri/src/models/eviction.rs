@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::batch::BatchItemResult;
+
+/// Progress of moving the records over the store limit into trash, it is pushed to the
+/// frontend after each batch
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EvictionProgress {
+    // records needed to remove when the eviction started
+    pub total: usize,
+    pub trashed: usize,
+    // records whose move was queued as the store was unavailable, they are not in trash yet
+    #[serde(default)]
+    pub queued: usize,
+    // records still failing after the retries, the next eviction selects them again
+    pub failed: Vec<BatchItemResult>,
+    pub finished: bool,
+}
+
+impl EvictionProgress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            ..Default::default()
+        }
+    }
+
+    /// Records not handled yet, the failed ones are not selected again in this eviction
+    pub fn remaining(&self) -> usize {
+        self.total
+            .saturating_sub(self.trashed + self.queued + self.failed.len())
+    }
+
+    /// A queued write succeeds with an empty view id
+    pub fn add_result(&mut self, result: BatchItemResult) {
+        if !result.success {
+            self.failed.push(result);
+        } else if result.new_view_id.as_deref() == Some("") {
+            self.queued += 1;
+        } else {
+            self.trashed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::batch::BatchItemResult;
+    use crate::models::eviction::EvictionProgress;
+
+    #[test]
+    fn test_remaining() {
+        let mut progress = EvictionProgress::new(3);
+        assert_eq!(progress.remaining(), 3);
+
+        progress.add_result(BatchItemResult::from_result(
+            "a".to_string(),
+            Ok("b".to_string()),
+        ));
+        progress.add_result(BatchItemResult::from_result(
+            "c".to_string(),
+            Err(anyhow::anyhow!("node is busy")),
+        ));
+        assert_eq!((progress.trashed, progress.failed.len()), (1, 1));
+        assert_eq!(progress.remaining(), 1);
+
+        progress.add_result(BatchItemResult::from_result(
+            "d".to_string(),
+            Ok("e".to_string()),
+        ));
+        assert_eq!(progress.remaining(), 0);
+        assert!(!progress.finished);
+
+        let mut progress = EvictionProgress::new(2);
+        progress.add_result(BatchItemResult::from_result(
+            "f".to_string(),
+            Ok(String::new()),
+        ));
+        assert_eq!((progress.trashed, progress.queued), (0, 1));
+        assert_eq!(progress.remaining(), 1);
+    }
+}
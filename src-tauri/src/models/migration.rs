@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Progress of copying the documents of an old schema version into the latest one. It is
+/// saved after each batch, so an interrupted migration resumes where it stopped
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MigrationProgress {
+    pub from_version: u32,
+    pub to_version: u32,
+    // old document id -> document id in the latest schema
+    pub migrated: BTreeMap<String, String>,
+    // old document id -> view id of the old document when it was copied
+    pub copied_views: BTreeMap<String, String>,
+    pub update_time: i64,
+}
+
+impl MigrationProgress {
+    /// Progress of the migration between the versions, the saved progress is dropped when
+    /// it belongs to other versions.
+    ///
+    /// The progress towards an older target version is kept: the documents copied into it
+    /// are migrated further together with the other documents of that version
+    pub fn resume(saved: Option<MigrationProgress>, from_version: u32, to_version: u32) -> Self {
+        match saved {
+            Some(mut saved)
+                if saved.from_version == from_version && saved.to_version <= to_version =>
+            {
+                saved.to_version = to_version;
+                saved
+            }
+            _ => Self {
+                from_version,
+                to_version,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn is_migrated(&self, document_id: &str) -> bool {
+        self.migrated.contains_key(document_id)
+    }
+
+    /// Document id in the latest schema of the copied document
+    pub fn migrated_id(&self, document_id: &str) -> Option<&String> {
+        self.migrated.get(document_id)
+    }
+
+    /// The old document is not copied yet, or changed since it was copied, e.g. edited on a
+    /// device still on the old version
+    pub fn needs_copy(&self, document_id: &str, view_id: &str) -> bool {
+        match self.copied_views.get(document_id) {
+            Some(copied) => copied.ne(view_id),
+            None => !self.is_migrated(document_id),
+        }
+    }
+
+    /// Keep the view of a document copied by the versions which did not keep the views, so
+    /// its later changes are copied. Return true when the view is kept
+    pub fn adopt_view(&mut self, document_id: &str, view_id: &str) -> bool {
+        if !self.is_migrated(document_id) || self.copied_views.contains_key(document_id) {
+            return false;
+        }
+        self.copied_views
+            .insert(document_id.to_string(), view_id.to_string());
+        true
+    }
+
+    pub fn mark_migrated(&mut self, document_id: String, view_id: String, new_document_id: String) {
+        self.copied_views.insert(document_id.clone(), view_id);
+        self.migrated.insert(document_id, new_document_id);
+        self.update_time = chrono::Local::now().timestamp();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::migration::MigrationProgress;
+
+    #[test]
+    fn test_resume() {
+        let mut progress = MigrationProgress::resume(None, 1, 2);
+        assert!(!progress.is_migrated("a"));
+        progress.mark_migrated("a".to_string(), "va".to_string(), "b".to_string());
+        assert!(progress.is_migrated("a"));
+
+        let resumed = MigrationProgress::resume(Some(progress.clone()), 1, 2);
+        assert_eq!(resumed, progress);
+
+        // v1 documents copied into v2 are migrated to v3 with the v2 documents
+        let upgraded = MigrationProgress::resume(Some(progress.clone()), 1, 3);
+        assert!(upgraded.is_migrated("a"));
+        assert_eq!(upgraded.to_version, 3);
+
+        let downgraded = MigrationProgress::resume(Some(upgraded), 1, 2);
+        assert!(!downgraded.is_migrated("a"));
+
+        let restarted = MigrationProgress::resume(Some(progress), 2, 3);
+        assert!(!restarted.is_migrated("a"));
+        assert_eq!((restarted.from_version, restarted.to_version), (2, 3));
+    }
+
+    #[test]
+    fn test_needs_copy() {
+        let mut progress = MigrationProgress::resume(None, 1, 3);
+        assert!(progress.needs_copy("a", "va"));
+
+        progress.mark_migrated("a".to_string(), "va".to_string(), "b".to_string());
+        assert!(!progress.needs_copy("a", "va"));
+        assert_eq!(progress.migrated_id("a").unwrap(), "b");
+        // Edited on a device still on the old version
+        assert!(progress.needs_copy("a", "va2"));
+
+        // Copied by a version which did not keep the view
+        progress.migrated.insert("c".to_string(), "d".to_string());
+        assert!(!progress.needs_copy("c", "vc"));
+        assert!(progress.adopt_view("c", "vc"));
+        assert!(!progress.adopt_view("c", "vc"));
+        assert!(progress.needs_copy("c", "vc2"));
+    }
+}
@@ -1,2 +1,13 @@
+pub mod batch;
+pub mod eviction;
 pub mod image_data;
+pub mod migration;
+pub mod pairing;
+pub mod paste_queue;
+pub mod pending_operation;
 pub mod record;
+pub mod record_list;
+pub mod register;
+pub mod snippet;
+pub mod space;
+pub mod storage;
@@ -0,0 +1,186 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::models::space::{SpaceSchemas, DEFAULT_SPACE_ID};
+use crate::utils::ip::check_addr;
+
+// Prefix of the invite shown as text or QR code
+const INVITE_PREFIX: &str = "cbs-pair:v1";
+
+const SESSION_KEY_CONTEXT: &str = "clipboard-syncer pairing v1 session key";
+
+/// Invite shown as a QR code on the device starting the pairing. The commitment binds the
+/// ephemeral key the device will use, so a device in the middle can not replace it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PairingInvite {
+    // ip:port of the pairing listener
+    pub address: String,
+    pub device_id: String,
+    pub commitment: String,
+}
+
+/// Messages exchanged on the pairing connection, one json per line
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PairingMessage {
+    Hello {
+        device_id: String,
+        device_name: String,
+        // hex of the ephemeral X25519 public key
+        ephemeral_key: String,
+    },
+    Confirm {
+        accepted: bool,
+    },
+    // the key of the active space encrypted with the session key, sent by the inviting
+    // device with the schemas of the space
+    GroupKey {
+        space_id: String,
+        space_name: String,
+        sealed: String,
+        #[serde(default)]
+        schemas: Option<SpaceSchemas>,
+    },
+}
+
+/// Pairing waiting for the user to compare the code shown on both devices
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PairingRequest {
+    pub device_id: String,
+    pub device_name: String,
+    // 6 位数字，两台设备上显示的应一致
+    pub code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PairingResult {
+    pub device_id: String,
+    // the space shared with the device
+    pub space_id: String,
+    pub paired: bool,
+    pub message: String,
+}
+
+/// Device allowed to replicate with this one
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PairedDevice {
+    // public key of the device
+    pub device_id: String,
+    pub name: String,
+    pub paired_at: i64,
+    // ids of the spaces shared with the device
+    #[serde(default = "default_spaces")]
+    pub spaces: Vec<String>,
+}
+
+fn default_spaces() -> Vec<String> {
+    vec![DEFAULT_SPACE_ID.to_string()]
+}
+
+impl PairingInvite {
+    pub fn new(address: String, device_id: String, ephemeral_key: &[u8; 32]) -> Self {
+        let commitment = Self::commit(&device_id, ephemeral_key);
+        Self {
+            address,
+            device_id,
+            commitment,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            INVITE_PREFIX, self.address, self.device_id, self.commitment
+        )
+    }
+
+    pub fn decode(invite: &str) -> Result<Self> {
+        let rest = match invite.trim().strip_prefix(INVITE_PREFIX) {
+            Some(rest) => rest,
+            None => bail!("invalid pairing invite"),
+        };
+        // the address contains a colon itself
+        let parts: Vec<&str> = rest.split(':').collect();
+        match parts.as_slice() {
+            ["", ip, port, device_id, commitment] => {
+                let address = format!("{}:{}", ip, port);
+                if !check_addr(&address) {
+                    bail!("invalid address in pairing invite: {}", address);
+                }
+                Ok(Self {
+                    address,
+                    device_id: device_id.to_string(),
+                    commitment: commitment.to_string(),
+                })
+            }
+            _ => bail!("invalid pairing invite"),
+        }
+    }
+
+    /// Whether the ephemeral key received on the connection is the one of the invite
+    pub fn verify(&self, device_id: &str, ephemeral_key: &[u8; 32]) -> bool {
+        self.device_id.eq(device_id) && self.commitment.eq(&Self::commit(device_id, ephemeral_key))
+    }
+
+    fn commit(device_id: &str, ephemeral_key: &[u8; 32]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(device_id.as_bytes());
+        hasher.update(ephemeral_key);
+        hex::encode(&hasher.finalize().as_bytes()[..16])
+    }
+}
+
+/// Key of the pairing connection, derived from the shared secret and both hellos. The
+/// inviting device is always passed first so both devices get the same key
+pub fn session_key(
+    shared_secret: &[u8; 32],
+    inviter: (&str, &[u8; 32]),
+    joiner: (&str, &[u8; 32]),
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key(SESSION_KEY_CONTEXT);
+    hasher.update(shared_secret);
+    for (device_id, ephemeral_key) in [inviter, joiner] {
+        hasher.update(device_id.as_bytes());
+        hasher.update(ephemeral_key);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// Short code compared by the user, a device in the middle ends up with different keys
+/// on both sides, so the codes do not match
+pub fn pairing_code(session_key: &[u8; 32]) -> String {
+    let hash = blake3::hash(session_key);
+    let n = u32::from_be_bytes(hash.as_bytes()[..4].try_into().unwrap());
+    format!("{:06}", n % 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::pairing::{pairing_code, session_key, PairingInvite};
+
+    #[test]
+    fn test_invite() {
+        let key = [7u8; 32];
+        let invite = PairingInvite::new("192.168.1.2:12024".to_string(), "pk".to_string(), &key);
+        let decoded = PairingInvite::decode(&invite.encode()).unwrap();
+        assert_eq!(decoded, invite);
+        assert!(decoded.verify("pk", &key));
+        assert!(!decoded.verify("pk", &[8u8; 32]));
+        assert!(!decoded.verify("other", &key));
+
+        assert!(PairingInvite::decode("cbs-pair:v1:localhost:pk:abc").is_err());
+        assert!(PairingInvite::decode("hello").is_err());
+    }
+
+    #[test]
+    fn test_pairing_code() {
+        let (a, b) = ([1u8; 32], [2u8; 32]);
+        let key = session_key(&[3u8; 32], ("a", &a), ("b", &b));
+        assert_eq!(key, session_key(&[3u8; 32], ("a", &a), ("b", &b)));
+        assert_ne!(key, session_key(&[4u8; 32], ("a", &a), ("b", &b)));
+
+        let code = pairing_code(&key);
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+    }
+}
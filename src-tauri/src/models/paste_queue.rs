@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteQueueMode {
+    // paste in the order of copying
+    #[default]
+    Fifo,
+    // paste the latest copied first
+    Lifo,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct QueueItem {
+    pub content: String,
+    pub content_preview: Option<String>,
+    pub data_type: String,
+    pub md5: String,
+}
+
+/// Queue state shown in the frontend, items are listed in the order they will be pasted
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PasteQueueState {
+    pub active: bool,
+    pub mode: PasteQueueMode,
+    pub items: Vec<QueueItemPreview>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct QueueItemPreview {
+    pub content_preview: Option<String>,
+    pub data_type: String,
+}
+
+#[derive(Debug, Default)]
+pub struct PasteQueue {
+    active: bool,
+    mode: PasteQueueMode,
+    items: VecDeque<QueueItem>,
+    // the content put on clipboard by the queue itself should not be captured again, it is
+    // only skipped when captured before the deadline
+    skip_md5: Option<(String, Instant)>,
+}
+
+impl PasteQueue {
+    // The clipboard listener polls every second, a capture later than this is a new copy
+    const SKIP_TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub fn start(&mut self, mode: PasteQueueMode) {
+        self.active = true;
+        self.mode = mode;
+        self.items.clear();
+        self.skip_md5 = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.items.clear();
+        self.skip_md5 = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Push the captured item, returns false if the item is ignored
+    pub fn push(&mut self, item: QueueItem) -> bool {
+        self.push_at(item, Instant::now())
+    }
+
+    fn push_at(&mut self, item: QueueItem, now: Instant) -> bool {
+        if !self.active {
+            return false;
+        }
+        if let Some((md5, deadline)) = self.skip_md5.take() {
+            if md5.eq(&item.md5) && now < deadline {
+                return false;
+            }
+        }
+        self.items.push_back(item);
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<QueueItem> {
+        self.pop_at(Instant::now())
+    }
+
+    fn pop_at(&mut self, now: Instant) -> Option<QueueItem> {
+        let item = match self.mode {
+            PasteQueueMode::Fifo => self.items.pop_front(),
+            PasteQueueMode::Lifo => self.items.pop_back(),
+        };
+        self.skip_md5 = item
+            .as_ref()
+            .map(|i| (i.md5.clone(), now + Self::SKIP_TIMEOUT));
+        item
+    }
+
+    /// The clipboard already held the popped item, the listener does not capture it, so the
+    /// next copy of the same content is a new one
+    pub fn clear_skip(&mut self) {
+        self.skip_md5 = None;
+    }
+
+    pub fn state(&self) -> PasteQueueState {
+        let preview = |item: &QueueItem| QueueItemPreview {
+            content_preview: item.content_preview.clone(),
+            data_type: item.data_type.clone(),
+        };
+        let items = match self.mode {
+            PasteQueueMode::Fifo => self.items.iter().map(preview).collect(),
+            PasteQueueMode::Lifo => self.items.iter().rev().map(preview).collect(),
+        };
+
+        PasteQueueState {
+            active: self.active,
+            mode: self.mode,
+            items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::models::paste_queue::{PasteQueue, PasteQueueMode, QueueItem};
+
+    fn item(md5: &str) -> QueueItem {
+        QueueItem {
+            content: md5.to_string(),
+            md5: md5.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fifo_and_lifo() {
+        let mut queue = PasteQueue::default();
+        assert!(!queue.push(item("a")));
+
+        queue.start(PasteQueueMode::Fifo);
+        queue.push(item("a"));
+        queue.push(item("b"));
+        assert_eq!(queue.pop().unwrap().md5, "a");
+
+        queue.start(PasteQueueMode::Lifo);
+        queue.push(item("a"));
+        queue.push(item("b"));
+        assert_eq!(queue.state().items.len(), 2);
+        assert_eq!(queue.pop().unwrap().md5, "b");
+    }
+
+    #[test]
+    fn test_skip_popped_item() {
+        let mut queue = PasteQueue::default();
+        queue.start(PasteQueueMode::Fifo);
+        queue.push(item("a"));
+
+        let popped = queue.pop().unwrap();
+        // The popped item is put on clipboard and captured by the listener again
+        assert!(!queue.push(popped));
+        assert!(queue.push(item("a")));
+    }
+
+    #[test]
+    fn test_skip_expires() {
+        let mut queue = PasteQueue::default();
+        queue.start(PasteQueueMode::Fifo);
+        queue.push(item("a"));
+        queue.push(item("a"));
+
+        // The clipboard already held the item, so it is not captured again
+        let now = Instant::now();
+        queue.pop_at(now);
+        queue.clear_skip();
+        assert!(queue.push_at(item("a"), now));
+
+        // A copy long after the pop is a new one
+        queue.pop_at(now);
+        assert!(queue.push_at(item("a"), now + PasteQueue::SKIP_TIMEOUT));
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{bail, Result};
+use p2panda_rs::operation::OperationValue;
+use serde::{Deserialize, Serialize};
+
+use crate::models::record::Record;
+use crate::utils::crypto::{self, GroupKey};
+use crate::utils::string;
+
+/// Field value of a queued update, only the value types used by the record schema
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PendingValue {
+    Integer(i64),
+    String(String),
+}
+
+/// Write to the records which could not reach the node, it is replayed once the node is
+/// reachable again
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PendingOperation {
+    // A copied clipboard content, it is replayed like a new copy so the content is merged
+    // into the existing record instead of creating another one. The content is sealed with
+    // the key of `space_id`, it is empty for the copies queued in cleartext by older versions
+    Insert {
+        record: Box<Record>,
+        #[serde(default)]
+        space_id: String,
+    },
+    Update {
+        view_id: String,
+        fields: Vec<(String, PendingValue)>,
+    },
+    Delete {
+        view_id: String,
+    },
+}
+
+impl PendingOperation {
+    pub fn update(view_id: &str, fields: &[(&str, OperationValue)]) -> Result<Self> {
+        let mut pending_fields = vec![];
+        for (name, value) in fields {
+            let value = match value {
+                OperationValue::Integer(v) => PendingValue::Integer(*v),
+                OperationValue::String(v) => PendingValue::String(v.clone()),
+                _ => bail!("can not queue record field {}: {:?}", name, value),
+            };
+            pending_fields.push((name.to_string(), value));
+        }
+        Ok(PendingOperation::Update {
+            view_id: view_id.to_string(),
+            fields: pending_fields,
+        })
+    }
+
+    /// Encrypt the content of a queued copy with the key of the space it was copied in, the
+    /// queue is written to the disk. md5 is left out as it reveals the content
+    pub fn seal(self, space_id: &str, key: &GroupKey) -> Result<Self> {
+        match self {
+            PendingOperation::Insert { mut record, .. } => {
+                record.content = crypto::encrypt(key, &record.content)?;
+                record.content_preview = match record.content_preview {
+                    Some(preview) => Some(crypto::encrypt(key, &preview)?),
+                    None => None,
+                };
+                record.md5 = String::new();
+                Ok(PendingOperation::Insert {
+                    record,
+                    space_id: space_id.to_string(),
+                })
+            }
+            operation => Ok(operation),
+        }
+    }
+
+    /// Decrypt the copy sealed by `seal` with the keys of its space
+    pub fn open(self, keys: &[GroupKey]) -> Result<Self> {
+        match self {
+            PendingOperation::Insert { mut record, .. } => {
+                record.content = crypto::decrypt_with_any(keys, &record.content)?;
+                record.content_preview = match record.content_preview {
+                    Some(preview) => Some(crypto::decrypt_with_any(keys, &preview)?),
+                    None => None,
+                };
+                record.md5 = string::md5(&record.content);
+                Ok(PendingOperation::Insert {
+                    record,
+                    space_id: String::new(),
+                })
+            }
+            operation => Ok(operation),
+        }
+    }
+
+    /// Fields of a queued update as operation values
+    pub fn operation_fields(fields: &[(String, PendingValue)]) -> Vec<(&str, OperationValue)> {
+        fields
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    PendingValue::Integer(v) => OperationValue::Integer(*v),
+                    PendingValue::String(v) => OperationValue::String(v.clone()),
+                };
+                (name.as_str(), value)
+            })
+            .collect()
+    }
+}
+
+/// Queued operation, `seq` keeps the order of the writes
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PendingEntry {
+    pub seq: u64,
+    pub queued_at: i64,
+    pub operation: PendingOperation,
+    // failed replays of the operation, the store was reachable but the write failed
+    #[serde(default)]
+    pub attempts: u32,
+    // the operation is not replayed before this time
+    #[serde(default)]
+    pub retry_at: i64,
+}
+
+impl PendingEntry {
+    // The first retry is after 30 seconds, the delay doubles up to an hour
+    const RETRY_SECS: i64 = 30;
+    const MAX_RETRY_SECS: i64 = 60 * 60;
+
+    pub fn is_due(&self, now: i64) -> bool {
+        self.retry_at <= now
+    }
+
+    /// Count the failed replay and delay the next one
+    pub fn retry_later(&mut self, now: i64) {
+        self.attempts += 1;
+        let delay = Self::RETRY_SECS << (self.attempts - 1).min(16);
+        self.retry_at = now + delay.min(Self::MAX_RETRY_SECS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::operation::OperationValue;
+
+    use crate::models::pending_operation::{PendingEntry, PendingOperation, PendingValue};
+    use crate::models::record::Record;
+    use crate::utils::{crypto, json};
+
+    #[test]
+    fn test_update_fields() {
+        let fields = [
+            ("is_deleted", OperationValue::Integer(5)),
+            (
+                "latest_addr",
+                OperationValue::String("127.0.0.1".to_string()),
+            ),
+        ];
+        let op = PendingOperation::update("view", &fields).unwrap();
+        let parsed: PendingOperation = json::parse(&json::stringify(&op).unwrap()).unwrap();
+        assert_eq!(parsed, op);
+
+        match parsed {
+            PendingOperation::Update { view_id, fields: f } => {
+                assert_eq!(view_id, "view");
+                assert_eq!(f[0], ("is_deleted".to_string(), PendingValue::Integer(5)));
+                assert_eq!(PendingOperation::operation_fields(&f), fields.to_vec());
+            }
+            _ => panic!("not an update: {:?}", parsed),
+        }
+
+        let invalid = [("is_deleted", OperationValue::Boolean(true))];
+        assert!(PendingOperation::update("view", &invalid).is_err());
+    }
+
+    #[test]
+    fn test_seal() {
+        let key = crypto::generate_key();
+        let op = PendingOperation::Insert {
+            record: Box::new(Record {
+                content: "secret".to_string(),
+                content_preview: Some("secret".to_string()),
+                md5: "5ebe2294ecd0e0f08eab7690d2a6ee69".to_string(),
+                ..Default::default()
+            }),
+            space_id: String::new(),
+        };
+
+        let sealed = op.clone().seal("space", &key).unwrap();
+        let line = json::stringify(&sealed).unwrap();
+        assert!(!line.contains("secret"));
+        assert!(!line.contains("5ebe2294ecd0e0f08eab7690d2a6ee69"));
+        match &sealed {
+            PendingOperation::Insert { space_id, .. } => assert_eq!(space_id, "space"),
+            _ => panic!("not an insert: {:?}", sealed),
+        }
+
+        let opened: PendingOperation = json::parse(&line).unwrap();
+        assert_eq!(opened.open(&[key]).unwrap(), op);
+        assert!(sealed.open(&[crypto::generate_key()]).is_err());
+    }
+
+    #[test]
+    fn test_retry_later() {
+        let mut entry: PendingEntry =
+            json::parse(r#"{"seq":1,"queued_at":0,"operation":{"action":"delete","view_id":"v"}}"#)
+                .unwrap();
+        assert_eq!(entry.attempts, 0);
+        assert!(entry.is_due(0));
+
+        entry.retry_later(100);
+        assert_eq!((entry.attempts, entry.retry_at), (1, 130));
+        assert!(!entry.is_due(129));
+        assert!(entry.is_due(130));
+        entry.retry_later(200);
+        assert_eq!(entry.retry_at, 260);
+        for _ in 0..40 {
+            entry.retry_later(1000);
+        }
+        assert_eq!(entry.retry_at, 1000 + 60 * 60);
+    }
+}
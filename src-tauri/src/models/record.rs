@@ -1,7 +1,11 @@
-pub const SCHEMA_ID: &str =
-    "record_002017915c937c1c44d1d6a7bc6697b2760396843676cc418a02b481fb08009e099f";
+use crate::utils::string;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+// Logical name of the schema in schema/schema.lock, see `p2panda::schema_registry`
+pub const SCHEMA_NAME: &str = "record";
+// Schema of the previous versions of the edited records
+pub const VERSION_SCHEMA_NAME: &str = "record_version";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 pub struct Record {
     pub content: String,
     // 文字为空，图片为缩略图
@@ -13,7 +17,146 @@ pub struct Record {
     pub is_favorite: i64,
     pub tags: String,
     pub latest_addr: String,
+    // 0 表示未删除，否则为移入回收站的时间戳
     pub is_deleted: i64,
+    // 复制该记录的设备，默认为 node 的公钥
+    pub device_id: String,
+    // 复制内容的来源应用，未知时为空
+    pub source_app: String,
+    pub mime_type: String,
+    // content 的字节数
+    pub byte_size: i64,
+    // data_type 下的细分类型，未知时为空
+    pub subtype: String,
+    // 过期时间戳，0 表示不过期
+    pub expires_at: i64,
+    // content 的 BLAKE3 哈希，用于去重，md5 仅为兼容旧版本保留
+    pub content_hash: String,
+    // 是否同步到配对设备，不同步的记录只保存在本机，不会写入 node
+    #[serde(default = "default_sync")]
+    pub sync: bool,
+}
+
+fn default_sync() -> bool {
+    true
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            content: String::new(),
+            content_preview: None,
+            data_type: String::new(),
+            md5: String::new(),
+            create_time: 0,
+            is_favorite: 0,
+            tags: String::new(),
+            latest_addr: String::new(),
+            is_deleted: 0,
+            device_id: String::new(),
+            source_app: String::new(),
+            mime_type: String::new(),
+            byte_size: 0,
+            subtype: String::new(),
+            expires_at: 0,
+            content_hash: String::new(),
+            sync: default_sync(),
+        }
+    }
+}
+
+impl Record {
+    /// Fill the fields added in the schema v2 and v3 which are not set by the caller
+    pub fn fill_defaults(&mut self, device_id: &str) {
+        if self.device_id.is_empty() {
+            self.device_id = device_id.to_string();
+        }
+        if self.mime_type.is_empty() {
+            self.mime_type = mime_type(&self.data_type).to_string();
+        }
+        if self.byte_size == 0 {
+            self.byte_size = self.content.len() as i64;
+        }
+        if self.content_hash.is_empty() {
+            self.content_hash = string::content_hash(&self.content);
+        }
+    }
+}
+
+/// A previous version of an edited record
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RecordVersion {
+    pub view_id: String,
+    pub content: String,
+    pub content_preview: Option<String>,
+    // empty for the versions kept on this device before the versions were synced
+    #[serde(default)]
+    pub content_hash: String,
+    // 该版本被编辑替换的时间
+    pub edit_time: i64,
+}
+
+// Tags are stored as a comma separated string
+const TAG_SEPARATOR: char = ',';
+
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(TAG_SEPARATOR)
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Add and remove tags, the order of the existing tags is kept
+pub fn merge_tags(tags: &str, add_tags: &[String], remove_tags: &[String]) -> String {
+    let mut merged = parse_tags(tags);
+    for tag in add_tags {
+        let tag = tag.trim().replace(TAG_SEPARATOR, "");
+        if !tag.is_empty() && !merged.contains(&tag) {
+            merged.push(tag);
+        }
+    }
+    merged.retain(|tag| !remove_tags.contains(tag));
+    merged.join(&TAG_SEPARATOR.to_string())
+}
+
+// Separator used when merging text records without template
+pub const DEFAULT_MERGE_SEPARATOR: &str = "\n";
+
+/// Merge text contents in order, `{index}` (starts from 1) and `{content}` in the template
+/// are replaced for each content
+pub fn merge_text_contents(
+    contents: &[String],
+    separator: Option<&str>,
+    template: Option<&str>,
+) -> String {
+    contents
+        .iter()
+        .enumerate()
+        .map(|(idx, content)| match template {
+            Some(template) => template
+                .replace("{index}", &(idx + 1).to_string())
+                .replace("{content}", content),
+            None => content.clone(),
+        })
+        .collect::<Vec<String>>()
+        .join(separator.unwrap_or(DEFAULT_MERGE_SEPARATOR))
+}
+
+// Max chars of the text preview
+pub const TEXT_PREVIEW_LEN: usize = 48;
+
+/// Preview shown in the list for text content
+pub fn text_preview(content: &str) -> String {
+    if content.len() > TEXT_PREVIEW_LEN {
+        content
+            .trim()
+            .chars()
+            .take(TEXT_PREVIEW_LEN)
+            .collect::<String>()
+            + "..."
+    } else {
+        content.to_string()
+    }
 }
 
 pub enum DataTypeEnum {
@@ -30,8 +173,80 @@ impl From<DataTypeEnum> for String {
     }
 }
 
+/// MIME type of the content, images are stored as `ImageData` json of rgba8 pixels
+pub fn mime_type(data_type: &str) -> &'static str {
+    match data_type {
+        "text" => "text/plain;charset=utf-8",
+        "image" => "application/x-rgba8+json",
+        _ => "application/octet-stream",
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::models::record::{
+        merge_tags, merge_text_contents, parse_tags, text_preview, DataTypeEnum, Record,
+        TEXT_PREVIEW_LEN,
+    };
+    use crate::utils::string;
+
     #[test]
     fn test_build() {}
+
+    #[test]
+    fn test_fill_defaults() {
+        let mut record = Record {
+            content: "hello".to_string(),
+            data_type: DataTypeEnum::TEXT.into(),
+            ..Default::default()
+        };
+        record.fill_defaults("device");
+        assert_eq!(record.device_id, "device");
+        assert_eq!(record.mime_type, "text/plain;charset=utf-8");
+        assert_eq!(record.byte_size, 5);
+        assert_eq!(record.expires_at, 0);
+        assert_eq!(record.content_hash, string::content_hash("hello"));
+
+        let mut record = Record {
+            device_id: "other".to_string(),
+            byte_size: 1,
+            ..Default::default()
+        };
+        record.fill_defaults("device");
+        assert_eq!(record.device_id, "other");
+        assert_eq!(record.mime_type, "application/octet-stream");
+        assert_eq!(record.byte_size, 1);
+    }
+
+    #[test]
+    fn test_text_preview() {
+        assert_eq!(text_preview("hello"), "hello");
+
+        let long = "a".repeat(TEXT_PREVIEW_LEN + 1);
+        assert_eq!(text_preview(&long), "a".repeat(TEXT_PREVIEW_LEN) + "...");
+    }
+
+    #[test]
+    fn test_merge_text_contents() {
+        let contents = vec!["error".to_string(), "https://a.b".to_string()];
+
+        assert_eq!(merge_text_contents(&contents, None, None), "error\nhttps://a.b");
+        assert_eq!(
+            merge_text_contents(&contents, Some(" | "), Some("{index}. {content}")),
+            "1. error | 2. https://a.b"
+        );
+    }
+
+    #[test]
+    fn test_merge_tags() {
+        assert!(parse_tags("").is_empty());
+        assert_eq!(parse_tags("a, b,,c"), vec!["a", "b", "c"]);
+
+        let merged = merge_tags(
+            "work,todo",
+            &["url".to_string(), "work".to_string()],
+            &["todo".to_string()],
+        );
+        assert_eq!(merged, "work,url");
+    }
 }
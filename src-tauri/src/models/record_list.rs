@@ -0,0 +1,398 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::models::record::{parse_tags, Record};
+use crate::utils::{json, string};
+
+// Max records in a single page
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Filters of `list_records`, all conditions must match
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecordFilter {
+    // data_type(文本=text、图片=image)
+    pub data_types: Option<Vec<String>>,
+    pub favorite: Option<bool>,
+    // records must have all the tags
+    pub tags: Option<Vec<String>>,
+    // public key of the device which created the record
+    pub device: Option<String>,
+    // create_time range, `created_after` included and `created_before` excluded
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    // the content is encrypted in the node, so it is matched by the store after decrypting
+    pub keyword: Option<String>,
+    // list records in trash instead of the alive ones
+    pub in_trash: bool,
+    // only the synced or the local-only records
+    pub sync: Option<bool>,
+}
+
+/// Conditions the stores select the records by, the filter of `list_records` and the ones
+/// only used by the app itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecordQuery {
+    pub data_types: Option<Vec<String>>,
+    pub favorite: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    pub device: Option<String>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub keyword: Option<String>,
+    pub in_trash: bool,
+    // list the records both alive and in trash, `in_trash` is ignored
+    pub include_trash: bool,
+    // records moved to trash before the time, only used with `in_trash`
+    pub deleted_before: Option<i64>,
+    pub content_hash: Option<String>,
+    pub view_ids: Option<Vec<String>>,
+    pub document_ids: Option<Vec<String>>,
+    // only the synced or the local-only records
+    pub sync: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSortField {
+    #[default]
+    CreateTime,
+    // the time the record was moved to trash
+    DeleteTime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecordSort {
+    pub field: RecordSortField,
+    pub direction: SortDirection,
+}
+
+/// Record returned to the frontend, independent from the p2panda schema. Only the preview
+/// is listed, the full content is loaded with `get_record_content` when needed
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RecordItem {
+    pub view_id: String,
+    pub document_id: String,
+    // public key of the device which created the record
+    pub owner: String,
+    pub content_preview: Option<String>,
+    pub data_type: String,
+    pub md5: String,
+    pub create_time: i64,
+    pub is_favorite: bool,
+    pub tags: Vec<String>,
+    pub latest_addr: String,
+    // the time the record was moved to trash
+    pub delete_time: Option<i64>,
+    pub source_app: String,
+    pub mime_type: String,
+    pub byte_size: i64,
+    pub subtype: String,
+    // 0 means the record never expires
+    pub expires_at: i64,
+    // false when the record is kept on this device only
+    pub sync: bool,
+}
+
+/// Full payload of a record, for images it is the json of the original `ImageData`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RecordContent {
+    pub view_id: String,
+    pub data_type: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RecordPage {
+    pub items: Vec<RecordItem>,
+    // pass it to `list_records` to load the next page, none when no more records
+    pub next_cursor: Option<String>,
+    pub total_count: i64,
+}
+
+impl From<RecordFilter> for RecordQuery {
+    fn from(filter: RecordFilter) -> Self {
+        Self {
+            data_types: filter.data_types,
+            favorite: filter.favorite,
+            tags: filter.tags,
+            device: filter.device,
+            created_after: filter.created_after,
+            created_before: filter.created_before,
+            keyword: filter.keyword,
+            in_trash: filter.in_trash,
+            sync: filter.sync,
+            ..Default::default()
+        }
+    }
+}
+
+impl RecordQuery {
+    /// Field filter of the record schema
+    pub fn graphql_filter(&self) -> Value {
+        let mut filter = Map::new();
+        if !self.include_trash {
+            let is_deleted = match (self.in_trash, self.deleted_before) {
+                (false, _) => json!({"eq": 0}),
+                (true, None) => json!({"gt": 0}),
+                (true, Some(deleted_before)) => json!({"gt": 0, "lt": deleted_before}),
+            };
+            filter.insert("is_deleted".into(), is_deleted);
+        }
+        if let Some(content_hash) = &self.content_hash {
+            filter.insert("content_hash".into(), json!({ "eq": content_hash }));
+        }
+        if let Some(data_types) = &self.data_types {
+            filter.insert("data_type".into(), json!({ "in": data_types }));
+        }
+        if let Some(favorite) = self.favorite {
+            filter.insert("is_favorite".into(), json!({"eq": favorite as i64}));
+        }
+        // Tags are stored as a comma separated string, the node narrows down by the first
+        // tag and `matches_tags` checks the exact tags
+        if let Some(tag) = self.tags.as_ref().and_then(|tags| tags.first()) {
+            filter.insert("tags".into(), json!({ "contains": tag }));
+        }
+        let mut create_time = Map::new();
+        if let Some(created_after) = self.created_after {
+            create_time.insert("gte".into(), json!(created_after));
+        }
+        if let Some(created_before) = self.created_before {
+            create_time.insert("lt".into(), json!(created_before));
+        }
+        if !create_time.is_empty() {
+            filter.insert("create_time".into(), Value::Object(create_time));
+        }
+        Value::Object(filter)
+    }
+
+    /// Meta filter of the documents
+    pub fn graphql_meta(&self) -> Option<Value> {
+        let mut meta = Map::new();
+        if let Some(device) = &self.device {
+            meta.insert("owner".into(), json!({ "eq": device }));
+        }
+        if let Some(view_ids) = &self.view_ids {
+            meta.insert("viewId".into(), json!({ "in": view_ids }));
+        }
+        if let Some(document_ids) = &self.document_ids {
+            meta.insert("documentId".into(), json!({ "in": document_ids }));
+        }
+        (!meta.is_empty()).then_some(Value::Object(meta))
+    }
+
+    pub fn matches_tags(&self, tags: &str) -> bool {
+        match &self.tags {
+            None => true,
+            Some(wanted) => {
+                let tags = parse_tags(tags);
+                wanted.iter().all(|tag| tags.contains(tag))
+            }
+        }
+    }
+}
+
+impl RecordSort {
+    pub fn graphql_order(&self) -> (&'static str, &'static str) {
+        let field = match self.field {
+            RecordSortField::CreateTime => "create_time",
+            RecordSortField::DeleteTime => "is_deleted",
+        };
+        let direction = match self.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        (field, direction)
+    }
+
+    /// Position of the document in the order, the document id breaks the ties
+    pub fn key(&self, record: &Record, document_id: &str) -> (i64, String) {
+        let value = match self.field {
+            RecordSortField::CreateTime => record.create_time,
+            RecordSortField::DeleteTime => record.is_deleted,
+        };
+        (value, document_id.to_string())
+    }
+
+    pub fn compare(&self, a: &(i64, String), b: &(i64, String)) -> Ordering {
+        match self.direction {
+            SortDirection::Asc => a.cmp(b),
+            SortDirection::Desc => b.cmp(a),
+        }
+    }
+}
+
+/// Cursor handed to the frontend, it is bound to the query so it can not be reused with
+/// other filters
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PageCursor {
+    // cursor of the last returned document
+    pub after: String,
+    // count of the first page, the stores may only count the first page
+    #[serde(default)]
+    pub total_count: i64,
+    query: String,
+}
+
+impl PageCursor {
+    pub fn new(after: String, total_count: i64, filter: &RecordQuery, sort: &RecordSort) -> Self {
+        Self {
+            after,
+            total_count,
+            query: Self::fingerprint(filter, sort),
+        }
+    }
+
+    pub fn encode(&self) -> Result<String> {
+        Ok(string::base64_encode(json::stringify(self)?.as_bytes()))
+    }
+
+    pub fn decode(cursor: &str, filter: &RecordQuery, sort: &RecordSort) -> Result<Self> {
+        let cursor: PageCursor = general_purpose::STANDARD
+            .decode(cursor)
+            .map_err(|e| anyhow!(e))
+            .and_then(|bytes| Ok(String::from_utf8(bytes)?))
+            .and_then(|s| json::parse(&s))
+            .map_err(|e| anyhow!("invalid cursor: {}", e))?;
+        if cursor.query.ne(&Self::fingerprint(filter, sort)) {
+            bail!("cursor does not belong to the query");
+        }
+        Ok(cursor)
+    }
+
+    fn fingerprint(filter: &RecordQuery, sort: &RecordSort) -> String {
+        string::md5(&json::stringify(&(filter, sort)).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::models::record_list::{
+        PageCursor, RecordFilter, RecordQuery, RecordSort, RecordSortField, SortDirection,
+    };
+    use crate::utils::json;
+
+    #[test]
+    fn test_graphql_filter() {
+        assert_eq!(
+            RecordQuery::default().graphql_filter(),
+            json!({"is_deleted": {"eq": 0}})
+        );
+
+        let filter = RecordQuery {
+            data_types: Some(vec!["text".to_string()]),
+            favorite: Some(true),
+            tags: Some(vec!["work".to_string(), "todo".to_string()]),
+            created_after: Some(10),
+            created_before: Some(20),
+            keyword: Some("hello".to_string()),
+            in_trash: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.graphql_filter(),
+            json!({
+                "is_deleted": {"gt": 0},
+                "data_type": {"in": ["text"]},
+                "is_favorite": {"eq": 1},
+                "tags": {"contains": "work"},
+                "create_time": {"gte": 10, "lt": 20},
+            })
+        );
+        assert_eq!(filter.graphql_meta(), None);
+        assert!(filter.matches_tags("todo,work,home"));
+        assert!(!filter.matches_tags("workday,todo"));
+
+        let filter = RecordQuery {
+            in_trash: true,
+            deleted_before: Some(30),
+            device: Some("pk".to_string()),
+            view_ids: Some(vec!["v1".to_string()]),
+            document_ids: Some(vec!["d1".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.graphql_filter(),
+            json!({"is_deleted": {"gt": 0, "lt": 30}})
+        );
+        assert_eq!(
+            filter.graphql_meta(),
+            Some(json!({
+                "owner": {"eq": "pk"},
+                "viewId": {"in": ["v1"]},
+                "documentId": {"in": ["d1"]},
+            }))
+        );
+
+        let filter = RecordQuery {
+            include_trash: true,
+            content_hash: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.graphql_filter(),
+            json!({"content_hash": {"eq": "abc"}})
+        );
+    }
+
+    #[test]
+    fn test_record_filter() {
+        // The conditions used by the app itself are not part of the filter of `list_records`
+        let filter: RecordFilter =
+            json::parse(r#"{"in_trash":true,"include_trash":true,"view_ids":["v1"]}"#).unwrap();
+        assert_eq!(
+            RecordQuery::from(filter),
+            RecordQuery {
+                in_trash: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_graphql_order() {
+        assert_eq!(
+            RecordSort::default().graphql_order(),
+            ("create_time", "DESC")
+        );
+        let sort = RecordSort {
+            field: RecordSortField::DeleteTime,
+            direction: SortDirection::Asc,
+        };
+        assert_eq!(sort.graphql_order(), ("is_deleted", "ASC"));
+    }
+
+    #[test]
+    fn test_page_cursor() {
+        let filter = RecordQuery {
+            favorite: Some(false),
+            ..Default::default()
+        };
+        let sort = RecordSort::default();
+
+        let encoded = PageCursor::new("abc".to_string(), 7, &filter, &sort)
+            .encode()
+            .unwrap();
+        let cursor = PageCursor::decode(&encoded, &filter, &sort).unwrap();
+        assert_eq!((cursor.after.as_str(), cursor.total_count), ("abc", 7));
+
+        assert!(PageCursor::decode(&encoded, &RecordQuery::default(), &sort).is_err());
+        assert!(PageCursor::decode("not a cursor", &filter, &sort).is_err());
+    }
+}
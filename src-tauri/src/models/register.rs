@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+// Logical name of the schema in schema/schema.lock, see `p2panda::schema_registry`
+pub const SCHEMA_NAME: &str = "register";
+
+// Entries kept in the history of each register
+pub const REGISTER_HISTORY_LEN: usize = 10;
+
+/// One value copied into a register, the newest entry is the current register value
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RegisterEntry {
+    pub view_id: String,
+    // 寄存器名称 a-z
+    pub name: String,
+    pub content: String,
+    pub content_preview: Option<String>,
+    pub data_type: String,
+    pub md5: String,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegisterAction {
+    // copy the clipboard content into the register
+    Copy,
+    // put the register value onto the clipboard
+    Paste,
+}
+
+/// Global shortcut bound to a register action
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RegisterHotkey {
+    pub register: String,
+    pub action: RegisterAction,
+    pub hotkey: String,
+}
+
+/// Normalize the register name, only a single letter a-z is allowed
+pub fn parse_register_name(name: &str) -> Result<String> {
+    let name = name.trim().to_ascii_lowercase();
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => Ok(name),
+        _ => bail!("invalid register name: {:?}, expect a letter a-z", name),
+    }
+}
+
+/// Latest entry of each register, `entries` should be ordered by create_time desc
+pub fn latest_entries(entries: Vec<RegisterEntry>) -> Vec<RegisterEntry> {
+    let mut latest: Vec<RegisterEntry> = vec![];
+    for entry in entries {
+        if !latest.iter().any(|e| e.name.eq(&entry.name)) {
+            latest.push(entry);
+        }
+    }
+    latest.sort_by(|a, b| a.name.cmp(&b.name));
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::register::{latest_entries, parse_register_name, RegisterEntry};
+
+    fn entry(name: &str, create_time: i64) -> RegisterEntry {
+        RegisterEntry {
+            view_id: format!("{}{}", name, create_time),
+            name: name.to_string(),
+            create_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_register_name() {
+        assert_eq!(parse_register_name("a").unwrap(), "a");
+        assert_eq!(parse_register_name(" Q ").unwrap(), "q");
+        assert!(parse_register_name("").is_err());
+        assert!(parse_register_name("ab").is_err());
+        assert!(parse_register_name("1").is_err());
+        assert!(parse_register_name("é").is_err());
+    }
+
+    #[test]
+    fn test_latest_entries() {
+        let latest = latest_entries(vec![entry("q", 3), entry("a", 2), entry("q", 1)]);
+        let view_ids: Vec<&str> = latest.iter().map(|e| e.view_id.as_str()).collect();
+        assert_eq!(view_ids, vec!["a2", "q3"]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+// Logical name of the schema in schema/schema.lock, see `p2panda::schema_registry`
+pub const SCHEMA_NAME: &str = "snippet";
+
+// Format used by `{date}` without format string
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Snippet {
+    pub view_id: String,
+    pub document_id: String,
+    pub name: String,
+    // 模板内容，支持 {date:%Y-%m-%d}、{clipboard}、{uuid}、{cursor}、{prompt:名称} 占位符
+    pub content: String,
+    // 渲染前需要用户填写的 prompt 名称
+    pub prompts: Vec<String>,
+    pub create_time: i64,
+    pub update_time: i64,
+}
+
+/// Values used to expand the placeholders of a snippet
+#[derive(Debug, Clone)]
+pub struct RenderContext {
+    pub now: DateTime<Local>,
+    pub clipboard: Option<String>,
+    pub prompts: HashMap<String, String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RenderedSnippet {
+    pub text: String,
+    // Char offset of `{cursor}` in the text
+    pub cursor: Option<usize>,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Split the template into text and placeholders, `{{` and `}}` are escaped braces
+fn tokenize(template: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = vec![];
+    let mut rest = template;
+    while !rest.is_empty() {
+        if let Some(stripped) = rest.strip_prefix("{{") {
+            tokens.push(Token::Text("{"));
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("}}") {
+            tokens.push(Token::Text("}"));
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix('{') {
+            let end = match stripped.find('}') {
+                Some(end) => end,
+                None => bail!("unclosed placeholder in snippet: {}", rest),
+            };
+            tokens.push(Token::Placeholder(&stripped[..end]));
+            rest = &stripped[end + 1..];
+        } else {
+            let end = rest.find(['{', '}']).unwrap_or(rest.len()).max(1);
+            tokens.push(Token::Text(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    Ok(tokens)
+}
+
+/// Names of the `{prompt:name}` placeholders in order, without duplicates
+pub fn prompt_names(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for token in tokenize(template).unwrap_or_default() {
+        if let Token::Placeholder(placeholder) = token {
+            if let Some(name) = placeholder.strip_prefix("prompt:") {
+                let name = name.trim().to_string();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    names
+}
+
+fn format_date(now: &DateTime<Local>, fmt: &str) -> Result<String> {
+    let items: Vec<Item> = StrftimeItems::new(fmt).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        bail!("invalid date format in snippet: {}", fmt);
+    }
+    Ok(now.format_with_items(items.into_iter()).to_string())
+}
+
+/// Expand the placeholders of the template, unknown placeholders are kept as they are
+pub fn render(template: &str, ctx: &RenderContext) -> Result<RenderedSnippet> {
+    let mut text = String::new();
+    let mut cursor = None;
+
+    for token in tokenize(template)? {
+        let placeholder = match token {
+            Token::Text(s) => {
+                text.push_str(s);
+                continue;
+            }
+            Token::Placeholder(placeholder) => placeholder,
+        };
+
+        let (name, arg) = match placeholder.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (placeholder, None),
+        };
+        match (name, arg) {
+            ("date", fmt) => text.push_str(&format_date(
+                &ctx.now,
+                fmt.unwrap_or(DEFAULT_DATE_FORMAT),
+            )?),
+            ("clipboard", None) => text.push_str(ctx.clipboard.as_deref().unwrap_or_default()),
+            ("uuid", None) => text.push_str(&uuid::Uuid::new_v4().to_string()),
+            ("cursor", None) => {
+                if cursor.is_none() {
+                    cursor = Some(text.chars().count());
+                }
+            }
+            ("prompt", Some(prompt)) => match ctx.prompts.get(prompt.trim()) {
+                Some(value) => text.push_str(value),
+                None => bail!("missing value for prompt: {}", prompt.trim()),
+            },
+            _ => {
+                text.push('{');
+                text.push_str(placeholder);
+                text.push('}');
+            }
+        }
+    }
+
+    Ok(RenderedSnippet { text, cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{Local, TimeZone};
+
+    use crate::models::snippet::{prompt_names, render, RenderContext};
+
+    fn context() -> RenderContext {
+        RenderContext {
+            now: Local.with_ymd_and_hms(2023, 11, 20, 8, 30, 0).unwrap(),
+            clipboard: Some("copied".to_string()),
+            prompts: HashMap::from([("name".to_string(), "Alice".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let res = render(
+            "{date:%Y-%m-%d} {date} {prompt:name}: {clipboard}{cursor}!",
+            &context(),
+        )
+        .unwrap();
+        assert_eq!(res.text, "2023-11-20 2023-11-20 Alice: copied!");
+        assert_eq!(res.cursor, Some(35));
+
+        let res = render("{{literal}} {unknown} {uuid}", &context()).unwrap();
+        assert!(res.text.starts_with("{literal} {unknown} "));
+        assert_eq!(res.text.len(), "{literal} {unknown} ".len() + 36);
+        assert_eq!(res.cursor, None);
+    }
+
+    #[test]
+    fn test_render_error() {
+        assert!(render("{prompt:missing}", &context()).is_err());
+        assert!(render("{date:%Q}", &context()).is_err());
+        assert!(render("unclosed {date", &context()).is_err());
+    }
+
+    #[test]
+    fn test_prompt_names() {
+        assert_eq!(
+            prompt_names("{prompt:b} {prompt:a} {prompt: b} {clipboard}"),
+            vec!["b", "a"]
+        );
+    }
+}
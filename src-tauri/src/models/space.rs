@@ -0,0 +1,231 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+// The space of the records written before spaces, its key is the first group key
+pub const DEFAULT_SPACE_ID: &str = "default";
+
+/// Records captured while a space is active are encrypted with the key of the space and
+/// written to the schemas of the space. Only the devices paired in the space know the
+/// schemas, the node replicates a schema with the devices supporting it, see `NodeServer`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Space {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    // none for the default space until its schemas are published, its records are in the
+    // schemas of the lock file until then
+    #[serde(default)]
+    pub schemas: Option<SpaceSchemas>,
+    // schemas replaced by pairing or by removing a member, the records of this device are
+    // moved from them into the current ones
+    #[serde(default)]
+    pub replaced_schemas: Vec<SpaceSchemas>,
+}
+
+/// Schema ids of the record and record version documents of a space
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpaceSchemas {
+    pub record: String,
+    pub record_version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpaceList {
+    // id of the active space
+    pub active: String,
+    pub spaces: Vec<Space>,
+}
+
+impl Default for SpaceList {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_SPACE_ID.to_string(),
+            spaces: vec![Space::new(
+                DEFAULT_SPACE_ID.to_string(),
+                "默认".to_string(),
+                0,
+            )],
+        }
+    }
+}
+
+impl Space {
+    pub fn new(id: String, name: String, created_at: i64) -> Self {
+        Self {
+            id,
+            name,
+            created_at,
+            schemas: None,
+            replaced_schemas: vec![],
+        }
+    }
+
+    /// Use the schemas from now on, the current ones are kept as replaced
+    pub fn replace_schemas(&mut self, schemas: SpaceSchemas) {
+        if self.schemas.as_ref() == Some(&schemas) {
+            return;
+        }
+        self.replaced_schemas.retain(|s| s.ne(&schemas));
+        if let Some(current) = self.schemas.replace(schemas) {
+            self.replaced_schemas.push(current);
+        }
+    }
+}
+
+impl SpaceList {
+    pub fn get(&self, id: &str) -> Option<&Space> {
+        self.spaces.iter().find(|s| s.id.eq(id))
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut Space> {
+        match self.spaces.iter_mut().find(|s| s.id.eq(id)) {
+            Some(space) => Ok(space),
+            None => bail!("space not found: {}", id),
+        }
+    }
+
+    /// Add the space or keep the existing one, e.g. when joining a space by pairing. The
+    /// existing space takes the schemas of the joined one, so both devices write to the
+    /// same schemas
+    pub fn add(&mut self, space: Space) {
+        match self.spaces.iter_mut().find(|s| s.id.eq(&space.id)) {
+            Some(existing) => {
+                if let Some(schemas) = space.schemas {
+                    existing.replace_schemas(schemas);
+                }
+            }
+            None => self.spaces.push(space),
+        }
+    }
+
+    pub fn replace_schemas(&mut self, id: &str, schemas: SpaceSchemas) -> Result<()> {
+        self.get_mut(id)?.replace_schemas(schemas);
+        Ok(())
+    }
+
+    /// Schemas of all the spaces, the current and the replaced ones
+    pub fn schemas(&self) -> Vec<&SpaceSchemas> {
+        self.spaces
+            .iter()
+            .flat_map(|s| s.schemas.iter().chain(s.replaced_schemas.iter()))
+            .collect()
+    }
+
+    pub fn rename(&mut self, id: &str, name: String) -> Result<()> {
+        let name = parse_space_name(&name)?;
+        self.get_mut(id)?.name = name;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<()> {
+        if id.eq(DEFAULT_SPACE_ID) {
+            bail!("the default space can not be removed");
+        }
+        if id.eq(&self.active) {
+            bail!("switch to another space before removing it");
+        }
+        let len = self.spaces.len();
+        self.spaces.retain(|s| s.id.ne(id));
+        if self.spaces.len() == len {
+            bail!("space not found: {}", id);
+        }
+        Ok(())
+    }
+
+    pub fn switch(&mut self, id: &str) -> Result<()> {
+        if self.get(id).is_none() {
+            bail!("space not found: {}", id);
+        }
+        self.active = id.to_string();
+        Ok(())
+    }
+}
+
+/// Space ids are used as file names, only the ids generated by `SpaceDao` are accepted
+pub fn check_space_id(id: &str) -> Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        bail!("invalid space id: {:?}", id);
+    }
+    Ok(())
+}
+
+pub fn parse_space_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("space name can not be empty");
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::space::{check_space_id, Space, SpaceList, SpaceSchemas, DEFAULT_SPACE_ID};
+
+    fn schemas(id: &str) -> SpaceSchemas {
+        SpaceSchemas {
+            record: format!("record_{}", id),
+            record_version: format!("record_version_{}", id),
+        }
+    }
+
+    #[test]
+    fn test_space_list() {
+        let mut list = SpaceList::default();
+        list.add(Space::new("work".to_string(), "工作".to_string(), 1));
+        list.add(Space::new("work".to_string(), "other".to_string(), 2));
+        assert_eq!(list.spaces.len(), 2);
+        assert_eq!(list.get("work").unwrap().name, "工作");
+
+        assert!(list.rename("work", " ".to_string()).is_err());
+        list.rename("work", " Work ".to_string()).unwrap();
+        assert_eq!(list.get("work").unwrap().name, "Work");
+
+        assert!(list.switch("home").is_err());
+        list.switch("work").unwrap();
+        assert_eq!(list.active, "work");
+
+        assert!(list.remove("work").is_err());
+        assert!(list.remove(DEFAULT_SPACE_ID).is_err());
+        list.switch(DEFAULT_SPACE_ID).unwrap();
+        list.remove("work").unwrap();
+        assert!(list.remove("work").is_err());
+        assert_eq!(list, SpaceList::default());
+
+        assert!(check_space_id(DEFAULT_SPACE_ID).is_ok());
+        assert!(check_space_id("5b0c2e7e-5d6f-4f0e-9a59-2c0f3e6b1d2a").is_ok());
+        assert!(check_space_id("../secret").is_err());
+        assert!(check_space_id("").is_err());
+    }
+
+    #[test]
+    fn test_space_schemas() {
+        let mut list = SpaceList::default();
+        assert!(list.schemas().is_empty());
+        list.replace_schemas(DEFAULT_SPACE_ID, schemas("a"))
+            .unwrap();
+        assert!(list.replace_schemas("work", schemas("b")).is_err());
+
+        // Joining the space again takes the schemas of the joined one
+        let mut joined = Space::new(DEFAULT_SPACE_ID.to_string(), "other".to_string(), 1);
+        joined.schemas = Some(schemas("b"));
+        list.add(joined.clone());
+        list.add(joined);
+        let space = list.get(DEFAULT_SPACE_ID).unwrap();
+        assert_eq!(space.name, "默认");
+        assert_eq!(space.schemas, Some(schemas("b")));
+        assert_eq!(space.replaced_schemas, vec![schemas("a")]);
+
+        // A space without schemas keeps the current ones
+        list.add(Space::new(
+            DEFAULT_SPACE_ID.to_string(),
+            "other".to_string(),
+            2,
+        ));
+        list.replace_schemas(DEFAULT_SPACE_ID, schemas("a"))
+            .unwrap();
+        let space = list.get(DEFAULT_SPACE_ID).unwrap();
+        assert_eq!(space.schemas, Some(schemas("a")));
+        assert_eq!(space.replaced_schemas, vec![schemas("b")]);
+        assert_eq!(list.schemas(), vec![&schemas("a"), &schemas("b")]);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Stored size of a single record document
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RecordSize {
+    pub view_id: String,
+    pub document_id: String,
+    pub data_type: String,
+    // public key of the device which created the record
+    pub owner: String,
+    pub byte_size: i64,
+    pub create_time: i64,
+    pub is_favorite: i64,
+    pub is_deleted: i64,
+}
+
+impl RecordSize {
+    /// Byte size of the payload stored for a record, the size of the content is kept in the
+    /// `byte_size` field so the content does not need to be loaded
+    pub fn calculate(content_size: i64, content_preview: &str) -> i64 {
+        content_size + content_preview.len() as i64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct UsageStat {
+    pub bytes: i64,
+    pub count: i64,
+}
+
+impl UsageStat {
+    fn add(&mut self, bytes: i64) {
+        self.bytes += bytes;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct StorageUsage {
+    // records not in trash
+    pub total: UsageStat,
+    pub trash: UsageStat,
+    pub by_type: HashMap<String, UsageStat>,
+    pub by_device: HashMap<String, UsageStat>,
+    pub quota_bytes: i64,
+    pub sqlite_file_bytes: u64,
+}
+
+impl StorageUsage {
+    pub fn from_records(records: &[RecordSize]) -> Self {
+        let mut usage = StorageUsage::default();
+        for r in records {
+            if r.is_deleted != 0 {
+                usage.trash.add(r.byte_size);
+                continue;
+            }
+            usage.total.add(r.byte_size);
+            usage
+                .by_type
+                .entry(r.data_type.clone())
+                .or_default()
+                .add(r.byte_size);
+            usage
+                .by_device
+                .entry(r.owner.clone())
+                .or_default()
+                .add(r.byte_size);
+        }
+        usage
+    }
+}
+
+/// Pick the records to evict until the records fit in `quota_bytes`. The records in trash
+/// still take space, they go first and are purged, then the oldest non-favorite ones, which
+/// are moved to trash and purged by a later run
+pub fn select_quota_evictions(records: &[RecordSize], quota_bytes: i64) -> Vec<RecordSize> {
+    let mut total: i64 = records.iter().map(|r| r.byte_size).sum();
+    if total <= quota_bytes {
+        return vec![];
+    }
+
+    let mut candidates: Vec<&RecordSize> = records
+        .iter()
+        .filter(|r| r.is_deleted != 0 || r.is_favorite == 0)
+        .collect();
+    // the trash by the time it was deleted, then the records by the time they were created
+    candidates.sort_by_key(|r| match r.is_deleted {
+        0 => (1, r.create_time),
+        deleted_at => (0, deleted_at),
+    });
+
+    let mut evictions = vec![];
+    for r in candidates {
+        if total <= quota_bytes {
+            break;
+        }
+        total -= r.byte_size;
+        evictions.push(r.clone());
+    }
+    evictions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{select_quota_evictions, RecordSize, StorageUsage};
+
+    fn record(view_id: &str, byte_size: i64, create_time: i64, is_favorite: i64) -> RecordSize {
+        RecordSize {
+            view_id: view_id.to_string(),
+            document_id: view_id.to_string(),
+            data_type: "text".to_string(),
+            owner: "device".to_string(),
+            byte_size,
+            create_time,
+            is_favorite,
+            is_deleted: 0,
+        }
+    }
+
+    #[test]
+    fn test_from_records() {
+        let mut trashed = record("c", 5, 3, 0);
+        trashed.is_deleted = 1;
+        let usage =
+            StorageUsage::from_records(&[record("a", 10, 1, 0), record("b", 20, 2, 1), trashed]);
+
+        assert_eq!(usage.total.bytes, 30);
+        assert_eq!(usage.total.count, 2);
+        assert_eq!(usage.trash.bytes, 5);
+        assert_eq!(usage.by_type["text"].bytes, 30);
+        assert_eq!(usage.by_device["device"].count, 2);
+    }
+
+    #[test]
+    fn test_select_quota_evictions() {
+        let records = vec![
+            record("new", 10, 3, 0),
+            record("fav", 10, 1, 1),
+            record("old", 10, 2, 0),
+        ];
+
+        assert!(select_quota_evictions(&records, 30).is_empty());
+
+        let evictions = select_quota_evictions(&records, 15);
+        let evicted: Vec<&str> = evictions.iter().map(|r| r.view_id.as_str()).collect();
+        assert_eq!(evicted, vec!["old", "new"]);
+
+        let evictions = select_quota_evictions(&records, 25);
+        let evicted: Vec<&str> = evictions.iter().map(|r| r.view_id.as_str()).collect();
+        assert_eq!(evicted, vec!["old"]);
+    }
+
+    #[test]
+    fn test_select_quota_evictions_trash_first() {
+        let mut trashed_fav = record("trashed_fav", 10, 3, 1);
+        trashed_fav.is_deleted = 20;
+        let mut trashed = record("trashed", 10, 4, 0);
+        trashed.is_deleted = 10;
+        let records = vec![record("old", 10, 1, 0), trashed_fav, trashed];
+
+        // Trash is in the quota, the one deleted first goes first
+        let evictions = select_quota_evictions(&records, 20);
+        let evicted: Vec<&str> = evictions.iter().map(|r| r.view_id.as_str()).collect();
+        assert_eq!(evicted, vec!["trashed"]);
+
+        let evictions = select_quota_evictions(&records, 5);
+        let evicted: Vec<&str> = evictions.iter().map(|r| r.view_id.as_str()).collect();
+        assert_eq!(evicted, vec!["trashed", "trashed_fav", "old"]);
+    }
+}
@@ -1,16 +1,28 @@
-use std::sync::OnceLock;
+//!
+//! Client of the GraphQL API of the embedded node. aquadoggo 0.5 only exposes `Node` and
+//! its configuration, the publishing of entries is private to the node, so the app publishes
+//! over the local HTTP endpoint like the frontend. The content of the records is read from
+//! the database of the node in this process, see `node_store`
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use graphql_client::{GraphQLQuery, Response};
 use log::{debug, info, warn};
 use p2panda_rs::entry::encode::sign_and_encode_entry;
 use p2panda_rs::entry::traits::AsEncodedEntry;
-use p2panda_rs::identity::PublicKey;
+use p2panda_rs::identity::{KeyPair, PublicKey};
 use p2panda_rs::operation::encode::encode_plain_operation;
 use p2panda_rs::operation::plain::PlainOperation;
-use p2panda_rs::operation::traits::Actionable;
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::schema::SchemaId;
 use reqwest::Client;
-use tokio::sync::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 use crate::config::app_config::AppConfig;
 use crate::graphql::next_args_query::NextArgsQueryNextArgs;
@@ -20,22 +32,114 @@ use crate::graphql::{
 };
 use crate::p2panda::key_pair::get_key_pair;
 
+/// Errors of publishing to and querying the node
+#[derive(Debug)]
+pub enum NodeError {
+    /// The node can not be reached, e.g. it is down or still starting
+    Unreachable(String),
+    /// The node answered with GraphQL errors
+    GraphQL(Vec<String>),
+    /// The node has no next arguments for the entry, or rejected the entry because another
+    /// entry was published into the log since the arguments were fetched
+    NextArgsConflict(String),
+    /// The operation or the entry can not be encoded and signed
+    Encoding(String),
+    /// The published document view is not materialized by the node in time
+    NotMaterialized(String),
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeError::Unreachable(e) => write!(f, "node unreachable: {}", e),
+            NodeError::GraphQL(errors) => write!(f, "graphql errors: {}", errors.join("; ")),
+            NodeError::NextArgsConflict(e) => write!(f, "next args conflict: {}", e),
+            NodeError::Encoding(e) => write!(f, "encoding failed: {}", e),
+            NodeError::NotMaterialized(view_id) => {
+                write!(f, "document view {} not materialized", view_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NodeError {}
+
+impl NodeError {
+    fn from_request(err: reqwest::Error) -> anyhow::Error {
+        if err.is_connect() || err.is_timeout() {
+            NodeError::Unreachable(err.to_string()).into()
+        } else {
+            err.into()
+        }
+    }
+
+    // aquadoggo rejects an entry built on stale next args with the log validation errors,
+    // e.g. "Entry's claimed seq num of 3 does not match expected seq num of 4 ..."
+    fn from_graphql(messages: Vec<String>) -> NodeError {
+        let stale = messages.iter().any(|message| {
+            let message = message.to_lowercase();
+            ["seq num", "backlink", "skiplink"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        });
+        if stale {
+            NodeError::NextArgsConflict(messages.join("; "))
+        } else {
+            NodeError::GraphQL(messages)
+        }
+    }
+
+    pub fn is_next_args_conflict(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<NodeError>(),
+            Some(NodeError::NextArgsConflict(_))
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct GraphQLHandler {
     endpoint: String,
+    // The client keeps a pool of connections, the reads run concurrently
     pub cli: Client,
+    // public key -> lock of the publishes signed by the key pair, see `lock_publish`
+    publish_locks: parking_lot::Mutex<HashMap<String, Arc<Mutex<()>>>>,
 }
 
 impl GraphQLHandler {
-    // init global
-    pub fn global() -> &'static Mutex<GraphQLHandler> {
-        static CLIENT: OnceLock<Mutex<GraphQLHandler>> = OnceLock::new();
+    // Publishing again with fresh next args, when the log got another entry meanwhile
+    const MAX_PUBLISH_ATTEMPTS: u32 = 3;
+
+    // The node materializes the documents in the background after publishing
+    const MATERIALIZE_CHECK_ATTEMPTS: u32 = 10;
+    const MATERIALIZE_CHECK_MILLIS: u64 = 100;
+
+    // init global, a client failing to build is built again by the next call
+    pub fn global() -> Result<&'static GraphQLHandler> {
+        static CLIENT: OnceLock<GraphQLHandler> = OnceLock::new();
+
+        if let Some(cli) = CLIENT.get() {
+            return Ok(cli);
+        }
+        let cli = GraphQLHandler::new()?;
+        Ok(CLIENT.get_or_init(|| cli))
+    }
 
-        CLIENT.get_or_init(|| Mutex::new(GraphQLHandler::new()))
+    /// Serialize the publishes signed by the key pair. The next args of an entry depend on
+    /// the entries published before it: the seq num in the log of the document, and for a
+    /// create the next free log id among all the logs of the key pair
+    pub async fn lock_publish(&self, public_key: &PublicKey) -> OwnedMutexGuard<()> {
+        let lock = self
+            .publish_locks
+            .lock()
+            .entry(public_key.to_string())
+            .or_default()
+            .clone();
+        lock.lock_owned().await
     }
 
     pub async fn next_args(
-        &mut self,
+        &self,
         public_key: PublicKey,
         view_id: Option<String>,
     ) -> Result<Option<NextArgsQueryNextArgs>> {
@@ -43,25 +147,15 @@ impl GraphQLHandler {
             pk: public_key,
             vid: view_id,
         });
+        let data: Option<next_args_query::ResponseData> = self.post(&request_body).await?;
 
-        let res = self
-            .cli
-            .post(&self.endpoint)
-            .json(&request_body)
-            .send()
-            .await?;
-        let response_body: Response<next_args_query::ResponseData> = res.json().await?;
-
-        debug!("next_args response: {:?}", response_body);
+        debug!("next_args response: {:?}", data);
 
-        match response_body.data {
-            None => Ok(None),
-            Some(res) => Ok(res.next_args),
-        }
+        Ok(data.and_then(|res| res.next_args))
     }
 
     pub async fn publish(
-        &mut self,
+        &self,
         encoded_entry: EncodedEntry,
         encoded_operation: EncodedOperation,
     ) -> Result<Option<PublishMutPublish>> {
@@ -69,30 +163,77 @@ impl GraphQLHandler {
             entry: encoded_entry,
             operation: encoded_operation,
         });
+        let data: Option<publish_mut::ResponseData> = self.post(&request_body).await?;
+
+        debug!("publish response: {:?}", data);
+
+        Ok(data.map(|res| res.publish))
+    }
+
+    /// Send the GraphQL request to the node, the errors in the response are returned as
+    /// `NodeError::GraphQL`
+    pub async fn post<Q: Serialize, R: DeserializeOwned>(
+        &self,
+        request_body: &Q,
+    ) -> Result<Option<R>> {
         let res = self
             .cli
             .post(&self.endpoint)
-            .json(&request_body)
+            .json(request_body)
             .send()
-            .await?;
-        let response_body: Response<publish_mut::ResponseData> = res.json().await?;
+            .await
+            .map_err(NodeError::from_request)?;
+        let response_body: Response<R> = res.json().await?;
 
-        debug!("publish response: {:?}", response_body);
-
-        match response_body.data {
-            None => Ok(None),
-            Some(res) => Ok(Some(res.publish)),
+        if let Some(errors) = response_body.errors.filter(|errors| !errors.is_empty()) {
+            let messages = errors.into_iter().map(|e| e.message).collect();
+            return Err(NodeError::from_graphql(messages).into());
         }
+        Ok(response_body.data)
     }
 
     pub fn endpoint(&self) -> &str {
         &self.endpoint
     }
 
-    pub async fn send_to_node(&mut self, operation: PlainOperation) -> Result<String> {
+    /// Publish the operation, return the operation id. The entry is built again with fresh
+    /// next args when another entry was published into the log meanwhile. With
+    /// `verify_publish` in the config, it also waits for the node to materialize the view
+    pub async fn send_to_node(&self, operation: PlainOperation) -> Result<String> {
         // 1. Load public key from key_pair
         let key_pair = get_key_pair()?;
 
+        let operation_id = {
+            // Only the publishes wait for each other, the reads go on meanwhile
+            let _guard = self.lock_publish(&key_pair.public_key()).await;
+            let mut attempt = 1;
+            loop {
+                match self.publish_operation(&key_pair, &operation).await {
+                    Err(err)
+                        if NodeError::is_next_args_conflict(&err)
+                            && attempt < Self::MAX_PUBLISH_ATTEMPTS =>
+                    {
+                        warn!("publish attempt {} failed, retry: {}", attempt, err);
+                        attempt += 1;
+                    }
+                    res => break res?,
+                }
+            }
+        };
+
+        let verify_publish = AppConfig::latest().read().verify_publish;
+        if verify_publish.unwrap_or_default() {
+            self.verify_materialized(operation.schema_id(), &operation_id)
+                .await?;
+        }
+        Ok(operation_id)
+    }
+
+    async fn publish_operation(
+        &self,
+        key_pair: &KeyPair,
+        operation: &PlainOperation,
+    ) -> Result<String> {
         // 2. Send `nextArgs` GraphQL query to get the arguments from the node to create the next entry
         let next_args = self
             .next_args(
@@ -102,28 +243,28 @@ impl GraphQLHandler {
             )
             .await?;
 
-        if next_args.is_none() {
-            warn!("Get next arguments failed: {:?}", next_args);
-            return Ok(String::new());
-        }
-
         let NextArgsQueryNextArgs {
             log_id,
             seq_num,
             skiplink,
             backlink,
-        } = next_args.unwrap();
+        } = next_args.ok_or(NodeError::NextArgsConflict(format!(
+            "no next args for previous view {:?}",
+            operation.previous()
+        )))?;
 
         // 3. Create p2panda data! Encode operation, sign and encode entry
-        let encoded_operation = encode_plain_operation(&operation)?;
+        let encoded_operation =
+            encode_plain_operation(operation).map_err(|e| NodeError::Encoding(e.to_string()))?;
         let encoded_entry = sign_and_encode_entry(
             &log_id,
             &seq_num,
             skiplink.as_ref(),
             backlink.as_ref(),
             &encoded_operation,
-            &key_pair,
-        )?;
+            key_pair,
+        )
+        .map_err(|e| NodeError::Encoding(e.to_string()))?;
 
         let operation_id = encoded_entry.hash();
         self.publish(encoded_entry, encoded_operation).await?;
@@ -131,31 +272,92 @@ impl GraphQLHandler {
         Ok(operation_id.to_string())
     }
 
-    fn new() -> Self {
+    /// Wait until the node materialized the document view of the published operation, the
+    /// operation id is the id of the view it created
+    pub async fn verify_materialized(&self, schema_id: &SchemaId, view_id: &str) -> Result<()> {
+        // The query of a single document is named after the schema id, it is not known when
+        // the queries are generated
+        let request_body = serde_json::json!({
+            "query": format!(
+                "query ViewById($view_id: DocumentViewId!) {{ view: {}(viewId: $view_id) {{ meta {{ viewId }} }} }}",
+                schema_id
+            ),
+            "variables": { "view_id": view_id },
+        });
+
+        for _ in 0..Self::MATERIALIZE_CHECK_ATTEMPTS {
+            let data: Option<serde_json::Value> = self.post(&request_body).await?;
+            if data.is_some_and(|data| !data["view"].is_null()) {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(Self::MATERIALIZE_CHECK_MILLIS)).await;
+        }
+        Err(NodeError::NotMaterialized(view_id.to_string()).into())
+    }
+
+    fn new() -> Result<Self> {
         let graphql_port;
         {
             graphql_port = AppConfig::latest().read().graphql_port.unwrap();
         }
         let endpoint = format!("http://localhost:{}/graphql", graphql_port);
-        let cli = Client::new();
+        // The node runs in this process, a system proxy would only add a round trip
+        let cli = Client::builder()
+            .no_proxy()
+            .build()
+            .context("failed to build the graphql client")?;
 
         info!("Init graphql client success endpoint: {}", endpoint);
 
-        Self { cli, endpoint }
+        Ok(Self {
+            cli,
+            endpoint,
+            publish_locks: Default::default(),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use p2panda_rs::identity::KeyPair;
+
     use crate::config::configure::Configure;
-    use crate::p2panda::graphql::GraphQLHandler;
+    use crate::p2panda::graphql::{GraphQLHandler, NodeError};
 
     #[test]
     fn test_new_config() {
         let cfg = Configure::new();
         println!("{:?}", cfg);
 
-        let cli = GraphQLHandler::global();
+        let cli = GraphQLHandler::global().unwrap();
         println!("client: {:?}", cli)
     }
+
+    #[tokio::test]
+    async fn test_lock_publish() {
+        let handler = GraphQLHandler::global().unwrap();
+        let (a, b) = (KeyPair::new().public_key(), KeyPair::new().public_key());
+        let _guard = handler.lock_publish(&a).await;
+        // Other key pairs publish meanwhile
+        let _other = handler.lock_publish(&b).await;
+
+        let wait = Duration::from_millis(50);
+        assert!(tokio::time::timeout(wait, handler.lock_publish(&a))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_graphql_errors() {
+        let stale = NodeError::from_graphql(vec![
+            "Entry's claimed seq num of 3 does not match expected seq num of 4".to_string(),
+        ]);
+        assert!(NodeError::is_next_args_conflict(&stale.into()));
+
+        let invalid = NodeError::from_graphql(vec!["field 'content' missing".to_string()]);
+        assert!(matches!(&invalid, NodeError::GraphQL(errors) if errors.len() == 1));
+        assert!(!NodeError::is_next_args_conflict(&invalid.into()));
+    }
 }
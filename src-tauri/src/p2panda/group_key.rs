@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs::{read_to_string, rename, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use parking_lot::RwLock;
+
+use crate::dao::space_dao::SpaceDao;
+use crate::models::space::{check_space_id, DEFAULT_SPACE_ID};
+use crate::utils::crypto::{self, GroupKey};
+use crate::utils::dir::{group_key_path, space_keys_dir};
+
+// Keys of each space, the current key first, then the keys replaced by pairing
+static GROUP_KEYS: RwLock<Option<HashMap<String, Vec<GroupKey>>>> = RwLock::new(None);
+
+/// Get or generate the key of the active space, the record content published to the node
+/// is encrypted with it
+pub fn get_group_key() -> Result<GroupKey> {
+    Ok(group_keys()?[0])
+}
+
+/// All the keys of the active space, the current one first. The records encrypted with
+/// the replaced keys are still readable until they are encrypted again with the current one
+pub fn group_keys() -> Result<Vec<GroupKey>> {
+    space_keys(&SpaceDao::active_id()?)
+}
+
+pub fn space_keys(space_id: &str) -> Result<Vec<GroupKey>> {
+    if let Some(keys) = GROUP_KEYS.read().as_ref().and_then(|m| m.get(space_id)) {
+        return Ok(keys.clone());
+    }
+
+    let mut cached = GROUP_KEYS.write();
+    let cached = cached.get_or_insert_with(HashMap::new);
+    if let Some(keys) = cached.get(space_id) {
+        return Ok(keys.clone());
+    }
+    let keys_path = keys_path(space_id)?;
+
+    // Read group keys from file or generate a new one
+    let keys = if Path::exists(&keys_path) {
+        let keys = read_to_string(&keys_path)?;
+        debug!("Load group key of space {} from file success!", space_id);
+        keys.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| decode(line.trim()))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+    let keys = if keys.is_empty() {
+        let keys = vec![crypto::generate_key()];
+        write_keys(&keys_path, &keys)?;
+        debug!("Generate new group key of space {} success!", space_id);
+        keys
+    } else {
+        keys
+    };
+    cached.insert(space_id.to_string(), keys.clone());
+    Ok(keys)
+}
+
+/// Replace the key of the space with the one received from a paired device, the replaced
+/// key is kept to read the records written before
+pub fn set_space_key(space_id: &str, key: GroupKey) -> Result<()> {
+    let mut keys = space_keys(space_id)?;
+    keys.retain(|k| k.ne(&key));
+    keys.insert(0, key);
+
+    let mut cached = GROUP_KEYS.write();
+    write_keys(&keys_path(space_id)?, &keys)?;
+    cached
+        .get_or_insert_with(HashMap::new)
+        .insert(space_id.to_string(), keys);
+    Ok(())
+}
+
+/// Forget the keys of the removed space, its records can not be read any more
+pub fn remove_space_keys(space_id: &str) -> Result<()> {
+    let mut cached = GROUP_KEYS.write();
+    let keys_path = keys_path(space_id)?;
+    if keys_path.exists() {
+        std::fs::remove_file(keys_path)?;
+    }
+    if let Some(cached) = cached.as_mut() {
+        cached.remove(space_id);
+    }
+    Ok(())
+}
+
+pub fn encode(key: &GroupKey) -> String {
+    hex::encode(key)
+}
+
+pub fn decode(key: &str) -> Result<GroupKey> {
+    hex::decode(key)?
+        .try_into()
+        .map_err(|_| anyhow!("group key must be 32 bytes"))
+}
+
+/// The default space keeps the group key file written before spaces
+fn keys_path(space_id: &str) -> Result<PathBuf> {
+    check_space_id(space_id)?;
+    if space_id.eq(DEFAULT_SPACE_ID) {
+        return group_key_path();
+    }
+    Ok(space_keys_dir()?.join(format!("{}.txt", space_id)))
+}
+
+/// Only the user can read the keys. A new file is written and renamed, a crash while
+/// writing never leaves the keys half written
+fn write_keys(path: &Path, keys: &[GroupKey]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp_path)?;
+    for key in keys {
+        writeln!(&mut file, "{}", encode(key))?;
+    }
+    file.sync_all()?;
+    rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::p2panda::group_key::{decode, encode, write_keys};
+    use crate::utils::crypto;
+
+    #[test]
+    fn test_encode_decode() {
+        let key = crypto::generate_key();
+        assert_eq!(decode(&encode(&key)).unwrap(), key);
+        assert!(decode("abcd").is_err());
+        assert!(decode("not hex").is_err());
+    }
+
+    #[test]
+    fn test_write_keys() {
+        let path = std::env::temp_dir().join(format!("group_key_{}.txt", std::process::id()));
+        std::fs::write(&path, "old").unwrap();
+        let keys = vec![crypto::generate_key(), crypto::generate_key()];
+        write_keys(&path, &keys).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines, vec![encode(&keys[0]), encode(&keys[1])]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,10 @@
 use std::fs::{read_to_string, File};
 use std::io::Write;
 use std::path::Path;
+use std::sync::OnceLock;
 
 use anyhow::Result;
+use libp2p_identity::{ed25519, PeerId, PublicKey};
 use log::debug;
 use p2panda_rs::identity::KeyPair;
 
@@ -28,3 +30,21 @@ pub fn get_key_pair() -> Result<KeyPair> {
     // Derive key pair from private key
     Ok(KeyPair::from_private_key_str(&private_key)?)
 }
+
+/// Public key of the node, used as the id of this device
+pub fn device_id() -> Result<String> {
+    static DEVICE_ID: OnceLock<String> = OnceLock::new();
+
+    if let Some(device_id) = DEVICE_ID.get() {
+        return Ok(device_id.clone());
+    }
+    let device_id = get_key_pair()?.public_key().to_string();
+    Ok(DEVICE_ID.get_or_init(|| device_id).clone())
+}
+
+/// Peer id of the device in the network, the node derives its own from the key pair the
+/// same way
+pub fn peer_id(device_id: &str) -> Result<PeerId> {
+    let public_key = ed25519::PublicKey::try_from_bytes(&hex::decode(device_id)?)?;
+    Ok(PublicKey::from(public_key).to_peer_id())
+}
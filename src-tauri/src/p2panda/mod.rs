@@ -1,4 +1,8 @@
 pub mod graphql;
-mod key_pair;
+pub mod group_key;
+pub mod key_pair;
 pub mod models;
 pub mod node;
+pub mod node_store;
+pub mod schema_registry;
+pub mod space_schema;
@@ -1,22 +1,72 @@
 use anyhow::{bail, Result};
-use aquadoggo::{Configuration, NetworkConfiguration, Node};
-use log::{debug, info};
+use aquadoggo::{AllowList, Configuration, NetworkConfiguration, Node};
+use log::{debug, error, info};
 use p2panda_rs::entry::decode::decode_entry;
 use p2panda_rs::entry::traits::AsEntry;
+use tokio::sync::Mutex;
 
 use crate::config::app_config::AppConfig;
 use crate::config::configure::{DEFAULT_GRAPHQL_PORT, DEFAULT_SYNC_PORT};
 use crate::consts::SQLITE_FILE;
+use crate::dao::paired_device_dao::PairedDeviceDao;
+use crate::dao::space_dao::SpaceDao;
+use crate::models::space::DEFAULT_SPACE_ID;
 use crate::p2panda::graphql::GraphQLHandler;
-use crate::p2panda::key_pair::get_key_pair;
+use crate::p2panda::key_pair::{get_key_pair, peer_id};
 use crate::p2panda::models::LockFile;
+use crate::p2panda::space_schema::{create_schemas, supported_schema_ids};
 use crate::utils::dir::app_data_dir;
 
 pub struct NodeServer;
 
+// The running node, it is started again when the paired devices or the spaces change
+static NODE: Mutex<Option<Node>> = Mutex::const_new(None);
+
 impl NodeServer {
-    /// Start the p2panda node server
-    pub async fn start() -> Result<Node> {
+    /// Start the p2panda node server, the default space gets its schemas on the first start
+    pub async fn start() -> Result<()> {
+        let mut node = NODE.lock().await;
+        if node.is_some() {
+            bail!("node server already started");
+        }
+        *node = Some(Self::start_node().await?);
+
+        // Step 4: Publish the schemas of the default space, its records are in the schemas
+        // of the lock file until then and are moved by the maintenance task. The devices
+        // paired in the default space before pair again to share its schemas
+        if SpaceDao::get(DEFAULT_SPACE_ID)?.schemas.is_none() {
+            match create_schemas().await {
+                Ok(schemas) => {
+                    SpaceDao::replace_schemas(DEFAULT_SPACE_ID, schemas)?;
+                    if let Some(running) = node.take() {
+                        running.shutdown().await;
+                    }
+                    *node = Some(Self::start_node().await?);
+                }
+                Err(e) => error!("create the schemas of the default space err: {:?}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// The node reads the paired devices and the schemas of the spaces when it starts, it
+    /// is restarted after they changed
+    pub async fn restart() -> Result<()> {
+        // On a task of its own, the caller being cancelled does not leave the node stopped
+        tauri::async_runtime::spawn(Self::restart_node()).await?
+    }
+
+    async fn restart_node() -> Result<()> {
+        let mut node = NODE.lock().await;
+        if let Some(running) = node.take() {
+            running.shutdown().await;
+        }
+        *node = Some(Self::start_node().await?);
+        info!("Node server restarted");
+        Ok(())
+    }
+
+    async fn start_node() -> Result<Node> {
         // Step 1: Load the configuration
         let graphql_port;
         let sync_port;
@@ -31,12 +81,30 @@ impl NodeServer {
             );
         }
 
+        // Only the paired devices replicate with the node. The node replicates every schema
+        // it supports with every paired device supporting it too, and a device supports the
+        // schemas of the spaces it is paired in, so the records of a space only reach the
+        // members of the space. The definitions of the space schemas are replicated with all
+        // the paired devices, they hold no records
+        let allow_peer_ids = PairedDeviceDao::list()?
+            .iter()
+            .map(|device| peer_id(&device.device_id))
+            .collect::<Result<Vec<_>>>()?;
+        let allow_schema_ids = supported_schema_ids(&SpaceDao::list()?)?;
+        info!(
+            "Allow {} paired devices and {} schemas to sync",
+            allow_peer_ids.len(),
+            allow_schema_ids.len()
+        );
+
         let config = Configuration {
+            allow_schema_ids: AllowList::Set(allow_schema_ids),
             database_url: database_uri,
             http_port: graphql_port,
             network: NetworkConfiguration {
                 quic_port: sync_port,
                 mdns: true,
+                allow_peer_ids: AllowList::Set(allow_peer_ids),
                 ..Default::default()
             },
             ..Default::default()
@@ -57,8 +125,11 @@ impl NodeServer {
         Ok(node)
     }
 
+    /// Publish the schema commits of the lock file which the node does not know yet, e.g.
+    /// the record schema v2. The records of the old versions are copied afterwards by
+    /// `RecordMigrationDao` in the maintenance task
     async fn migration() -> Result<()> {
-        let mut cli = GraphQLHandler::global().lock().await;
+        let cli = GraphQLHandler::global()?;
 
         let data = include_str!("../../schema/schema.lock");
         let lock_file: LockFile = toml::from_str(data)?;
@@ -73,6 +144,9 @@ impl NodeServer {
             let pub_key = entry.public_key();
             let log_id = entry.log_id();
             let seq_num = entry.seq_num();
+            // The commits are signed by the schema key pair, its logs are checked and
+            // published without other publishes in between
+            let _guard = cli.lock_publish(pub_key).await;
 
             // Check if node already knows about this entry
             let next_args_res = cli
@@ -116,7 +190,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_build() {
-        let _node = NodeServer::start().await.unwrap();
-        // _node.on_exit().await;
+        NodeServer::start().await.unwrap();
+        NodeServer::restart().await.unwrap();
     }
 }
@@ -0,0 +1,198 @@
+//!
+//! Reads of the documents straight from the database of the embedded node, in this process.
+//! The content of a record is read from its materialized view here, so the large images are
+//! not sent over HTTP nor parsed from the JSON of a GraphQL response. aquadoggo 0.5 keeps
+//! publishing private to the node, the entries are still published through the GraphQL API
+//! so the node validates, materializes and replicates them.
+//! The tables are private to aquadoggo as well, the callers fall back to the GraphQL API
+//! when a read fails, e.g. after the layout changed in an upgrade
+//!
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use log::info;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+
+use crate::consts::SQLITE_FILE;
+use crate::utils::dir::app_data_dir;
+
+/// Fields of a materialized document view
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentView {
+    pub view_id: String,
+    pub document_id: String,
+    // public key of the device which created the document
+    pub owner: String,
+    pub schema_id: String,
+    // field name -> value, the integers are kept as text like in the node
+    pub fields: HashMap<String, String>,
+}
+
+impl DocumentView {
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn integer(&self, name: &str) -> Result<Option<i64>> {
+        self.fields
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("field {} is not an integer: {}", name, value))
+            })
+            .transpose()
+    }
+}
+
+pub struct NodeStore {
+    pool: SqlitePool,
+}
+
+impl NodeStore {
+    // The node writes the database, the reads of the app share a few connections
+    const MAX_CONNECTIONS: u32 = 4;
+
+    // The fields of the view with the document and its owner, the one who published the
+    // create operation, whose id is the document id. Deleted documents have no view
+    const VIEW_SQL: &'static str = "
+        SELECT
+            operations_v1.document_id,
+            create_operations.public_key AS owner,
+            document_views.schema_id,
+            operation_fields_v1.name,
+            operation_fields_v1.value
+        FROM document_views
+        JOIN document_view_fields
+            ON document_view_fields.document_view_id = document_views.document_view_id
+        JOIN operation_fields_v1
+            ON operation_fields_v1.operation_id = document_view_fields.operation_id
+            AND operation_fields_v1.name = document_view_fields.name
+        JOIN operations_v1
+            ON operations_v1.operation_id = document_view_fields.operation_id
+        JOIN operations_v1 AS create_operations
+            ON create_operations.operation_id = operations_v1.document_id
+        JOIN documents
+            ON documents.document_id = operations_v1.document_id
+        WHERE document_views.document_view_id = $1
+            AND NOT documents.is_deleted
+        ORDER BY operation_fields_v1.list_index ASC
+    ";
+
+    // init global, the connections are opened by the first read
+    pub fn global() -> Result<&'static NodeStore> {
+        static STORE: OnceLock<NodeStore> = OnceLock::new();
+
+        if let Some(store) = STORE.get() {
+            return Ok(store);
+        }
+        let store = NodeStore::new()?;
+        Ok(STORE.get_or_init(|| store))
+    }
+
+    fn new() -> Result<Self> {
+        let path = app_data_dir()?.join(SQLITE_FILE);
+        // Only the node writes, it stays the one validating the entries
+        let options = SqliteConnectOptions::new().filename(&path).read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(Self::MAX_CONNECTIONS)
+            .connect_lazy_with(options);
+
+        info!("Init node store success, database: {}", path.display());
+        Ok(Self { pool })
+    }
+
+    /// The view of the document, none when the node has not materialized it or the document
+    /// is deleted
+    pub async fn view(&self, view_id: &str) -> Result<Option<DocumentView>> {
+        let rows = sqlx::query(Self::VIEW_SQL)
+            .bind(view_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut view: Option<DocumentView> = None;
+        for row in rows {
+            let view = view.get_or_insert(DocumentView {
+                view_id: view_id.to_string(),
+                document_id: row.try_get("document_id")?,
+                owner: row.try_get("owner")?,
+                schema_id: row.try_get("schema_id")?,
+                fields: HashMap::new(),
+            });
+            // The value of an empty list is null, the records have no lists
+            if let Some(value) = row.try_get::<Option<String>, _>("value")? {
+                view.fields.insert(row.try_get("name")?, value);
+            }
+        }
+        Ok(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use crate::p2panda::node_store::NodeStore;
+
+    // The tables of aquadoggo 0.5 with the columns read by the node store
+    const TABLES: [&str; 5] = [
+        "CREATE TABLE operations_v1 (public_key TEXT, document_id TEXT, operation_id TEXT)",
+        "CREATE TABLE operation_fields_v1 (operation_id TEXT, name TEXT, field_type TEXT, \
+            value TEXT NULL, list_index NUMERIC)",
+        "CREATE TABLE documents (document_id TEXT, document_view_id TEXT, schema_id TEXT, \
+            is_deleted BOOLEAN)",
+        "CREATE TABLE document_views (document_view_id TEXT, schema_id TEXT)",
+        "CREATE TABLE document_view_fields (document_view_id TEXT, operation_id TEXT, \
+            name TEXT)",
+    ];
+
+    const ROWS: [&str; 10] = [
+        // created by owner, the content is updated by peer
+        "INSERT INTO operations_v1 VALUES ('owner', 'doc', 'doc'), ('peer', 'doc', 'update')",
+        "INSERT INTO operation_fields_v1 VALUES \
+            ('doc', 'content', 'str', 'old', 0), \
+            ('doc', 'create_time', 'int', '42', 0), \
+            ('update', 'content', 'str', 'new', 0)",
+        "INSERT INTO documents VALUES ('doc', 'update', 'record_v3', false)",
+        "INSERT INTO document_views VALUES ('update', 'record_v3'), ('doc', 'record_v3')",
+        "INSERT INTO document_view_fields VALUES \
+            ('update', 'update', 'content'), ('update', 'doc', 'create_time'), \
+            ('doc', 'doc', 'content'), ('doc', 'doc', 'create_time')",
+        // deleted
+        "INSERT INTO operations_v1 VALUES ('owner', 'gone', 'gone')",
+        "INSERT INTO operation_fields_v1 VALUES ('gone', 'content', 'str', 'gone', 0)",
+        "INSERT INTO documents VALUES ('gone', 'gone', 'record_v3', true)",
+        "INSERT INTO document_views VALUES ('gone', 'record_v3')",
+        "INSERT INTO document_view_fields VALUES ('gone', 'gone', 'content')",
+    ];
+
+    #[tokio::test]
+    async fn test_view() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for sql in TABLES.iter().chain(ROWS.iter()) {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        let store = NodeStore { pool };
+
+        let view = store.view("update").await.unwrap().unwrap();
+        assert_eq!(view.document_id, "doc");
+        assert_eq!(view.owner, "owner");
+        assert_eq!(view.schema_id, "record_v3");
+        assert_eq!(view.text("content").unwrap(), "new");
+        assert_eq!(view.integer("create_time").unwrap(), Some(42));
+        assert_eq!(view.integer("is_deleted").unwrap(), None);
+
+        // The views before the update are kept
+        let view = store.view("doc").await.unwrap().unwrap();
+        assert_eq!(view.text("content").unwrap(), "old");
+
+        assert!(store.view("gone").await.unwrap().is_none());
+        assert!(store.view("unknown").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{anyhow, Result};
+use p2panda_rs::schema::SchemaId;
+
+// `SCHEMA_IDS`: (logical name, version, schema id) of all the versions of the application
+// schemas, `SCHEMA_FIELDS`: (schema id, view ids of the field definitions), derived from
+// `schema/schema.lock` by build.rs
+include!(concat!(env!("OUT_DIR"), "/schema_registry.rs"));
+
+/// Schema id of the latest version of the schema named `name` in the lock file
+pub fn schema_id(name: &str) -> Result<SchemaId> {
+    schema_id_version(name, latest_version(name)?)
+}
+
+/// Schema id of the given version, older versions are still used by the devices which are
+/// not upgraded yet
+pub fn schema_id_version(name: &str, version: u32) -> Result<SchemaId> {
+    let id = SCHEMA_IDS
+        .iter()
+        .find(|(schema_name, schema_version, _)| {
+            schema_name.eq(&name) && *schema_version == version
+        })
+        .map(|(_, _, id)| *id)
+        .ok_or(anyhow!(
+            "schema {} v{} not found in schema.lock",
+            name,
+            version
+        ))?;
+    Ok(SchemaId::new(id)?)
+}
+
+/// View ids of the field definitions of the schema, a space schema pins the same ones
+pub fn schema_fields(schema_id: &SchemaId) -> Result<&'static [&'static str]> {
+    let id = schema_id.to_string();
+    SCHEMA_FIELDS
+        .iter()
+        .find(|(schema_id, _)| schema_id.eq(&id))
+        .map(|(_, fields)| *fields)
+        .ok_or(anyhow!("schema {} not found in schema.lock", id))
+}
+
+pub fn latest_version(name: &str) -> Result<u32> {
+    SCHEMA_IDS
+        .iter()
+        .filter(|(schema_name, _, _)| schema_name.eq(&name))
+        .map(|(_, version, _)| *version)
+        .max()
+        .ok_or(anyhow!("schema {} not found in schema.lock", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{record, register, snippet};
+    use crate::p2panda::schema_registry::{
+        latest_version, schema_fields, schema_id, schema_id_version,
+    };
+
+    #[test]
+    fn test_schema_id() {
+        for name in [
+            record::SCHEMA_NAME,
+            record::VERSION_SCHEMA_NAME,
+            snippet::SCHEMA_NAME,
+            register::SCHEMA_NAME,
+        ] {
+            let id = schema_id(name).unwrap();
+            assert!(id.to_string().starts_with(&format!("{}_0020", name)));
+        }
+        assert!(schema_id("unknown").is_err());
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        assert_eq!(latest_version(record::SCHEMA_NAME).unwrap(), 3);
+        assert_eq!(latest_version(snippet::SCHEMA_NAME).unwrap(), 1);
+
+        let v1 = schema_id_version(record::SCHEMA_NAME, 1).unwrap();
+        let v2 = schema_id_version(record::SCHEMA_NAME, 2).unwrap();
+        let v3 = schema_id_version(record::SCHEMA_NAME, 3).unwrap();
+        assert_ne!(v1, v2);
+        assert_ne!(v2, v3);
+        assert_eq!(schema_id(record::SCHEMA_NAME).unwrap(), v3);
+        assert!(schema_id_version(record::SCHEMA_NAME, 4).is_err());
+
+        // v3 added fields to v1
+        let v1_fields = schema_fields(&v1).unwrap();
+        let v3_fields = schema_fields(&v3).unwrap();
+        assert!(!v1_fields.is_empty());
+        assert!(v3_fields.len() > v1_fields.len());
+    }
+}
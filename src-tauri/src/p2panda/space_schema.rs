@@ -0,0 +1,176 @@
+//!
+//! Schemas of the spaces. Each space has its own record and record version schemas, which
+//! pin the field definitions of the latest versions in the lock file. The node only
+//! replicates a schema with the peers supporting it, and a device supports the schemas of
+//! the spaces it is paired in, so the records of a space only reach its members
+//!
+use std::str::FromStr;
+
+use anyhow::Result;
+use log::info;
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::{
+    OperationAction, OperationBuilder, OperationValue, PinnedRelationList,
+};
+use p2panda_rs::schema::SchemaId;
+
+use crate::models::record;
+use crate::models::space::{SpaceList, SpaceSchemas};
+use crate::p2panda::graphql::GraphQLHandler;
+use crate::p2panda::schema_registry::{schema_fields, schema_id, SCHEMA_IDS};
+
+const SCHEMA_DEFINITION: &str = "schema_definition_v1";
+const SCHEMA_FIELD_DEFINITION: &str = "schema_field_definition_v1";
+
+/// Publish the schemas of a space, the node supports them once it is restarted
+pub async fn create_schemas() -> Result<SpaceSchemas> {
+    let record = create_schema(record::SCHEMA_NAME, "Clipboard records of a space").await?;
+    let record_version = create_schema(
+        record::VERSION_SCHEMA_NAME,
+        "Contents replaced by the edits of the records of a space",
+    )
+    .await?;
+    Ok(SpaceSchemas {
+        record: record.to_string(),
+        record_version: record_version.to_string(),
+    })
+}
+
+/// The schema has the fields of the latest version in the lock file, so the queries
+/// generated for that version work with it
+async fn create_schema(name: &str, description: &str) -> Result<SchemaId> {
+    let fields = schema_fields(&schema_id(name)?)?
+        .iter()
+        .map(|view_id| DocumentViewId::from_str(view_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let opt = OperationBuilder::new(&SchemaId::new(SCHEMA_DEFINITION)?)
+        .action(OperationAction::Create)
+        .fields(&[
+            ("name", OperationValue::String(name.to_string())),
+            (
+                "description",
+                OperationValue::String(description.to_string()),
+            ),
+            (
+                "fields",
+                OperationValue::PinnedRelationList(PinnedRelationList::new(fields)),
+            ),
+        ])
+        .build()?;
+
+    let view_id = GraphQLHandler::global()?
+        .send_to_node(PlainOperation::from(&opt))
+        .await?;
+    let schema_id = SchemaId::new(&format!("{}_{}", name, view_id))?;
+    info!("create space schema: {}", schema_id);
+    Ok(schema_id)
+}
+
+/// Record and record version schema ids of the space, the ones of the lock file when the
+/// space has no schemas yet
+pub fn schema_ids(schemas: Option<&SpaceSchemas>) -> Result<(SchemaId, SchemaId)> {
+    match schemas {
+        Some(schemas) => Ok((
+            SchemaId::new(&schemas.record)?,
+            SchemaId::new(&schemas.record_version)?,
+        )),
+        None => Ok((
+            schema_id(record::SCHEMA_NAME)?,
+            schema_id(record::VERSION_SCHEMA_NAME)?,
+        )),
+    }
+}
+
+/// The query documents are generated for the schemas of the lock file, the schema ids of
+/// the space are filled in instead
+pub fn space_query(query: &str, record: &SchemaId, record_version: &SchemaId) -> Result<String> {
+    Ok(query
+        .replace(
+            &schema_id(record::SCHEMA_NAME)?.to_string(),
+            &record.to_string(),
+        )
+        .replace(
+            &schema_id(record::VERSION_SCHEMA_NAME)?.to_string(),
+            &record_version.to_string(),
+        ))
+}
+
+/// Schemas the node supports: the system schemas, which bring the definitions of the space
+/// schemas along, the schemas of the lock file and the schemas of the spaces of this device
+pub fn supported_schema_ids(spaces: &SpaceList) -> Result<Vec<SchemaId>> {
+    let mut ids = vec![
+        SchemaId::new(SCHEMA_DEFINITION)?,
+        SchemaId::new(SCHEMA_FIELD_DEFINITION)?,
+    ];
+    for (_, _, id) in SCHEMA_IDS {
+        ids.push(SchemaId::new(id)?);
+    }
+    for schemas in spaces.schemas() {
+        ids.push(SchemaId::new(&schemas.record)?);
+        ids.push(SchemaId::new(&schemas.record_version)?);
+    }
+    let mut supported: Vec<SchemaId> = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !supported.contains(&id) {
+            supported.push(id);
+        }
+    }
+    Ok(supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphql::record_list;
+    use crate::models::record;
+    use crate::models::space::{SpaceList, SpaceSchemas, DEFAULT_SPACE_ID};
+    use crate::p2panda::schema_registry::schema_id;
+    use crate::p2panda::space_schema::{schema_ids, space_query, supported_schema_ids};
+
+    const RECORD: &str =
+        "record_0020c933b2cc15417f5541a856dd4b1e752839e6d6776a3c2a9165a2e9e1bea51bb9";
+    const RECORD_VERSION: &str =
+        "record_version_00207e2aca82f1d8a86b852bc627e103c79d3f8eb4b622350754d508c2b75178a386";
+
+    fn schemas() -> SpaceSchemas {
+        SpaceSchemas {
+            record: RECORD.to_string(),
+            record_version: RECORD_VERSION.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_space_query() {
+        let lock_record = schema_id(record::SCHEMA_NAME).unwrap().to_string();
+        assert!(record_list::QUERY.contains(&lock_record));
+
+        let (record, record_version) = schema_ids(Some(&schemas())).unwrap();
+        let query = space_query(record_list::QUERY, &record, &record_version).unwrap();
+        assert!(!query.contains(&lock_record));
+        assert!(query.contains(&format!("all_{}(", RECORD)));
+        assert!(query.contains(&format!("{}Filter", RECORD)));
+
+        // The space without schemas queries the lock file ones
+        let (record, record_version) = schema_ids(None).unwrap();
+        let query = space_query(record_list::QUERY, &record, &record_version).unwrap();
+        assert_eq!(query, record_list::QUERY);
+    }
+
+    #[test]
+    fn test_supported_schema_ids() {
+        let mut spaces = SpaceList::default();
+        let lock_only = supported_schema_ids(&spaces).unwrap();
+        assert!(lock_only.contains(&schema_id(record::SCHEMA_NAME).unwrap()));
+
+        spaces.replace_schemas(DEFAULT_SPACE_ID, schemas()).unwrap();
+        let ids: Vec<String> = supported_schema_ids(&spaces)
+            .unwrap()
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(ids.len(), lock_only.len() + 2);
+        assert!(ids.contains(&"schema_definition_v1".to_string()));
+        assert!(ids.contains(&RECORD.to_string()));
+        assert!(ids.contains(&RECORD_VERSION.to_string()));
+    }
+}
@@ -7,6 +7,11 @@ use crate::utils::image::base64_to_rgba8;
 pub struct ClipBoardOperator;
 
 impl ClipBoardOperator {
+    pub fn get_text() -> Result<String> {
+        let mut clipboard = Clipboard::new()?;
+        Ok(clipboard.get_text()?)
+    }
+
     pub fn set_text(text: String) -> Result<()> {
         let mut clipboard = Clipboard::new()?;
         clipboard.set_text(text)?;
@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::utils::string;
+
+pub type GroupKey = [u8; 32];
+
+// Encrypted values start with it, the values without it were written in cleartext by the
+// versions before the encryption
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+const NONCE_LEN: usize = 24;
+
+pub fn generate_key() -> GroupKey {
+    XChaCha20Poly1305::generate_key(&mut OsRng).into()
+}
+
+/// Encrypt with XChaCha20-Poly1305, the random nonce is stored before the ciphertext
+pub fn encrypt(key: &GroupKey, plaintext: &str) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| anyhow!("encrypt failed: {}", e))?;
+
+    let mut bytes = nonce.to_vec();
+    bytes.extend(ciphertext);
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_PREFIX,
+        string::base64_encode(&bytes)
+    ))
+}
+
+/// Decrypt the value written by `encrypt`, cleartext values are returned as they are
+pub fn decrypt(key: &GroupKey, value: &str) -> Result<String> {
+    let encoded = match value.strip_prefix(ENCRYPTED_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Ok(value.to_string()),
+    };
+    let bytes = general_purpose::STANDARD.decode(encoded)?;
+    if bytes.len() < NONCE_LEN {
+        return Err(anyhow!("encrypted value too short"));
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        // Written by a device which does not share the group key
+        .map_err(|e| anyhow!("decrypt failed: {}", e))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Try the keys in order, e.g. the current group key and the replaced ones
+pub fn decrypt_with_any(keys: &[GroupKey], value: &str) -> Result<String> {
+    let mut last_err = anyhow!("no key to decrypt");
+    for key in keys {
+        match decrypt(key, value) {
+            Ok(plaintext) => return Ok(plaintext),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// BLAKE3 hash keyed with the group key. The devices sharing the key get the same hash for
+/// the same content, the other peers can not guess the content from it
+pub fn keyed_content_hash(key: &GroupKey, content: &str) -> String {
+    blake3::keyed_hash(key, content.as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::crypto::{
+        decrypt, decrypt_with_any, encrypt, generate_key, is_encrypted, keyed_content_hash,
+    };
+    use crate::utils::string;
+
+    #[test]
+    fn test_encrypt() {
+        let key = generate_key();
+        let encrypted = encrypt(&key, "secret").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("secret"));
+        // A random nonce each time
+        assert_ne!(encrypted, encrypt(&key, "secret").unwrap());
+        assert_eq!(decrypt(&key, &encrypted).unwrap(), "secret");
+
+        assert!(decrypt(&generate_key(), &encrypted).is_err());
+        assert!(decrypt(&key, "enc:v1:AAAA").is_err());
+        assert_eq!(decrypt(&key, "cleartext").unwrap(), "cleartext");
+
+        let replaced = generate_key();
+        let encrypted = encrypt(&replaced, "old").unwrap();
+        assert_eq!(
+            decrypt_with_any(&[key, replaced], &encrypted).unwrap(),
+            "old"
+        );
+        assert!(decrypt_with_any(&[key], &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_keyed_content_hash() {
+        let key = generate_key();
+        assert_eq!(
+            keyed_content_hash(&key, "hello"),
+            keyed_content_hash(&key, "hello")
+        );
+        assert_ne!(
+            keyed_content_hash(&key, "hello"),
+            string::content_hash("hello")
+        );
+        assert_ne!(
+            keyed_content_hash(&key, "hello"),
+            keyed_content_hash(&generate_key(), "hello")
+        );
+    }
+}
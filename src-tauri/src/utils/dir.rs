@@ -8,6 +8,8 @@ const CONFIG_FILE: &str = "config.json";
 
 const SECRET_FILE: &str = "secret.txt";
 
+const GROUP_KEY_FILE: &str = "group_key.txt";
+
 /// get the app home dir
 pub fn app_home_dir() -> Result<PathBuf> {
     #[cfg(target_os = "windows")]
@@ -44,12 +46,36 @@ pub fn secret_path() -> Result<PathBuf> {
     Ok(app_home_dir()?.join(SECRET_FILE))
 }
 
+pub fn group_key_path() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join(GROUP_KEY_FILE))
+}
+
+/// Group keys of the spaces other than the default one
+pub fn space_keys_dir() -> Result<PathBuf> {
+    let space_keys_dir = app_home_dir()?.join("space_keys");
+    fs::create_dir_all(&space_keys_dir)?;
+    Ok(space_keys_dir)
+}
+
 pub fn app_data_dir() -> Result<PathBuf> {
     let app_data_dir = app_home_dir()?.join("data");
     fs::create_dir_all(&app_data_dir)?;
     Ok(app_data_dir)
 }
 
+pub fn app_data_history_dir() -> Result<PathBuf> {
+    let app_data_history_dir = app_data_dir()?.join("history");
+    fs::create_dir_all(&app_data_history_dir)?;
+    Ok(app_data_history_dir)
+}
+
+/// Records kept on this device only, see `SelectiveRecordStore`
+pub fn app_data_local_records_dir() -> Result<PathBuf> {
+    let app_data_local_records_dir = app_data_dir()?.join("local_records");
+    fs::create_dir_all(&app_data_local_records_dir)?;
+    Ok(app_data_local_records_dir)
+}
+
 pub fn app_data_img_dir() -> Result<PathBuf> {
     let app_data_img_dir = app_data_dir()?.join("img");
     fs::create_dir_all(&app_data_img_dir)?;
@@ -1,7 +1,7 @@
+use std::io::Write;
 use std::{fs, path::PathBuf};
 
 use anyhow::{bail, Context, Result};
-use log::debug;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
 
@@ -27,9 +27,17 @@ pub fn read<T: DeserializeOwned>(path: &PathBuf) -> Result<T> {
     ))
 }
 
+/// Write a temp file and rename it, a crash while writing never leaves the file half written
 pub fn save<T: Serialize>(path: &PathBuf, data: &T) -> Result<()> {
     let data_str = serde_json::to_string(data)?;
-    debug!("data_str: {}", data_str);
     let path_str = path.as_os_str().to_string_lossy().to_string();
-    fs::write(path, data_str.as_bytes()).context(format!("failed to save file \"{path_str}\""))
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    let mut file =
+        fs::File::create(&tmp_path).context(format!("failed to save file \"{path_str}\""))?;
+    file.write_all(data_str.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path).context(format!("failed to save file \"{path_str}\""))
 }
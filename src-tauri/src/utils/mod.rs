@@ -1,6 +1,8 @@
 pub mod clipboard;
+pub mod crypto;
 pub mod dir;
 pub mod image;
 pub mod ip;
 pub mod json;
+pub mod source_app;
 pub mod string;
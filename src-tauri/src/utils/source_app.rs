@@ -0,0 +1,101 @@
+//!
+//! Name of the application in front, taken as the source of a new copy as the clipboard does
+//! not tell which application wrote it. It is empty when it can not be found, e.g. on
+//! Wayland or without `xdotool` on Linux
+//!
+use log::debug;
+
+pub fn frontmost_app() -> String {
+    match frontmost() {
+        Some(app) => app.trim().to_string(),
+        None => {
+            debug!("frontmost app is unknown");
+            String::new()
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn frontmost() -> Option<String> {
+    // lsappinfo needs no automation permission, unlike asking System Events
+    let asn = command_output("lsappinfo", &["front"])?;
+    let info = command_output("lsappinfo", &["info", "-only", "name", asn.trim()])?;
+    // "LSDisplayName"="Safari"
+    info.split_once('=')
+        .map(|(_, name)| name.trim().trim_matches('"').to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn frontmost() -> Option<String> {
+    use std::path::Path;
+
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, BOOL};
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid as *mut u32)) };
+    if pid == 0 {
+        return None;
+    }
+
+    let mut path = [0u16; 1024];
+    let mut len = path.len() as u32;
+    let found = unsafe {
+        let process =
+            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, BOOL::from(false), pid).ok()?;
+        let found = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(process);
+        found.as_bool()
+    };
+    if !found {
+        return None;
+    }
+    // C:\Program Files\Slack\slack.exe => slack
+    let path = String::from_utf16_lossy(&path[..len as usize]);
+    Path::new(&path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn frontmost() -> Option<String> {
+    let pid = command_output("xdotool", &["getactivewindow", "getwindowpid"])?;
+    std::fs::read_to_string(format!("/proc/{}/comm", pid.trim())).ok()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn frontmost() -> Option<String> {
+    None
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::source_app::frontmost_app;
+
+    #[test]
+    fn test_frontmost_app() {
+        println!("The frontmost app is: {:?}", frontmost_app());
+    }
+}
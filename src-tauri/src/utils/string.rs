@@ -15,6 +15,16 @@ pub fn md5_by_bytes(bytes: &[u8]) -> String {
     hasher.result_str()
 }
 
+/// BLAKE3 hash of the content, it identifies the record content instead of the md5, whose
+/// collisions are easy to craft
+pub fn content_hash(s: &str) -> String {
+    content_hash_by_bytes(s.as_bytes())
+}
+
+pub fn content_hash_by_bytes(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
 pub fn base64_encode(bytes: &[u8]) -> String {
     general_purpose::STANDARD.encode(bytes)
 }
@@ -22,3 +32,17 @@ pub fn base64_encode(bytes: &[u8]) -> String {
 pub fn base64_decode(base64: &str) -> Vec<u8> {
     general_purpose::STANDARD.decode(base64).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::string::content_hash;
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_ne!(content_hash("a"), content_hash("b"));
+    }
+}
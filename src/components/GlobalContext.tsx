@@ -1,4 +1,4 @@
-import {CommandEnum, DefaultGraphqlEndpoint} from "@/utils/consts";
+import {CommandEnum} from "@/utils/consts";
 import React, { createContext, useState, useEffect } from 'react';
 import {invoke} from "@tauri-apps/api";
 
@@ -7,7 +7,7 @@ interface Context {
 }
 
 const GlobalContext = createContext<Context>({
-    graphqlEndpoint: DefaultGraphqlEndpoint
+    graphqlEndpoint: ""
 });
 
 type Props = {
@@ -15,7 +15,7 @@ type Props = {
 };
 
 export const GlobalContextProvider: React.FC<Props> = ({ children }) => {
-    const [graphqlEndpoint, setGraphqlEndpoint] = useState<string>(DefaultGraphqlEndpoint);
+    const [graphqlEndpoint, setGraphqlEndpoint] = useState<string>("");
 
     useEffect(() => {
         async function fetchData() {
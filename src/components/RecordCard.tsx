@@ -3,21 +3,22 @@ import React, {useState} from "react";
 import {Button, Image, message} from "antd";
 import {base64ToImage, parseImageData} from "@/utils/image";
 import {CommandEnum} from "@/utils/consts";
-import {Record, RecordDataTypeEnum} from "@/models/Record";
-import {RecordDocument} from "@/models/RecordDocument";
+import {RecordDataTypeEnum} from "@/models/Record";
+import {RecordItem} from "@/models/RecordPage";
+import {getRecordContent} from "@/utils/records";
 import {invoke} from "@tauri-apps/api";
 import {Tooltip} from 'react-tooltip';
 
 interface RecordCardProps {
-    data: RecordDocument,
+    data: RecordItem,
 }
 
 interface RecordCardPropsLeft {
-    data: RecordDocument,
+    data: RecordItem,
 }
 
 interface RecordCardPropsRight {
-    data: RecordDocument,
+    data: RecordItem,
 }
 
 export default function RecordCard(props: RecordCardProps) {
@@ -31,33 +32,48 @@ export default function RecordCard(props: RecordCardProps) {
 
 function RecordCardLeft(props: RecordCardPropsLeft) {
 
-    const isImage = props.data.fields.data_type === RecordDataTypeEnum.Image;
+    const isImage = props.data.data_type === RecordDataTypeEnum.Image;
+    // Full text is loaded on demand, the list only contains the preview
+    const [fullText, setFullText] = useState<string>();
 
-    let clickCopy = (_: React.MouseEvent<HTMLDivElement>, record: Record) => {
+    let clickCopy = (_: React.MouseEvent<HTMLDivElement>, record: RecordItem) => {
         console.debug(record);
 
-        invoke(CommandEnum.TapChangeClipboardCommand, {
-            content: record.content,
-            dataType: record.data_type
+        getRecordContent(record.view_id).then(res => {
+            return invoke(CommandEnum.TapChangeClipboardCommand, {
+                content: res.content,
+                dataType: res.data_type
+            });
         }).catch(e => {
             message.error(`change clipboard record failed: ${e}`);
         });
     }
 
+    let loadFullText = () => {
+        if (isImage || fullText !== undefined) {
+            return;
+        }
+        getRecordContent(props.data.view_id).then(res => {
+            setFullText(res.content);
+        }).catch(e => {
+            console.error(`load record content failed: ${e}`);
+        });
+    }
+
     return (
-        <div className={"record-card-left"} onClick={(e) => clickCopy(e, props.data.fields)}>
+        <div className={"record-card-left"} onClick={(e) => clickCopy(e, props.data)}>
             {
                 isImage ? (
                     <Image
-                        src={URL.createObjectURL(base64ToImage((parseImageData(props.data.fields.content_preview).base64)))}
+                        src={URL.createObjectURL(base64ToImage((parseImageData(props.data.content_preview ?? "").base64)))}
                         preview={false}/>
                 ) : (
-                    <div className={"record-card-text-container"}>
+                    <div className={"record-card-text-container"} onMouseEnter={loadFullText}>
                         <p
                             data-tooltip-id="record-card-text"
-                            data-tooltip-content={props.data.fields.content}
+                            data-tooltip-content={fullText ?? props.data.content_preview}
                             data-tooltip-place={"bottom"}
-                        >{props.data.fields.content_preview}</p>
+                        >{props.data.content_preview}</p>
                         <Tooltip id={"record-card-text"} className={"record-card-text-tooltip"}/>
                     </div>
                 )
@@ -68,32 +84,26 @@ function RecordCardLeft(props: RecordCardPropsLeft) {
 
 function RecordCardRight(props: RecordCardPropsRight) {
 
-    const [isFavorite, setIsFavorite] = useState(props.data.fields.is_favorite);
+    const [isFavorite, setIsFavorite] = useState(props.data.is_favorite);
 
-    let deleteRecord = (_: React.MouseEvent<HTMLElement>, doc: RecordDocument) => {
-        // Use documentId to delete the entire document!
-        console.debug(`delete record emit view id: ${doc.meta.documentId}`);
+    let deleteRecord = (_: React.MouseEvent<HTMLElement>, doc: RecordItem) => {
+        // Deleted record will be moved to trash with the latest viewId
+        console.debug(`delete record emit view id: ${doc.view_id}`);
 
         invoke(CommandEnum.DeleteRecordCommand, {
-            viewId: doc.meta.documentId
+            viewId: doc.view_id
         }).catch(e => {
             message.error(`delete record failed: ${e}`);
         });
     }
 
-    let toggleFavorite = (_: React.MouseEvent<HTMLElement>, doc: RecordDocument) => {
+    let toggleFavorite = (_: React.MouseEvent<HTMLElement>, doc: RecordItem) => {
 
         invoke(CommandEnum.ToggleFavoriteRecordCommand, {
-            viewId: doc.meta.viewId,
-            oldFavorite: doc.fields.is_favorite,
+            viewId: doc.view_id,
+            oldFavorite: doc.is_favorite ? 1 : 0,
         }).then(() => {
-            setIsFavorite(old_state => {
-                if (old_state === 0) {
-                    return 1;
-                } else {
-                    return 0;
-                }
-            });
+            setIsFavorite(old_state => !old_state);
         }).catch(e => {
             message.error(`toggle record favorite failed: ${e}`);
         });
@@ -1,9 +1,10 @@
 import React, {useEffect, useRef, useState} from 'react';
 import {message} from 'antd';
-import {allFavoriteRecords, getRecordByPage} from "@/utils/graphql";
+import {allFavoriteRecords, getRecordByPage} from "@/utils/records";
 import InfiniteScroll from 'react-infinite-scroll-component';
 import RecordCard from "@/components/RecordCard";
-import {RecordDocument} from "@/models/RecordDocument";
+import {RecordItem} from "@/models/RecordPage";
+import {EvictionProgress} from "@/models/EvictionProgress";
 import {listen, UnlistenFn} from "@tauri-apps/api/event";
 import {EventListenerEnum} from "@/utils/consts";
 import "@/css/RecordList.css";
@@ -18,9 +19,9 @@ export default function RecordList(props: RecordListProps) {
 
     const prevSearchKeywordRef = useRef<string>(props.searchKeyword);
 
-    const [favoriteRecords, setFavoriteRecords] = useState<RecordDocument[]>([]);
+    const [favoriteRecords, setFavoriteRecords] = useState<RecordItem[]>([]);
     const [hasMore, setHasMore] = useState(false);
-    const [records, setRecords] = useState<RecordDocument[]>([]);
+    const [records, setRecords] = useState<RecordItem[]>([]);
     const [endCursor, setEndCursor] = useState<string>("");
 
     useEffect(() => {
@@ -50,6 +51,14 @@ export default function RecordList(props: RecordListProps) {
                 message.error(`handle event: ${EventListenerEnum.DeleteClipboardRecordBackend} err: ${err.message}`);
             });
 
+        evictionProgressListener()
+            .then((ulf) => {
+                unlistenFns.push(ulf);
+            })
+            .catch((err) => {
+                message.error(`handle event: ${EventListenerEnum.EvictionProgressBackend} err: ${err.message}`);
+            });
+
         reloadFavoriteRecords().then(() => {
             reloadRecords().then();
         });
@@ -74,11 +83,11 @@ export default function RecordList(props: RecordListProps) {
 
         try {
             let allFavoriteResp = await allFavoriteRecords();
-            if (!allFavoriteResp || !allFavoriteResp.documents || allFavoriteResp.documents.length <= 0) {
+            if (!allFavoriteResp || !allFavoriteResp.items || allFavoriteResp.items.length <= 0) {
                 console.log("No favorite records!")
             }
-            console.debug(`all ${allFavoriteResp.documents.length} favorite items loaded!`);
-            setFavoriteRecords(allFavoriteResp.documents);
+            console.debug(`all ${allFavoriteResp.items.length} favorite items loaded!`);
+            setFavoriteRecords(allFavoriteResp.items);
         } catch (err) {
             message.error(`load more items failed: ${err}`);
         }
@@ -86,17 +95,17 @@ export default function RecordList(props: RecordListProps) {
 
     const fetchRecords = async () => {
         try {
-            let res = await getRecordByPage(PageSize, endCursor, [0], props.searchKeyword);
+            let res = await getRecordByPage(PageSize, endCursor, props.searchKeyword);
 
-            if (!res || !res.documents || res.documents.length <= 0) {
+            if (!res || !res.items || res.items.length <= 0) {
                 console.log("No records found!")
                 return;
             }
 
-            setHasMore(res.hasNextPage);
-            setRecords(records.concat(res.documents));
-            setEndCursor(res.endCursor);
-            console.log(`${res.documents.length} more items loaded!`);
+            setHasMore(!!res.next_cursor);
+            setRecords(records.concat(res.items));
+            setEndCursor(res.next_cursor ?? "");
+            console.log(`${res.items.length} more items loaded!`);
         } catch (err) {
             message.error(`load more items failed: ${err}`);
         }
@@ -108,16 +117,16 @@ export default function RecordList(props: RecordListProps) {
         setEndCursor("");
 
         try {
-            let res = await getRecordByPage(PageSize, "", [0], props.searchKeyword);
-            if (!res || !res.documents || res.documents.length <= 0) {
+            let res = await getRecordByPage(PageSize, "", props.searchKeyword);
+            if (!res || !res.items || res.items.length <= 0) {
                 console.log("No records found!")
                 return;
             }
 
-            setHasMore(res.hasNextPage);
-            setRecords(res.documents);
-            setEndCursor(res.endCursor);
-            console.log(`${res.documents.length} more items loaded!`);
+            setHasMore(!!res.next_cursor);
+            setRecords(res.items);
+            setEndCursor(res.next_cursor ?? "");
+            console.log(`${res.items.length} more items loaded!`);
         } catch (err) {
             message.error(`load more items failed: ${err}`);
         }
@@ -148,6 +157,27 @@ export default function RecordList(props: RecordListProps) {
         });
     }
 
+    const evictionProgressListener = async (): Promise<UnlistenFn> => {
+        return listen<{ message: EvictionProgress }>(EventListenerEnum.EvictionProgressBackend, async (event) => {
+            const progress = event.payload.message;
+            console.debug(`Got EvictionProgressBackend event: ${JSON.stringify(progress)}`);
+            const handled = progress.trashed + progress.queued + progress.failed.length;
+            if (!progress.finished) {
+                message.loading({key: "eviction", content: `Cleaning records over the limit: ${handled}/${progress.total}`});
+            } else if (progress.failed.length > 0) {
+                console.error(`Failed to clean records: ${JSON.stringify(progress.failed)}`);
+                message.warning({
+                    key: "eviction",
+                    content: `${progress.failed.length} records over the limit failed to move to trash: ${progress.failed[0].error}`,
+                });
+            } else if (progress.queued > 0) {
+                message.info({key: "eviction", content: `Node unreachable, ${progress.trashed} records over the limit moved to trash, the rest later`});
+            } else {
+                message.success({key: "eviction", content: `${progress.trashed} records over the limit moved to trash`});
+            }
+        });
+    }
+
     return (
         <div id={"record-list-container"}>
             favorite: {favoriteRecords.length}, current normal: {records.length}
@@ -160,7 +190,7 @@ export default function RecordList(props: RecordListProps) {
                     dataLength={favoriteRecords.length}>
                     {
                         favoriteRecords.map(item => (
-                            <div key={item.meta.viewId}>
+                            <div key={item.view_id}>
                                 <RecordCard data={item}/>
                             </div>
                         ))
@@ -181,7 +211,7 @@ export default function RecordList(props: RecordListProps) {
                 >
                     {
                         records.map(item => (
-                            <div key={item.meta.viewId}>
+                            <div key={item.view_id}>
                                 <RecordCard data={item}/>
                             </div>
                         ))
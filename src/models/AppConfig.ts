@@ -1,6 +1,32 @@
+export interface RetentionRule {
+    data_type: string,
+    max_age_days: number,
+}
+
+export interface RegisterHotkey {
+    register: string,
+    action: "copy" | "paste",
+    hotkey: string,
+}
+
+export interface SyncPolicy {
+    favorites_only: boolean,
+    max_image_mb: number,
+    local_data_types: string[],
+    local_source_apps: string[],
+}
+
 export interface AppConfig {
     store_limit?: number,
     sync_port?: number,
     graphql_port?: number,
+    pairing_port?: number,
     toggle_window_hotkey?: string,
+    paste_next_hotkey?: string,
+    trash_retention_days?: number,
+    retention_rules?: RetentionRule[],
+    storage_quota_mb?: number,
+    register_hotkeys?: RegisterHotkey[],
+    verify_publish?: boolean,
+    sync_policy?: SyncPolicy,
 }
@@ -0,0 +1,6 @@
+export type BatchItemResult = {
+    view_id: string,
+    success: boolean,
+    new_view_id?: string,
+    error?: string,
+}
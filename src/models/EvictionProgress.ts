@@ -0,0 +1,10 @@
+import {BatchItemResult} from "@/models/BatchItemResult";
+
+export type EvictionProgress = {
+    total: number,
+    trashed: number,
+    // moves queued while the node was unreachable, not in trash yet
+    queued: number,
+    failed: BatchItemResult[],
+    finished: boolean,
+}
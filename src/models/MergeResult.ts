@@ -0,0 +1,9 @@
+import {BatchItemResult} from "@/models/BatchItemResult";
+
+export type MergeResult = {
+    // empty when the merge is queued
+    document_id: string,
+    trashed_sources: BatchItemResult[],
+    // the sources were asked to be deleted but are kept as the merge is queued
+    sources_kept: boolean,
+}
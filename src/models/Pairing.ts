@@ -0,0 +1,19 @@
+export type PairingRequest = {
+    device_id: string,
+    device_name: string,
+    code: string,
+}
+
+export type PairingResult = {
+    device_id: string,
+    space_id: string,
+    paired: boolean,
+    message: string,
+}
+
+export type PairedDevice = {
+    device_id: string,
+    name: string,
+    paired_at: number,
+    spaces: string[],
+}
@@ -0,0 +1,12 @@
+export type PasteQueueMode = "fifo" | "lifo";
+
+export type QueueItemPreview = {
+    content_preview?: string,
+    data_type: string,
+}
+
+export type PasteQueueState = {
+    active: boolean,
+    mode: PasteQueueMode,
+    items: QueueItemPreview[],
+}
@@ -15,3 +15,11 @@ export type Record = {
     latest_addr: string,
     is_deleted: number,
 }
+
+export type RecordVersion = {
+    view_id: string,
+    content: string,
+    content_preview?: string,
+    content_hash: string,
+    edit_time: number,
+}
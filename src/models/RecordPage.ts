@@ -0,0 +1,52 @@
+export type RecordItem = {
+    view_id: string,
+    document_id: string,
+    // public key of the device which created the record
+    owner: string,
+    content_preview?: string,
+    // data_type(文本=text、图片=image)
+    data_type: string,
+    md5: string,
+    create_time: number,
+    is_favorite: boolean,
+    tags: string[],
+    latest_addr: string,
+    delete_time?: number,
+    source_app: string,
+    mime_type: string,
+    byte_size: number,
+    subtype: string,
+    // 0 means the record never expires
+    expires_at: number,
+    sync: boolean,
+}
+
+export type RecordContent = {
+    view_id: string,
+    data_type: string,
+    content: string,
+}
+
+export type RecordFilter = {
+    data_types?: string[],
+    favorite?: boolean,
+    tags?: string[],
+    device?: string,
+    created_after?: number,
+    created_before?: number,
+    keyword?: string,
+    in_trash?: boolean,
+    // only the synced or the local-only records
+    sync?: boolean,
+}
+
+export type RecordSort = {
+    field: "create_time" | "delete_time",
+    direction: "asc" | "desc",
+}
+
+export type RecordPage = {
+    items: RecordItem[],
+    next_cursor?: string,
+    total_count: number,
+}
@@ -0,0 +1,9 @@
+export type RegisterEntry = {
+    view_id: string,
+    name: string,
+    content: string,
+    content_preview?: string,
+    data_type: string,
+    md5: string,
+    create_time: number,
+}
@@ -0,0 +1,14 @@
+export type Snippet = {
+    view_id: string,
+    document_id: string,
+    name: string,
+    content: string,
+    prompts: string[],
+    create_time: number,
+    update_time: number,
+}
+
+export type RenderedSnippet = {
+    text: string,
+    cursor?: number,
+}
@@ -0,0 +1,17 @@
+export type SpaceSchemas = {
+    record: string,
+    record_version: string,
+}
+
+export type Space = {
+    id: string,
+    name: string,
+    created_at: number,
+    schemas: SpaceSchemas | null,
+    replaced_schemas: SpaceSchemas[],
+}
+
+export type SpaceList = {
+    active: string,
+    spaces: Space[],
+}
@@ -0,0 +1,13 @@
+export type UsageStat = {
+    bytes: number,
+    count: number,
+}
+
+export type StorageUsage = {
+    total: UsageStat,
+    trash: UsageStat,
+    by_type: { [dataType: string]: UsageStat },
+    by_device: { [owner: string]: UsageStat },
+    quota_bytes: number,
+    sqlite_file_bytes: number,
+}
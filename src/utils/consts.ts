@@ -1,26 +1,59 @@
-import {invoke} from "@tauri-apps/api";
-
 export enum CommandEnum {
     GraphqlEndpointCommand = "graphql_endpoint",
     LoadAppConfigCommand = "load_app_config",
     SaveAppConfigCommand = "save_app_config",
     TapChangeClipboardCommand = "tap_change_clipboard",
+    ListRecordsCommand = "list_records",
+    GetRecordContentCommand = "get_record_content",
     DeleteRecordCommand = "delete_record",
+    RestoreRecordCommand = "restore_record",
+    EmptyTrashCommand = "empty_trash",
     ToggleFavoriteRecordCommand = "toggle_favorite_record",
+    EditRecordCommand = "edit_record",
+    RecordHistoryCommand = "record_history",
+    RevertRecordCommand = "revert_record",
+    BatchDeleteCommand = "batch_delete",
+    BatchSetFavoriteCommand = "batch_set_favorite",
+    SetRecordSyncCommand = "set_record_sync",
+    BatchTagCommand = "batch_tag",
+    MergeRecordsCommand = "merge_records",
+    StorageUsageCommand = "storage_usage",
+    StartPasteQueueCommand = "start_paste_queue",
+    StopPasteQueueCommand = "stop_paste_queue",
+    PasteQueueStateCommand = "paste_queue_state",
+    PasteNextCommand = "paste_next",
+    ListSnippetsCommand = "list_snippets",
+    CreateSnippetCommand = "create_snippet",
+    UpdateSnippetCommand = "update_snippet",
+    DeleteSnippetCommand = "delete_snippet",
+    RenderSnippetCommand = "render_snippet",
+    ListRegistersCommand = "list_registers",
+    RegisterHistoryCommand = "register_history",
+    CopyToRegisterCommand = "copy_to_register",
+    PasteFromRegisterCommand = "paste_from_register",
+    ClearRegisterCommand = "clear_register",
+    StartPairingCommand = "start_pairing",
+    JoinPairingCommand = "join_pairing",
+    ConfirmPairingCommand = "confirm_pairing",
+    CancelPairingCommand = "cancel_pairing",
+    ListPairedDevicesCommand = "list_paired_devices",
+    RenamePairedDeviceCommand = "rename_paired_device",
+    RevokePairedDeviceCommand = "revoke_paired_device",
+    ListSpacesCommand = "list_spaces",
+    CreateSpaceCommand = "create_space",
+    RenameSpaceCommand = "rename_space",
+    DeleteSpaceCommand = "delete_space",
+    SwitchSpaceCommand = "switch_space",
+    RemoveSpaceMemberCommand = "remove_space_member",
 }
 
 export enum EventListenerEnum {
     ChangeClipboardBackend = "cbs://change-clipboard-backend",
     UpdateClipboardRecordBackend = "cbs://update-clipboard-record-backend",
     DeleteClipboardRecordBackend = "cbs://delete-clipboard-record-backend",
+    PasteQueueChangeBackend = "cbs://paste-queue-change-backend",
+    RegisterChangeBackend = "cbs://register-change-backend",
+    EvictionProgressBackend = "cbs://eviction-progress-backend",
+    PairingRequestBackend = "cbs://pairing-request-backend",
+    PairingResultBackend = "cbs://pairing-result-backend",
 }
-
-export const DefaultGraphqlEndpoint = "http://localhost:12020/graphql";
-
-export let GraphqlEndpoint: string;
-
-(async () => {
-    GraphqlEndpoint = await invoke(CommandEnum.GraphqlEndpointCommand) as string; // 使用 async/await 来初始化全局变量
-    console.log(`Get graphql endpoint: ${GraphqlEndpoint}`); // 确保变量已经被正确初始化
-    return GraphqlEndpoint;
-})();
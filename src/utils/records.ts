@@ -0,0 +1,52 @@
+import {invoke} from "@tauri-apps/api";
+import {CommandEnum} from "@/utils/consts";
+import {RecordDataTypeEnum} from "@/models/Record";
+import {RecordContent, RecordFilter, RecordPage, RecordSort} from "@/models/RecordPage";
+
+export async function listRecords(filter: RecordFilter, limit: number = 20, cursor?: string, sort?: RecordSort): Promise<RecordPage> {
+    try {
+        return await invoke(CommandEnum.ListRecordsCommand, {
+            filter: filter,
+            sort: sort,
+            limit: limit,
+            cursor: cursor && cursor.length > 0 ? cursor : undefined,
+        }) as RecordPage;
+    } catch (error) {
+        console.error(`Error: list records failed, error: ${error}`);
+        return Promise.reject(error);
+    }
+}
+
+export async function allFavoriteRecords(): Promise<RecordPage> {
+    let page = await listRecords({favorite: true}, 100);
+    const items = page.items;
+    while (page.next_cursor) {
+        page = await listRecords({favorite: true}, 100, page.next_cursor);
+        items.push(...page.items);
+    }
+    return {...page, items: items};
+}
+
+export async function getRecordByPage(limit: number = 20, startCursor?: string, searchKeyword?: string): Promise<RecordPage> {
+    if (!searchKeyword || searchKeyword === "") {
+        return listRecords({favorite: false}, limit, startCursor);
+    }
+    return listRecords({
+        favorite: false,
+        keyword: searchKeyword,
+        data_types: [RecordDataTypeEnum.Text],
+    }, limit, startCursor);
+}
+
+export async function getTrashRecordByPage(limit: number = 20, startCursor?: string): Promise<RecordPage> {
+    return listRecords({in_trash: true}, limit, startCursor, {field: "delete_time", direction: "desc"});
+}
+
+export async function getRecordContent(viewId: string): Promise<RecordContent> {
+    try {
+        return await invoke(CommandEnum.GetRecordContentCommand, {viewId: viewId}) as RecordContent;
+    } catch (error) {
+        console.error(`Error: load record content failed, error: ${error}`);
+        return Promise.reject(error);
+    }
+}
@@ -1,10 +1,11 @@
 import React, {useEffect} from "react";
-import {Button, Form, Input, InputNumber, message} from "antd";
+import {Button, Form, Input, InputNumber, message, Select, Space} from "antd";
 import "@/css/AppSetting.css";
 import {useNavigate} from 'react-router-dom';
 import {AppConfig} from "@/models/AppConfig";
 import {invoke} from "@tauri-apps/api";
 import {CommandEnum} from "@/utils/consts";
+import {RecordDataTypeEnum} from "@/models/Record";
 
 export default function AppSetting() {
 
@@ -73,6 +74,44 @@ export default function AppSetting() {
                 >
                     <InputNumber/>
                 </Form.Item>
+                <Form.Item<AppConfig>
+                    label="回收站保留天数"
+                    name="trash_retention_days"
+                    rules={[{type: "number", required: true, min: 1, max: 3650}]}
+                >
+                    <InputNumber/>
+                </Form.Item>
+                <Form.Item<AppConfig>
+                    label="最大储存空间（MB）"
+                    name="storage_quota_mb"
+                    rules={[{type: "number", required: true, min: 1, max: 1048576}]}
+                >
+                    <InputNumber/>
+                </Form.Item>
+                <Form.Item<AppConfig> label="按类型自动过期（天，收藏除外）">
+                    <Form.List name="retention_rules">
+                        {(fields, {add, remove}) => (
+                            <>
+                                {fields.map(({key, name}) => (
+                                    <Space key={key} align="baseline">
+                                        <Form.Item name={[name, "data_type"]} rules={[{required: true}]}>
+                                            <Select style={{width: 100}} options={[
+                                                {value: RecordDataTypeEnum.Text, label: "文本"},
+                                                {value: RecordDataTypeEnum.Image, label: "图片"},
+                                            ]}/>
+                                        </Form.Item>
+                                        <Form.Item name={[name, "max_age_days"]}
+                                                   rules={[{type: "number", required: true, min: 1, max: 3650}]}>
+                                            <InputNumber/>
+                                        </Form.Item>
+                                        <Button type={"text"} onClick={() => remove(name)}>×</Button>
+                                    </Space>
+                                ))}
+                                <Button type={"dashed"} onClick={() => add()}>添加规则</Button>
+                            </>
+                        )}
+                    </Form.List>
+                </Form.Item>
                 <Form.Item<AppConfig>
                     label="数据同步端口号（重启后生效）"
                     name="sync_port"
@@ -94,6 +133,33 @@ export default function AppSetting() {
                 >
                     <Input disabled={true}/>
                 </Form.Item>
+                <Form.Item<AppConfig> label="寄存器快捷键（重启后生效）">
+                    <Form.List name="register_hotkeys">
+                        {(fields, {add, remove}) => (
+                            <>
+                                {fields.map(({key, name}) => (
+                                    <Space key={key} align="baseline">
+                                        <Form.Item name={[name, "register"]}
+                                                   rules={[{required: true, pattern: /^[a-z]$/}]}>
+                                            <Input style={{width: 50}} maxLength={1}/>
+                                        </Form.Item>
+                                        <Form.Item name={[name, "action"]} rules={[{required: true}]}>
+                                            <Select style={{width: 100}} options={[
+                                                {value: "copy", label: "存入"},
+                                                {value: "paste", label: "粘贴"},
+                                            ]}/>
+                                        </Form.Item>
+                                        <Form.Item name={[name, "hotkey"]} rules={[{required: true}]}>
+                                            <Input placeholder={"CommandOrControl+Alt+a"}/>
+                                        </Form.Item>
+                                        <Button type={"text"} onClick={() => remove(name)}>×</Button>
+                                    </Space>
+                                ))}
+                                <Button type={"dashed"} onClick={() => add()}>添加快捷键</Button>
+                            </>
+                        )}
+                    </Form.List>
+                </Form.Item>
 
                 <Form.Item wrapperCol={{offset: 8, span: 16}}>
                     <Button htmlType="submit"